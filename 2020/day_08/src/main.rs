@@ -1,80 +1,274 @@
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
-use std::collections::HashSet;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum Operation {
-    Acc,
-    Jmp,
-    Nop,
+// The console's registers, every opcode can change the accumulator
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct Registers {
+    acc: i64,
 }
 
+// One opcode of an instruction set
+//
+// Control flow only depends on the argument, so the control flow graph of a
+// program is known without running it. `swap` is the opcode this one may have
+// been corrupted from.
 #[derive(Copy, Clone, Debug)]
+struct Opcode {
+    name: &'static str,
+    execute: fn(&mut Registers, i64),
+    offset: fn(i64) -> i64,
+    swap: Option<&'static str>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct InstructionSet {
+    opcodes: Vec<Opcode>,
+}
+
+impl InstructionSet {
+    fn with(mut self, opcode: Opcode) -> Self {
+        self.opcodes.retain(|o| o.name != opcode.name);
+        self.opcodes.push(opcode);
+        self
+    }
+
+    // acc increases the accumulator, jmp jumps relative to itself and nop does nothing
+    fn handheld() -> Self {
+        Self::default()
+            .with(Opcode {
+                name: "acc",
+                execute: |r, arg| r.acc += arg,
+                offset: |_| 1,
+                swap: None,
+            })
+            .with(Opcode {
+                name: "jmp",
+                execute: |_, _| (),
+                offset: |arg| arg,
+                swap: Some("nop"),
+            })
+            .with(Opcode {
+                name: "nop",
+                execute: |_, _| (),
+                offset: |_| 1,
+                swap: Some("jmp"),
+            })
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.opcodes.iter().position(|o| o.name == name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 struct Instruction {
-    op: Operation,
-    arg: i32,
+    op: usize,
+    arg: i64,
 }
 
-fn get_program(data: &[String]) -> Vec<Instruction> {
+fn get_program(data: &[String], set: &InstructionSet) -> Result<Vec<Instruction>, Box<dyn Error>> {
     let mut prog = vec![];
-
     for line in data {
-        let tokens = line.split_whitespace().collect::<Vec<_>>();
-        let op = match tokens[0] {
-            "acc" => Operation::Acc,
-            "jmp" => Operation::Jmp,
-            "nop" => Operation::Nop,
-            _ => unreachable!(),
-        };
-        let arg = tokens[1].parse::<i32>().expect("failed parse");
-        prog.push(Instruction { op, arg });
-    }
-    prog
-}
-
-fn solution1(prog: &[Instruction]) -> (i32, usize) {
-    let mut executed = HashSet::new();
-    let mut inst_ptr = 0;
-    let mut accumulator = 0;
-
-    while !executed.contains(&inst_ptr) {
-        executed.insert(inst_ptr);
-        match prog[inst_ptr].op {
-            Operation::Acc => {
-                accumulator += prog[inst_ptr].arg;
-                inst_ptr += 1;
+        let (name, arg) = line
+            .split_once(' ')
+            .ok_or_else(|| format!("bad instruction {line:?}"))?;
+        let op = set.find(name).ok_or_else(|| format!("unknown opcode {name:?}"))?;
+        prog.push(Instruction {
+            op,
+            arg: arg.trim().parse()?,
+        });
+    }
+    Ok(prog)
+}
+
+// why a run stopped, with the instruction pointer at that moment
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Halt {
+    // ran off the end of the program by exactly one instruction
+    Terminated,
+    // about to execute an instruction for the second time
+    Loop(i64),
+    // jumped anywhere else outside the program
+    OutOfBounds(i64),
+}
+
+// one executed instruction and the accumulator after it
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Step {
+    pc: i64,
+    op: &'static str,
+    arg: i64,
+    acc: i64,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:4}: {} {:+}  acc {}", self.pc, self.op, self.arg, self.acc)
+    }
+}
+
+struct Vm<'a> {
+    set: &'a InstructionSet,
+    program: &'a [Instruction],
+    registers: Registers,
+    trace: Option<Vec<Step>>,
+}
+
+impl<'a> Vm<'a> {
+    fn new(set: &'a InstructionSet, program: &'a [Instruction]) -> Self {
+        Self {
+            set,
+            program,
+            registers: Registers::default(),
+            trace: None,
+        }
+    }
+
+    // record every executed instruction
+    fn with_trace(mut self) -> Self {
+        self.trace = Some(vec![]);
+        self
+    }
+
+    // run from the first instruction until the program ends or repeats an instruction
+    fn run(&mut self) -> Halt {
+        let mut executed = vec![false; self.program.len()];
+        let mut pc = 0_i64;
+        loop {
+            if pc == self.program.len() as i64 {
+                return Halt::Terminated;
             }
-            Operation::Jmp => {
-                inst_ptr = (prog[inst_ptr].arg + inst_ptr as i32) as usize;
+            if pc < 0 || pc > self.program.len() as i64 {
+                return Halt::OutOfBounds(pc);
             }
-            Operation::Nop => {
-                inst_ptr += 1;
+            if executed[pc as usize] {
+                return Halt::Loop(pc);
             }
-        }
+            executed[pc as usize] = true;
 
-        if inst_ptr == prog.len() {
-            break; // prog end
+            let inst = self.program[pc as usize];
+            let opcode = &self.set.opcodes[inst.op];
+            (opcode.execute)(&mut self.registers, inst.arg);
+            if let Some(trace) = &mut self.trace {
+                trace.push(Step {
+                    pc,
+                    op: opcode.name,
+                    arg: inst.arg,
+                    acc: self.registers.acc,
+                });
+            }
+            pc += (opcode.offset)(inst.arg);
         }
     }
-    (accumulator, inst_ptr)
 }
 
-fn solution2(prog: &[Instruction]) -> i32 {
-    for (i, stmt) in prog.iter().enumerate() {
-        if stmt.op == Operation::Jmp || stmt.op == Operation::Nop {
-            let mut d = prog.to_vec();
-            d[i].op = match d[i].op {
-                Operation::Nop => Operation::Jmp,
-                _ => Operation::Nop,
-            };
-            let (accumulator, inst_ptr) = solution1(&d);
-            if inst_ptr == prog.len() {
-                return accumulator;
+// the next instruction after each instruction, program.len() is the exit and None is out of bounds
+fn control_flow(program: &[Instruction], set: &InstructionSet) -> Vec<Option<usize>> {
+    program
+        .iter()
+        .enumerate()
+        .map(|(i, inst)| next(i, inst.arg, &set.opcodes[inst.op], program.len()))
+        .collect()
+}
+
+fn next(i: usize, arg: i64, opcode: &Opcode, len: usize) -> Option<usize> {
+    let n = i as i64 + (opcode.offset)(arg);
+    match n >= 0 && n <= len as i64 {
+        true => Some(n as usize),
+        false => None,
+    }
+}
+
+// The single swapped instruction that makes the program terminate
+//
+// Instructions which reach the exit are found by walking the control flow graph
+// backwards from it. The first instruction on the original path whose swapped
+// successor is one of those is the fix, linear in the program length.
+fn repair(program: &[Instruction], set: &InstructionSet) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let n = program.len();
+    let cfg = control_flow(program, set);
+
+    let mut predecessors = vec![vec![]; n + 1];
+    for (i, succ) in cfg.iter().enumerate() {
+        if let Some(s) = succ {
+            predecessors[*s].push(i);
+        }
+    }
+    let mut reaches_exit = vec![false; n + 1];
+    reaches_exit[n] = true;
+    let mut workq = VecDeque::from([n]);
+    while let Some(i) = workq.pop_front() {
+        for p in &predecessors[i] {
+            if !reaches_exit[*p] {
+                reaches_exit[*p] = true;
+                workq.push_back(*p);
+            }
+        }
+    }
+
+    if reaches_exit[0] {
+        return Ok(program.to_vec());
+    }
+
+    // follow the original program until an instruction can be swapped onto an exiting path
+    let mut visited = vec![false; n];
+    let mut pc = 0;
+    while pc < n && !visited[pc] {
+        visited[pc] = true;
+        let inst = program[pc];
+        if let Some(op) = set.opcodes[inst.op].swap.and_then(|name| set.find(name)) {
+            if next(pc, inst.arg, &set.opcodes[op], n).is_some_and(|s| reaches_exit[s]) {
+                let mut fixed = program.to_vec();
+                fixed[pc].op = op;
+                return Ok(fixed);
             }
         }
+        pc = cfg[pc].ok_or("the program jumps out of bounds")?;
+    }
+    Err("no single swapped instruction terminates the program".into())
+}
+
+// run with the executed instructions collected into `steps`, if given
+fn run<'a>(set: &'a InstructionSet, prog: &'a [Instruction], steps: Option<&mut Vec<String>>) -> (Vm<'a>, Halt) {
+    let mut vm = Vm::new(set, prog);
+    if steps.is_some() {
+        vm = vm.with_trace();
+    }
+    let halt = vm.run();
+    if let (Some(steps), Some(trace)) = (steps, &vm.trace) {
+        steps.extend(trace.iter().map(|step| step.to_string()));
+    }
+    (vm, halt)
+}
+
+// the accumulator just before any instruction is executed a second time
+fn solution1(
+    prog: &[Instruction],
+    set: &InstructionSet,
+    steps: Option<&mut Vec<String>>,
+) -> Result<i64, Box<dyn Error>> {
+    let (vm, halt) = run(set, prog, steps);
+    match halt {
+        Halt::Loop(_) => Ok(vm.registers.acc),
+        halt => Err(format!("expected a loop, the program stopped with {halt:?}").into()),
+    }
+}
+
+// the accumulator after the repaired program terminates
+fn solution2(
+    prog: &[Instruction],
+    set: &InstructionSet,
+    steps: Option<&mut Vec<String>>,
+) -> Result<i64, Box<dyn Error>> {
+    let fixed = repair(prog, set)?;
+    let (vm, halt) = run(set, &fixed, steps);
+    match halt {
+        Halt::Terminated => Ok(vm.registers.acc),
+        halt => Err(format!("repair failed, the program stopped with {halt:?}").into()),
     }
-    panic!("no solution")
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -93,9 +287,20 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let prog = get_program(&puzzle_lines);
-    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&prog))?;
-    writeln!(stdout, "Answer Part 2 = {}", solution2(&prog))?;
+    let set = InstructionSet::handheld();
+    let prog = get_program(&puzzle_lines, &set)?;
+    let verbose = args.get_flag("verbose");
+    let mut steps = vec![];
+    let answer = solution1(&prog, &set, verbose.then_some(&mut steps))?;
+    for line in steps.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 1 = {answer}")?;
+    let answer = solution2(&prog, &set, verbose.then_some(&mut steps))?;
+    for line in steps {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {answer}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -115,28 +320,88 @@ mod tests {
     #[test]
     fn part1_example() {
         let data = get_data("input-example");
-        let prog = get_program(&data);
-        assert_eq!(5, solution1(&prog).0);
+        let set = InstructionSet::handheld();
+        let prog = get_program(&data, &set).unwrap();
+        assert_eq!(5, solution1(&prog, &set, None).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let data = get_data("input-actual");
-        let prog = get_program(&data);
-        assert_eq!(2025, solution1(&prog).0);
+        let set = InstructionSet::handheld();
+        let prog = get_program(&data, &set).unwrap();
+        assert_eq!(2025, solution1(&prog, &set, None).unwrap());
     }
 
     #[test]
     fn part2_example() {
         let data = get_data("input-example");
-        let prog = get_program(&data);
-        assert_eq!(8, solution2(&prog));
+        let set = InstructionSet::handheld();
+        let prog = get_program(&data, &set).unwrap();
+        assert_eq!(8, solution2(&prog, &set, None).unwrap());
     }
 
     #[test]
     fn part2_actual() {
         let data = get_data("input-actual");
-        let prog = get_program(&data);
-        assert_eq!(2001, solution2(&prog));
+        let set = InstructionSet::handheld();
+        let prog = get_program(&data, &set).unwrap();
+        assert_eq!(2001, solution2(&prog, &set, None).unwrap());
+    }
+
+    #[test]
+    fn trace() {
+        let set = InstructionSet::handheld();
+        let prog = get_program(&get_data("input-example"), &set).unwrap();
+        let mut vm = Vm::new(&set, &prog).with_trace();
+        assert_eq!(vm.run(), Halt::Loop(1));
+        let trace = vm.trace.unwrap();
+        let pcs = trace.iter().map(|s| s.pc).collect::<Vec<_>>();
+        assert_eq!(pcs, vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(
+            trace[6],
+            Step {
+                pc: 4,
+                op: "jmp",
+                arg: -3,
+                acc: 5
+            }
+        );
+
+        let mut steps = vec![];
+        assert_eq!(8, solution2(&prog, &set, Some(&mut steps)).unwrap());
+        assert_eq!(steps.first().unwrap(), "   0: nop +0  acc 0");
+        assert_eq!(steps.last().unwrap(), "   8: acc +6  acc 8");
+    }
+
+    #[test]
+    fn custom_opcode() {
+        // "mul" scales the accumulator, "hop" skips the next instruction unless swapped with "nop"
+        let set = InstructionSet::handheld()
+            .with(Opcode {
+                name: "mul",
+                execute: |r, arg| r.acc *= arg,
+                offset: |_| 1,
+                swap: None,
+            })
+            .with(Opcode {
+                name: "hop",
+                execute: |_, _| (),
+                offset: |_| 2,
+                swap: Some("nop"),
+            });
+        let lines = ["acc +3", "jmp +2", "hop +0", "mul +7", "jmp -3"].map(String::from);
+        let prog = get_program(&lines, &set).unwrap();
+        assert_eq!(
+            control_flow(&prog, &set),
+            vec![Some(1), Some(3), Some(4), Some(4), Some(1)]
+        );
+        assert_eq!(solution1(&prog, &set, None).unwrap(), 21);
+        assert_eq!(solution2(&prog, &set, None).unwrap(), 21);
+
+        // a program that already terminates needs no repair
+        let prog = get_program(&["hop +0", "jmp -1", "acc +1"].map(String::from), &set).unwrap();
+        assert_eq!(repair(&prog, &set).unwrap(), prog);
+        assert!(get_program(&["sub +1".to_string()], &set).is_err());
     }
 }
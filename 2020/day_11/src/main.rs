@@ -1,3 +1,4 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use std::error::Error;
 use std::io::{self, Write};
@@ -64,26 +65,24 @@ fn occupied_sight(seats: &Array2<Option<bool>>, i: usize, j: usize) -> usize {
     cnt
 }
 
-#[allow(dead_code)]
-fn display(seats: &Array2<Option<bool>>) {
-    for i in 0..seats.nrows() {
-        for j in 0..seats.ncols() {
-            match seats[[i, j]] {
-                Some(true) => print!("#"),
-                Some(false) => print!("L"),
-                None => print!("."),
-            }
-        }
-        println!();
-    }
-    println!()
+fn display(anim: &mut Animation, title: &str, seats: &Array2<Option<bool>>) -> io::Result<()> {
+    anim.draw(
+        title,
+        seats.rows().into_iter().map(|row| {
+            row.into_iter().map(|seat| match seat {
+                Some(true) => '#',
+                Some(false) => 'L',
+                None => '.',
+            })
+        }),
+    )
 }
 
-fn solution1(seats: &Array2<Option<bool>>) -> usize {
+fn solution1(seats: &Array2<Option<bool>>, anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let mut updated = seats.clone();
     loop {
         let prev = updated.clone();
-        //display(&prev);
+        display(anim, "Part 1", &prev)?;
         for i in 0..seats.nrows() {
             for j in 0..seats.ncols() {
                 if prev[[i, j]].is_none() {
@@ -106,16 +105,16 @@ fn solution1(seats: &Array2<Option<bool>>) -> usize {
             }
         }
         if updated == prev {
-            return prev.into_iter().filter(|e| *e == Some(true)).count();
+            return Ok(prev.into_iter().filter(|e| *e == Some(true)).count());
         }
     }
 }
 
-fn solution2(seats: &Array2<Option<bool>>) -> usize {
+fn solution2(seats: &Array2<Option<bool>>, anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let mut updated = seats.clone();
     loop {
         let prev = updated.clone();
-        //display(&prev);
+        display(anim, "Part 2", &prev)?;
         for i in 0..seats.nrows() {
             for j in 0..seats.ncols() {
                 if prev[[i, j]].is_none() {
@@ -138,7 +137,7 @@ fn solution2(seats: &Array2<Option<bool>>) -> usize {
             }
         }
         if updated == prev {
-            return prev.into_iter().filter(|e| *e == Some(true)).count();
+            return Ok(prev.into_iter().filter(|e| *e == Some(true)).count());
         }
     }
 }
@@ -160,8 +159,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // ==============================================================
 
     let seats = get_seats(&puzzle_lines);
-    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&seats))?;
-    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&seats))?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('#', Color::BrightRed)
+        .with_color('L', Color::Green)
+        .with_color('.', Color::Gray);
+    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&seats, &mut anim)?)?;
+    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&seats, &mut anim)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -182,27 +185,27 @@ mod tests {
     fn part1_example() {
         let data = get_data("input-example");
         let seats = get_seats(&data);
-        assert_eq!(37, solution1(&seats));
+        assert_eq!(37, solution1(&seats, &mut Animation::default()).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let data = get_data("input-actual");
         let seats = get_seats(&data);
-        assert_eq!(2321, solution1(&seats));
+        assert_eq!(2321, solution1(&seats, &mut Animation::default()).unwrap());
     }
 
     #[test]
     fn part2_example() {
         let data = get_data("input-example");
         let seats = get_seats(&data);
-        assert_eq!(26, solution2(&seats));
+        assert_eq!(26, solution2(&seats, &mut Animation::default()).unwrap());
     }
 
     #[test]
     fn part2_actual() {
         let data = get_data("input-actual");
         let seats = get_seats(&data);
-        assert_eq!(2102, solution2(&seats));
+        assert_eq!(2102, solution2(&seats, &mut Animation::default()).unwrap());
     }
 }
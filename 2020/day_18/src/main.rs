@@ -2,121 +2,238 @@ use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use std::error::Error;
 use std::io::{self, Write};
 
-#[derive(PartialEq)]
-enum Operator {
-    Add,
-    Mul,
-}
-
-fn expr(e: &str) -> Option<(usize, i64)> {
-    let mut operand: Option<(usize, i64)> = None;
-    let mut operator = None;
-    let tokens = e.chars().collect::<Vec<char>>();
-
-    let mut i = 0;
-    while i < tokens.len() {
-        match tokens[i] {
-            '(' => match expr(&e[i + 1..]) {
-                Some(result) => {
-                    i += result.0;
-                    operand = match operator {
-                        Some(Operator::Add) => Some((i, operand.unwrap().1 + result.1)),
-                        Some(Operator::Mul) => Some((i, operand.unwrap().1 * result.1)),
-                        None => Some((i, result.1)),
-                    }
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Op(char),
+    LParen,
+    RParen,
+}
+
+fn tokenize(e: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+    let mut tokens = vec![];
+    let mut chars = e.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            '+' | '-' | '*' | '/' => tokens.push(Token::Op(c)),
+            c if c.is_ascii_digit() => {
+                // collect multi-digit literals
+                let mut n = c.to_digit(10).unwrap() as i64;
+                while let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) {
+                    n = n
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(d as i64))
+                        .ok_or("literal overflow")?;
+                    chars.next();
                 }
-                None => unreachable!(),
-            },
-            ')' => {
-                return Some((i + 1, operand.unwrap().1));
-            }
-            '+' => {
-                operator = Some(Operator::Add);
+                tokens.push(Token::Num(n));
             }
-            '*' => {
-                operator = Some(Operator::Mul);
-            }
-            c if "0123456789".contains(c) => {
-                let n = "0123456789".find(c).unwrap() as i64;
-                operand = match operator {
-                    Some(Operator::Add) => Some((i, operand.unwrap().1 + n)),
-                    Some(Operator::Mul) => Some((i, operand.unwrap().1 * n)),
-                    None => Some((i, n)),
+            c if c.is_whitespace() => {}
+            _ => return Err(format!("unexpected character {c:?} in {e:?}").into()),
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Assoc {
+    Left,
+    // only the tests build tables with right associative operators
+    #[cfg(test)]
+    Right,
+}
+
+// Binary operator precedence (higher binds tighter) and associativity
+//
+// Unary minus always binds tighter than any binary operator.
+#[derive(Debug, Clone)]
+struct Precedence {
+    binary: Vec<(char, u8, Assoc)>,
+}
+
+impl Precedence {
+    // part 1, everything evaluated left to right
+    fn equal() -> Self {
+        Self {
+            binary: vec![
+                ('+', 1, Assoc::Left),
+                ('-', 1, Assoc::Left),
+                ('*', 1, Assoc::Left),
+                ('/', 1, Assoc::Left),
+            ],
+        }
+    }
+
+    // part 2, addition and subtraction before multiplication and division
+    fn addition_first() -> Self {
+        Self {
+            binary: vec![
+                ('+', 2, Assoc::Left),
+                ('-', 2, Assoc::Left),
+                ('*', 1, Assoc::Left),
+                ('/', 1, Assoc::Left),
+            ],
+        }
+    }
+
+    // the usual school arithmetic
+    #[cfg(test)]
+    fn conventional() -> Self {
+        Self {
+            binary: vec![
+                ('+', 1, Assoc::Left),
+                ('-', 1, Assoc::Left),
+                ('*', 2, Assoc::Left),
+                ('/', 2, Assoc::Left),
+            ],
+        }
+    }
+
+    // left and right binding power of a binary operator
+    fn binding_power(&self, op: char) -> Option<(u8, u8)> {
+        self.binary.iter().find(|b| b.0 == op).map(|(_, p, assoc)| match assoc {
+            Assoc::Left => (2 * p, 2 * p + 1),
+            #[cfg(test)]
+            Assoc::Right => (2 * p + 1, 2 * p),
+        })
+    }
+
+    // unary minus binds tighter than every binary operator
+    fn prefix_power(&self) -> u8 {
+        2 * self.binary.iter().map(|b| b.1).max().unwrap_or(0) + 2
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(i64),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self) -> Result<i64, Box<dyn Error>> {
+        let value = match self {
+            Expr::Num(n) => Some(*n),
+            Expr::Neg(e) => e.eval()?.checked_neg(),
+            Expr::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval()?, rhs.eval()?);
+                match op {
+                    '+' => a.checked_add(b),
+                    '-' => a.checked_sub(b),
+                    '*' => a.checked_mul(b),
+                    '/' if b == 0 => return Err(format!("division by zero in {self}").into()),
+                    '/' => a.checked_div(b),
+                    _ => return Err(format!("unknown operator {op:?}").into()),
                 }
             }
-            _ => {}
+        };
+        value.ok_or_else(|| format!("overflow in {self}").into())
+    }
+}
+
+// prints the AST as an s-expression, e.g. "1 + 2 * 3" with equal precedence is (* (+ 1 2) 3)
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{n}"),
+            Expr::Neg(e) => write!(f, "(- {e})"),
+            Expr::Binary(op, lhs, rhs) => write!(f, "({op} {lhs} {rhs})"),
         }
-        i += 1;
     }
-    operand
 }
 
-fn expr2(e: &str) -> (usize, i64) {
-    let mut operator = vec![];
-    let mut operand = vec![];
+// Pratt parser over the token stream
+// https://matklad.github.io/2020/04/13/simple-but-powerful-pratt-parsing.html
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    precedence: &'a Precedence,
+}
 
-    let tokens = e.chars().collect::<Vec<char>>();
+impl<'a> Parser<'a> {
+    fn parse(tokens: &'a [Token], precedence: &'a Precedence) -> Result<Expr, Box<dyn Error>> {
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            precedence,
+        };
+        let expr = parser.expr(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(t) => Err(format!("unexpected {t:?} at token {}", parser.pos).into()),
+        }
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.peek();
+        self.pos += 1;
+        t
+    }
 
-    let mut i = 0;
-    while i < tokens.len() {
-        match tokens[i] {
-            '(' => {
-                let result = expr2(&e[i + 1..]);
-                i += result.0;
-                if operator.is_empty() || operator[operator.len() - 1] == Operator::Mul {
-                    operand.push(result.1);
-                } else {
-                    let a = operand.pop().unwrap();
-                    operand.push(a + result.1);
-                    operator.pop();
+    fn expr(&mut self, min_power: u8) -> Result<Expr, Box<dyn Error>> {
+        let mut lhs = match self.next() {
+            Some(Token::Num(n)) => Expr::Num(n),
+            Some(Token::Op('-')) => Expr::Neg(Box::new(self.expr(self.precedence.prefix_power())?)),
+            Some(Token::LParen) => {
+                let e = self.expr(0)?;
+                if self.next() != Some(Token::RParen) {
+                    return Err("missing ')'".into());
                 }
+                e
             }
-            ')' => {
-                i += 1;
+            t => return Err(format!("expected an operand, found {t:?}").into()),
+        };
+
+        while let Some(Token::Op(op)) = self.peek() {
+            let (left, right) = self
+                .precedence
+                .binding_power(op)
+                .ok_or_else(|| format!("operator {op:?} has no precedence"))?;
+            if left < min_power {
                 break;
             }
-            '*' => {
-                if !operator.is_empty() && operator[operator.len() - 1] == Operator::Mul && operand.len() > 1 {
-                    let a = operand.pop().unwrap();
-                    let b = operand.pop().unwrap();
-                    operand.push(a * b);
-                } else {
-                    operator.push(Operator::Mul);
-                }
-            }
-            '+' => {
-                operator.push(Operator::Add);
-            }
-            c if "0123456789".contains(c) => {
-                let n = "0123456789".find(c).unwrap() as i64;
-                if operator.is_empty() || operator[operator.len() - 1] == Operator::Mul {
-                    operand.push(n);
-                } else {
-                    let a = operand.pop().unwrap();
-                    operand.push(a + n);
-                    operator.pop();
-                }
-            }
-            _ => {}
+            self.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.expr(right)?));
         }
-        i += 1;
+        Ok(lhs)
     }
-    match operand.len() {
-        0 => panic!("oops, empty operand"),
-        1 => (i, operand[0]),
-        _ => match operator[0] {
-            Operator::Add => (i, operand[0] + operand[1]),
-            Operator::Mul => (i, operand[0] * operand[1]),
-        },
+}
+
+fn parse(e: &str, precedence: &Precedence) -> Result<Expr, Box<dyn Error>> {
+    Parser::parse(&tokenize(e)?, precedence)
+}
+
+// `parsed` collects each expression's AST and value
+fn evaluate(
+    data: &[String],
+    precedence: &Precedence,
+    mut parsed: Option<&mut Vec<String>>,
+) -> Result<i64, Box<dyn Error>> {
+    let mut sum: i64 = 0;
+    for line in data {
+        let e = parse(line, precedence)?;
+        let value = e.eval()?;
+        if let Some(parsed) = parsed.as_mut() {
+            parsed.push(format!("{e} = {value}"));
+        }
+        sum = sum.checked_add(value).ok_or("overflow in the sum")?;
     }
+    Ok(sum)
 }
 
-fn solution1(data: &[String]) -> i64 {
-    data.iter().map(|e| expr(e).unwrap().1).sum()
+fn solution1(data: &[String], parsed: Option<&mut Vec<String>>) -> Result<i64, Box<dyn Error>> {
+    evaluate(data, &Precedence::equal(), parsed)
 }
 
-fn solution2(data: &[String]) -> i64 {
-    data.iter().map(|e| expr2(e).1).sum()
+fn solution2(data: &[String], parsed: Option<&mut Vec<String>>) -> Result<i64, Box<dyn Error>> {
+    evaluate(data, &Precedence::addition_first(), parsed)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -135,8 +252,18 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&puzzle_lines))?;
-    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&puzzle_lines))?;
+    let verbose = args.get_flag("verbose");
+    let mut parsed = vec![];
+    let answer = solution1(&puzzle_lines, verbose.then_some(&mut parsed))?;
+    for line in parsed.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 1 = {answer:?}")?;
+    let answer = solution2(&puzzle_lines, verbose.then_some(&mut parsed))?;
+    for line in parsed {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {answer:?}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -156,29 +283,35 @@ mod tests {
     #[test]
     fn part1_example() {
         let data = get_data("input-example");
-        assert_eq!(71, solution1(&data));
+        assert_eq!(71, solution1(&data, None).unwrap());
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(51, solution1(&["1 + (2 * 3) + (4 * (5 + 6))".to_string()]));
+        assert_eq!(
+            51,
+            solution1(&["1 + (2 * 3) + (4 * (5 + 6))".to_string()], None).unwrap()
+        );
     }
 
     #[test]
     fn part1_example3() {
-        assert_eq!(26, solution1(&["2 * 3 + (4 * 5)".to_string()]));
+        assert_eq!(26, solution1(&["2 * 3 + (4 * 5)".to_string()], None).unwrap());
     }
 
     #[test]
     fn part1_example4() {
-        assert_eq!(437, solution1(&["5 + (8 * 3 + 9 + 3 * 4 * 3)".to_string()]));
+        assert_eq!(
+            437,
+            solution1(&["5 + (8 * 3 + 9 + 3 * 4 * 3)".to_string()], None).unwrap()
+        );
     }
 
     #[test]
     fn part1_example5() {
         assert_eq!(
             12240,
-            solution1(&["5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))".to_string()])
+            solution1(&["5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))".to_string()], None).unwrap()
         )
     }
 
@@ -186,42 +319,48 @@ mod tests {
     fn part1_example6() {
         assert_eq!(
             13632,
-            solution1(&["((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2".to_string()])
+            solution1(&["((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2".to_string()], None).unwrap()
         );
     }
 
     #[test]
     fn part1_actual() {
         let data = get_data("input-actual");
-        assert_eq!(23507031841020, solution1(&data));
+        assert_eq!(23507031841020, solution1(&data, None).unwrap());
     }
 
     #[test]
     fn part2_example() {
         let data = get_data("input-example");
-        assert_eq!(231, solution2(&data));
+        assert_eq!(231, solution2(&data, None).unwrap());
     }
 
     #[test]
     fn part2_example2() {
-        assert_eq!(51, solution2(&["1 + (2 * 3) + (4 * (5 + 6))".to_string()]));
+        assert_eq!(
+            51,
+            solution2(&["1 + (2 * 3) + (4 * (5 + 6))".to_string()], None).unwrap()
+        );
     }
 
     #[test]
     fn part2_example3() {
-        assert_eq!(46, solution2(&["2 * 3 + (4 * 5)".to_string()]));
+        assert_eq!(46, solution2(&["2 * 3 + (4 * 5)".to_string()], None).unwrap());
     }
 
     #[test]
     fn part2_example4() {
-        assert_eq!(1445, solution2(&["5 + (8 * 3 + 9 + 3 * 4 * 3)".to_string()]));
+        assert_eq!(
+            1445,
+            solution2(&["5 + (8 * 3 + 9 + 3 * 4 * 3)".to_string()], None).unwrap()
+        );
     }
 
     #[test]
     fn part2_example5() {
         assert_eq!(
             669060,
-            solution2(&["5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))".to_string()])
+            solution2(&["5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))".to_string()], None).unwrap()
         )
     }
 
@@ -229,13 +368,72 @@ mod tests {
     fn part2_example6() {
         assert_eq!(
             23340,
-            solution2(&["((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2".to_string()])
+            solution2(&["((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2".to_string()], None).unwrap()
         );
     }
 
     #[test]
     fn part2_actual() {
         let data = get_data("input-actual");
-        assert_eq!(218621700997826, solution2(&data));
+        assert_eq!(218621700997826, solution2(&data, None).unwrap());
+    }
+
+    #[test]
+    fn precedence_tables() -> Result<(), Box<dyn Error>> {
+        let e = "10 - 4 / 2 * -3";
+        assert_eq!(parse(e, &Precedence::equal())?.eval()?, -9);
+        assert_eq!(parse(e, &Precedence::addition_first())?.eval()?, -9);
+        assert_eq!(parse(e, &Precedence::conventional())?.eval()?, 16);
+        assert_eq!(parse("2 * 3 + 4", &Precedence::addition_first())?.eval()?, 14);
+        assert_eq!(parse("--12 - -(3 - 45)", &Precedence::conventional())?.eval()?, -30);
+        Ok(())
+    }
+
+    #[test]
+    fn right_associative() -> Result<(), Box<dyn Error>> {
+        let precedence = Precedence {
+            binary: vec![('-', 1, Assoc::Right)],
+        };
+        assert_eq!(parse("10 - 4 - 3", &precedence)?.eval()?, 9);
+        assert_eq!(parse("10 - 4 - 3", &Precedence::equal())?.eval()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_print() -> Result<(), Box<dyn Error>> {
+        let e = "1 + 2 * -(3 + 40)";
+        assert_eq!(parse(e, &Precedence::equal())?.to_string(), "(* (+ 1 2) (- (+ 3 40)))");
+        assert_eq!(
+            parse(e, &Precedence::conventional())?.to_string(),
+            "(+ 1 (* 2 (- (+ 3 40))))"
+        );
+
+        let mut parsed = vec![];
+        assert_eq!(46, solution2(&["2 * 3 + (4 * 5)".to_string()], Some(&mut parsed))?);
+        assert_eq!(parsed, ["(* 2 (+ 3 (* 4 5))) = 46"]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let precedence = Precedence::equal();
+        assert!(parse("1 +", &precedence).is_err());
+        assert!(parse("(1 + 2", &precedence).is_err());
+        assert!(parse("1 + 2)", &precedence).is_err());
+        assert!(parse("1 % 2", &precedence).is_err());
+        assert!(parse("1 / (2 - 2)", &precedence).unwrap().eval().is_err());
+
+        // overflow is an error rather than a panic or a wrapped value
+        let min = "(-9223372036854775807 - 1)";
+        assert_eq!(parse(min, &precedence).unwrap().eval().unwrap(), i64::MIN);
+        for e in [
+            "9223372036854775807 + 1".to_string(),
+            format!("{min} - 1"),
+            "4611686018427387904 * 2".to_string(),
+            format!("{min} / -1"),
+            format!("-{min}"),
+        ] {
+            assert!(parse(&e, &precedence).unwrap().eval().is_err(), "{e}");
+        }
     }
 }
@@ -1,84 +1,340 @@
-use general::{get_args, read_trimmed_data_lines, trim_split_on, reset_sigpipe};
+use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_on};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
-use regex::Regex;
-use std::collections::HashMap;
 
-fn build_re(key: &str, rules: &HashMap<String, Vec<Vec<String>>>) -> String {
-    let mut s1 = String::new();
-    let mut s2 = String::new();
+type Rules = HashMap<String, Vec<Vec<String>>>;
 
-    match rules.get(key) {
-        Some(v) => {
-            for item in &v[0] {
-                s1.push_str(&build_re(item, rules));
+// rules up to the first empty line, the messages after it, literals keep their quotes
+fn get_data(data: &[String]) -> Result<(Rules, Vec<String>), Box<dyn Error>> {
+    let blank = data.iter().position(|s| s.is_empty()).ok_or("missing empty line")?;
+    let mut rules = HashMap::new();
+    for line in &data[..blank] {
+        let parts = trim_split_on::<String>(line, ':')?;
+        let mut groups = vec![];
+        for group in trim_split_on::<String>(&parts[1], '|')? {
+            groups.push(group.split_whitespace().map(String::from).collect());
+        }
+        rules.insert(parts[0].to_string(), groups);
+    }
+    Ok((rules, data[blank + 1..].to_vec()))
+}
+
+// a quoted symbol matches literally, anything else names a rule
+#[derive(Debug, Clone, PartialEq)]
+enum Symbol {
+    Rule(usize),
+    Literal(String),
+}
+
+// an Earley item, alternative `alt` of `rule` with `dot` symbols matched starting at `origin`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+// A parse tree, each node records the rule and which alternative matched
+#[derive(Debug, Clone, PartialEq)]
+enum Tree {
+    Leaf(String),
+    Node(String, usize, Vec<Tree>),
+}
+
+// prints as rule[alternative](children...), e.g. 0[0](4[0](a) 1[1](...) 5[0](b))
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tree::Leaf(s) => write!(f, "{s}"),
+            Tree::Node(name, alt, children) => {
+                write!(f, "{name}[{alt}](")?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{child}")?;
+                }
+                write!(f, ")")
             }
+        }
+    }
+}
+
+// A context free grammar matched with an Earley parser
+//
+// Any recursion is allowed, left, right or nested like "11: 42 31 | 42 11 31".
+// https://en.wikipedia.org/wiki/Earley_parser
+struct Grammar {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    alts: Vec<Vec<Vec<Symbol>>>,
+    nullable: Vec<bool>,
+}
 
-            // v.len() == 2 if this is an alternation:  part1 | part2
-            if v.len() > 1 {
-                // check for a self-reference (cycle)
-                if v[1].contains(&key.to_string()) {
-                    if v[1].len() == 2 {
-                        // example: rule 8 can be simplified to 42+
-                        //   "8: 42 | 42 8"
-                        s1.push('+');
-                    } else {
-                        // example: rule 11 turns into 42{n}, (42 31), 31{n}
-                        //   "11: 42 31 | 42 11 31"
-                        let before = build_re(&v[1][0], rules);
-                        let after = build_re(&v[1][2], rules);
-
-                        // hedge that 4 symmetrical before/after surrounding v[0] will suffice
-                        for i in 1..4 {
-                            s2.push_str(&format!("|{before}{{{i}}}{s1}{after}{{{i}}}"));
+impl Grammar {
+    fn new(rules: &Rules) -> Result<Self, Box<dyn Error>> {
+        let mut names: Vec<String> = rules.keys().cloned().collect();
+        names.sort();
+        let index: HashMap<String, usize> = names.iter().enumerate().map(|(i, n)| (n.clone(), i)).collect();
+        let symbol = |s: &String| match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            Some(literal) => Ok(Symbol::Literal(literal.to_string())),
+            None => index
+                .get(s)
+                .map(|r| Symbol::Rule(*r))
+                .ok_or_else(|| format!("undefined rule {s:?}")),
+        };
+        let alts = names
+            .iter()
+            .map(|n| {
+                rules[n]
+                    .iter()
+                    .map(|alt| alt.iter().map(symbol).collect::<Result<Vec<_>, _>>())
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // rules that can match the empty string, needed to complete items predicted at the same position
+        let mut nullable = vec![false; names.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for r in 0..names.len() {
+                if !nullable[r]
+                    && alts[r].iter().any(|alt| {
+                        alt.iter().all(|s| match s {
+                            Symbol::Rule(x) => nullable[*x],
+                            Symbol::Literal(l) => l.is_empty(),
+                        })
+                    })
+                {
+                    nullable[r] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        Ok(Self {
+            names,
+            index,
+            alts,
+            nullable,
+        })
+    }
+
+    fn rule(&self, start: &str) -> Result<usize, Box<dyn Error>> {
+        self.index
+            .get(start)
+            .copied()
+            .ok_or_else(|| format!("no rule {start:?}").into())
+    }
+
+    // Earley chart, chart[j] holds the items that have matched message[origin..j]
+    //
+    // positions are byte offsets, so literals match on bytes and any message is safe to slice
+    fn chart(&self, start: usize, message: &str) -> Vec<Vec<Item>> {
+        let message = message.as_bytes();
+        let n = message.len();
+        let mut chart: Vec<Vec<Item>> = vec![vec![]; n + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); n + 1];
+        let add = |chart: &mut Vec<Vec<Item>>, seen: &mut Vec<HashSet<Item>>, j: usize, item: Item| {
+            if seen[j].insert(item) {
+                chart[j].push(item);
+            }
+        };
+
+        for alt in 0..self.alts[start].len() {
+            let item = Item {
+                rule: start,
+                alt,
+                dot: 0,
+                origin: 0,
+            };
+            add(&mut chart, &mut seen, 0, item);
+        }
+
+        for j in 0..=n {
+            let mut i = 0;
+            while i < chart[j].len() {
+                let item = chart[j][i];
+                let advanced = Item {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match self.alts[item.rule][item.alt].get(item.dot) {
+                    // predict
+                    Some(Symbol::Rule(r)) => {
+                        for alt in 0..self.alts[*r].len() {
+                            let item = Item {
+                                rule: *r,
+                                alt,
+                                dot: 0,
+                                origin: j,
+                            };
+                            add(&mut chart, &mut seen, j, item);
+                        }
+                        if self.nullable[*r] {
+                            add(&mut chart, &mut seen, j, advanced);
                         }
                     }
-                } else {
-                    s2.push('|');
-                    for item in &v[1] {
-                        s2.push_str(&build_re(item, rules));
+                    // scan
+                    Some(Symbol::Literal(l)) => {
+                        if message[j..].starts_with(l.as_bytes()) {
+                            add(&mut chart, &mut seen, j + l.len(), advanced);
+                        }
+                    }
+                    // complete
+                    None => {
+                        for k in 0..chart[item.origin].len() {
+                            let waiting = chart[item.origin][k];
+                            if self.alts[waiting.rule][waiting.alt].get(waiting.dot) == Some(&Symbol::Rule(item.rule)) {
+                                let waiting = Item {
+                                    dot: waiting.dot + 1,
+                                    ..waiting
+                                };
+                                add(&mut chart, &mut seen, j, waiting);
+                            }
+                        }
                     }
                 }
+                i += 1;
             }
-            "(".to_owned() + &s1 + &s2 + ")"
         }
-        None => key.to_string(),
+        chart
+    }
+
+    fn matches(&self, start: &str, message: &str) -> Result<bool, Box<dyn Error>> {
+        let rule = self.rule(start)?;
+        let chart = self.chart(rule, message);
+        Ok(chart[message.len()]
+            .iter()
+            .any(|it| it.rule == rule && it.origin == 0 && it.dot == self.alts[it.rule][it.alt].len()))
+    }
+
+    // up to `limit` parse trees of the whole message, empty when it doesn't match
+    fn parse_trees(&self, start: &str, message: &str, limit: usize) -> Result<Vec<Tree>, Box<dyn Error>> {
+        let rule = self.rule(start)?;
+        let chart = self.chart(rule, message);
+
+        // every (rule, start, end) span some rule matched
+        let mut spans = HashSet::new();
+        for (j, items) in chart.iter().enumerate() {
+            for it in items.iter().filter(|it| it.dot == self.alts[it.rule][it.alt].len()) {
+                spans.insert((it.rule, it.origin, j));
+            }
+        }
+
+        let mut walk = TreeWalk {
+            grammar: self,
+            message: message.as_bytes(),
+            spans,
+            active: HashSet::new(),
+            limit,
+        };
+        Ok(walk.trees(rule, 0, message.len()))
     }
 }
 
-fn solution1(data: &[String]) -> usize {
-    let mut rules = HashMap::new();
+// enumerates parse trees from the completed spans of an Earley chart
+struct TreeWalk<'a> {
+    grammar: &'a Grammar,
+    message: &'a [u8],
+    spans: HashSet<(usize, usize, usize)>,
+    active: HashSet<(usize, usize, usize)>,
+    limit: usize,
+}
+
+impl TreeWalk<'_> {
+    fn trees(&mut self, rule: usize, i: usize, j: usize) -> Vec<Tree> {
+        // a span being expanded again through a cycle of rules would never finish
+        if !self.spans.contains(&(rule, i, j)) || !self.active.insert((rule, i, j)) {
+            return vec![];
+        }
+        let mut trees = vec![];
+        for (a, alt) in self.grammar.alts[rule].iter().enumerate() {
+            for children in self.sequences(alt, i, j) {
+                trees.push(Tree::Node(self.grammar.names[rule].clone(), a, children));
+                if trees.len() >= self.limit {
+                    break;
+                }
+            }
+        }
+        trees.truncate(self.limit);
+        self.active.remove(&(rule, i, j));
+        trees
+    }
+
+    // every way `symbols` can match message[i..j]
+    fn sequences(&mut self, symbols: &[Symbol], i: usize, j: usize) -> Vec<Vec<Tree>> {
+        let Some((first, rest)) = symbols.split_first() else {
+            return match i == j {
+                true => vec![vec![]],
+                false => vec![],
+            };
+        };
+        let mut result = vec![];
+        for mid in i..=j {
+            let heads = match first {
+                Symbol::Literal(l) if self.message[i..mid] == *l.as_bytes() => vec![Tree::Leaf(l.clone())],
+                Symbol::Literal(_) => continue,
+                Symbol::Rule(r) => self.trees(*r, i, mid),
+            };
+            if heads.is_empty() {
+                continue;
+            }
+            for tail in self.sequences(rest, mid, j) {
+                for head in &heads {
+                    let mut seq = vec![head.clone()];
+                    seq.extend(tail.iter().cloned());
+                    result.push(seq);
+                    if result.len() >= self.limit {
+                        return result;
+                    }
+                }
+            }
+        }
+        result
+    }
+}
 
-    for (count, line) in data.iter().enumerate() {
-        if line.is_empty() {
-            let re = Regex::new(&("^".to_owned() + &build_re("0", &rules) + "$")).unwrap();
-            return data[count + 1..].iter().filter(|s| re.is_match(s)).count();
-        } else {
-            let mut groups = vec![];
-            let parts = trim_split_on::<String>(&line.replace('"', ""), ':').unwrap();
-            for group in trim_split_on::<String>(&parts[1], '|').unwrap() {
-                let items = group.split_whitespace().map(|s| s.into()).collect();
-                groups.push(items);
+// `trees` collects a parse tree of each matching message
+fn solve(rules: &Rules, messages: &[String], mut trees: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    let grammar = Grammar::new(rules)?;
+    let mut count = 0;
+    for message in messages {
+        if grammar.matches("0", message)? {
+            count += 1;
+            if let Some(trees) = trees.as_mut() {
+                for tree in grammar.parse_trees("0", message, 1)? {
+                    trees.push(format!("{message}: {tree}"));
+                }
             }
-            rules.insert(parts[0].to_string(), groups);
         }
     }
-    panic!("missing empty line")
+    Ok(count)
 }
 
-fn solution2(data: &[String]) -> usize {
-    let mut cycle_data = vec![];
-    // replace rules 8 and 11 with rules which cycle
-    for line in data {
-        if line == "8: 42" {
-            cycle_data.push("8: 42 | 42 8".into());
-        } else if line == "11: 42 31" {
-            cycle_data.push("11: 42 31 | 42 11 31".into());
-        } else {
-            cycle_data.push(line.into());
+fn solution1(data: &[String], trees: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    let (rules, messages) = get_data(data)?;
+    solve(&rules, &messages, trees)
+}
+
+fn solution2(data: &[String], trees: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    let (mut rules, messages) = get_data(data)?;
+
+    // replace rules 8 and 11, when the grammar has them, with rules which cycle
+    let rule = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+    for (name, alts) in [
+        ("8", vec![rule("42"), rule("42 8")]),
+        ("11", vec![rule("42 31"), rule("42 11 31")]),
+    ] {
+        if let Some(r) = rules.get_mut(name) {
+            *r = alts;
         }
     }
-    solution1(&cycle_data)
+    solve(&rules, &messages, trees)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -97,8 +353,18 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&puzzle_lines))?;
-    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&puzzle_lines))?;
+    let verbose = args.get_flag("verbose");
+    let mut trees = vec![];
+    let answer = solution1(&puzzle_lines, verbose.then_some(&mut trees))?;
+    for line in trees.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 1 = {answer:?}")?;
+    let answer = solution2(&puzzle_lines, verbose.then_some(&mut trees))?;
+    for line in trees {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {answer:?}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -118,24 +384,85 @@ mod tests {
     #[test]
     fn part1_example() {
         let data = get_data("input-example");
-        assert_eq!(2, solution1(&data));
+        assert_eq!(2, solution1(&data, None).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let data = get_data("input-actual");
-        assert_eq!(156, solution1(&data));
+        assert_eq!(156, solution1(&data, None).unwrap());
     }
 
     #[test]
     fn part2_example() {
         let data = get_data("input-example");
-        assert_eq!(2, solution2(&data));
+        assert_eq!(2, solution2(&data, None).unwrap());
     }
 
     #[test]
     fn part2_actual() {
         let data = get_data("input-actual");
-        assert_eq!(363, solution2(&data));
+        assert_eq!(363, solution2(&data, None).unwrap());
+    }
+
+    #[test]
+    fn parse_trees() -> Result<(), Box<dyn Error>> {
+        let (rules, _) = super::get_data(&get_data("input-example"))?;
+        let grammar = Grammar::new(&rules)?;
+        let trees = grammar.parse_trees("0", "ababbb", 10)?;
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            trees[0].to_string(),
+            "0[0](4[0](a) 1[1](3[1](5[0](b) 4[0](a)) 2[1](5[0](b) 5[0](b))) 5[0](b))"
+        );
+        assert!(grammar.parse_trees("0", "aaabbb", 10)?.is_empty());
+
+        let mut trees = vec![];
+        assert_eq!(2, solution1(&get_data("input-example"), Some(&mut trees))?);
+        assert_eq!(trees.len(), 2);
+        assert_eq!(
+            trees[0],
+            "ababbb: 0[0](4[0](a) 1[1](3[1](5[0](b) 4[0](a)) 2[1](5[0](b) 5[0](b))) 5[0](b))"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_rules() -> Result<(), Box<dyn Error>> {
+        // left recursive, right recursive and ambiguous
+        let data = ["0: 0 1 | 1", "1: 2 | 3", "2: \"a\"", "3: 1 3 | \"b\"", "", "abab"].map(String::from);
+        let (rules, _) = super::get_data(&data)?;
+        let grammar = Grammar::new(&rules)?;
+        assert!(grammar.matches("0", "abab")?);
+        assert!(grammar.matches("0", "bbba")?);
+        assert!(!grammar.matches("0", "abac")?);
+        assert!(grammar.parse_trees("0", "ab", 100)?.len() > 1);
+
+        // a rule that can match the empty string
+        let data = ["0: 1 2 1", "1: 3 | ", "2: \"x\"", "3: \"y\"", ""].map(String::from);
+        let (rules, _) = super::get_data(&data)?;
+        let grammar = Grammar::new(&rules)?;
+        assert!(grammar.matches("0", "x")?);
+        assert!(grammar.matches("0", "yxy")?);
+        assert!(!grammar.matches("0", "yy")?);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_input() -> Result<(), Box<dyn Error>> {
+        // messages are matched on bytes, so any text is safe
+        let data = ["0: 1 1", "1: \"é\" | \"e\"", ""].map(String::from);
+        let (rules, _) = super::get_data(&data)?;
+        let grammar = Grammar::new(&rules)?;
+        assert!(grammar.matches("0", "éé")?);
+        assert!(grammar.matches("0", "eé")?);
+        assert!(!grammar.matches("0", "ü")?);
+        assert_eq!(grammar.parse_trees("0", "éü", 1)?, []);
+
+        // an unquoted name is always a rule reference
+        let data = ["0: 1 2", "1: \"a\"", ""].map(String::from);
+        let (rules, _) = super::get_data(&data)?;
+        assert!(Grammar::new(&rules).is_err());
+        Ok(())
     }
 }
@@ -1,440 +1,286 @@
+use general::render::{Dumper, Palette, BLACK, BLUE, CYAN, GRAY};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
-use std::error::Error;
-use std::io::{self, Write};
 use ndarray::*;
-use rand::Rng;
 use regex::Regex;
-use std::collections::HashMap;
-
-#[rustfmt::skip]
-#[derive(Debug, PartialEq)]
-struct Tile {
-    mat: Array2<bool>,
-}
-impl Tile {
-    fn edge_vector(&self, n: usize) -> Vec<bool> {
-        match n {
-            0 => self.mat.slice(s![.., 0]).to_vec(),
-            1 => self.mat.slice(s![.., -1]).to_vec(),
-            2 => self.mat.slice(s![0, ..]).to_vec(),
-            3 => self.mat.slice(s![-1, ..]).to_vec(),
-            _ => unreachable!(),
-        }
-    }
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::io::{self, Write};
 
-    fn inner_data(&self) -> Array2<bool> {
-        let clippy_reversed_empty_ranges = -1;
-        self.mat
-            .slice(s![1..clippy_reversed_empty_ranges, 1..clippy_reversed_empty_ranges])
-            .to_owned()
-    }
-}
+//                  #
+//#    ##    ##    ###
+// #  #  #  #  #  #
+const SEA_MONSTER: [&str; 3] = ["                  # ", "#    ##    ##    ###", " #  #  #  #  #  #   "];
 
-fn get_tiles(data: &[String]) -> HashMap<usize, Tile> {
-    let re: Regex = Regex::new(r"Tile (\d+):").unwrap();
-    let mut rows = vec![];
-    let mut ids = vec![];
+// tiles by id, the map keeps the assembly deterministic
+fn get_tiles(data: &[String]) -> Result<BTreeMap<usize, Array2<bool>>, Box<dyn Error>> {
+    let re: Regex = Regex::new(r"^Tile (\d+):$").unwrap();
+    let mut tiles = BTreeMap::new();
+    let mut current: Option<(usize, Vec<Vec<bool>>)> = None;
     for line in data.iter().filter(|line| !line.is_empty()) {
-        if line.starts_with("Tile") {
-            let caps = re.captures(line).unwrap();
-            let id = caps
-                .get(1)
-                .expect("capture failed")
-                .as_str()
-                .parse::<usize>()
-                .expect("Can't parse");
-            ids.push(id);
+        if let Some(caps) = re.captures(line) {
+            if let Some((id, rows)) = current.take() {
+                tiles.insert(id, to_array(id, &rows)?);
+            }
+            current = Some((caps[1].parse()?, vec![]));
         } else {
-            rows.push(line.chars().map(|c| c == '#').collect::<Vec<_>>());
+            let (_, rows) = current.as_mut().ok_or(format!("image data before a tile id: {line}"))?;
+            rows.push(line.chars().map(|c| c == '#').collect());
         }
     }
-
-    // tiles are square: nXn
-    let n = rows[0].len();
-
-    let mut tiles = HashMap::new();
-
-    for (i, id) in ids.iter().enumerate() {
-        let mut mat = Array::from_elem((0, n), false);
-        for row in rows.iter().skip(i * n).take(n) {
-            mat.push_row(ArrayView::from(row)).unwrap();
-        }
-        tiles.insert(*id, Tile { mat });
+    if let Some((id, rows)) = current {
+        tiles.insert(id, to_array(id, &rows)?);
+    }
+    if tiles.is_empty() {
+        return Err("no tiles".into());
     }
-    tiles
+    Ok(tiles)
 }
 
-fn rotate_flip(degrees: i32, horiz_flip: bool, vert_flip: bool, tile: &Array2<bool>) -> Array2<bool> {
-    assert_eq!((degrees + 360) % 90, 0, "degrees = {degrees} is not a multiple of 90");
-    let mut rotated_tile = tile.clone();
-    let dim = tile.nrows();
-    let rotation = (degrees + 360) / 90 % 4 * 90;
-
-    if rotation > 0 {
-        for i in 0..dim {
-            for j in 0..dim {
-                rotated_tile[[i, j]] = match (degrees + 360) / 90 % 4 * 90 {
-                    90 => tile[[dim - 1 - j, i]],
-                    180 => tile[[dim - 1 - i, dim - 1 - j]],
-                    270 => tile[[j, dim - 1 - i]],
-                    _ => tile[[i, j]], // NOTREACHED
-                }
-            }
-        }
+// tiles are square: nXn
+fn to_array(id: usize, rows: &[Vec<bool>]) -> Result<Array2<bool>, Box<dyn Error>> {
+    let n = rows.len();
+    if rows.iter().any(|r| r.len() != n) {
+        return Err(format!("tile {id} isn't square").into());
     }
+    Ok(Array::from_shape_fn((n, n), |(i, j)| rows[i][j]))
+}
 
-    if horiz_flip {
-        rotated_tile = flip_horizontal_axis(&rotated_tile);
+// the dihedral group of the square: k % 4 quarter turns clockwise, then a transpose when k >= 4
+fn orient<T: Clone>(mat: &Array2<T>, k: usize) -> Array2<T> {
+    let mut view = mat.view();
+    for _ in 0..k % 4 {
+        view = view.reversed_axes();
+        view.invert_axis(Axis(1));
     }
-
-    if vert_flip {
-        rotated_tile = flip_vertical_axis(&rotated_tile);
+    if k >= 4 {
+        view = view.reversed_axes();
     }
-
-    rotated_tile
+    view.to_owned()
 }
 
-fn flip_vertical_axis(tile: &Array2<bool>) -> Array2<bool> {
-    rotate_flip(90, false, false, &tile.t().to_owned())
+// 0=top, 1=right, 2=bottom, 3=left, read left to right and top to bottom
+fn edge(mat: &Array2<bool>, side: usize) -> Vec<bool> {
+    match side {
+        0 => mat.row(0).to_vec(),
+        1 => mat.column(mat.ncols() - 1).to_vec(),
+        2 => mat.row(mat.nrows() - 1).to_vec(),
+        3 => mat.column(0).to_vec(),
+        _ => unreachable!(),
+    }
 }
 
-fn flip_horizontal_axis(tile: &Array2<bool>) -> Array2<bool> {
-    rotate_flip(-90, false, false, &tile.t().to_owned())
+// an edge and its reverse are the same edge once tiles can flip
+fn canonical(edge: Vec<bool>) -> Vec<bool> {
+    let rev: Vec<bool> = edge.iter().rev().copied().collect();
+    edge.min(rev)
 }
 
-#[allow(clippy::type_complexity)]
-fn get_edges(tiles: &HashMap<usize, Tile>) -> HashMap<usize, (Vec<bool>, Vec<bool>, Vec<bool>, Vec<bool>)> {
-    /*
-    let mut edges = HashMap::new();
-
-    for (id, tile) in tiles {
-        edges.insert(
-            *id,
-            (
-                tile.edge_vector(0),
-                tile.edge_vector(1),
-                tile.edge_vector(2),
-                tile.edge_vector(3),
-            ),
-        );
+// the number of other tiles sharing an edge with each tile, corners have 2
+fn neighbor_counts(tiles: &BTreeMap<usize, Array2<bool>>) -> BTreeMap<usize, usize> {
+    let mut owners: HashMap<Vec<bool>, Vec<usize>> = HashMap::new();
+    for (id, mat) in tiles {
+        for side in 0..4 {
+            owners.entry(canonical(edge(mat, side))).or_default().push(*id);
+        }
     }
-    edges
-    */
-
-    tiles
-        .iter()
-        .map(|(id, tile)| {
-            (
-                *id,
-                (
-                    tile.edge_vector(0),
-                    tile.edge_vector(1),
-                    tile.edge_vector(2),
-                    tile.edge_vector(3),
-                ),
-            )
-        })
-        .collect::<HashMap<_, _>>()
-}
-
-#[allow(clippy::type_complexity)]
-fn get_neighbors(
-    edges: &HashMap<usize, (Vec<bool>, Vec<bool>, Vec<bool>, Vec<bool>)>,
-) -> HashMap<usize, Vec<(usize, usize)>> {
-    let mut neighbors = HashMap::new();
-
-    for (id1, e1) in edges {
-        // match helper function
-        let mtch = |v: &Vec<bool>, e: &(Vec<bool>, Vec<bool>, Vec<bool>, Vec<bool>)| {
-            *v == e.0 || *v == e.1 || *v == e.2 || *v == e.3
-        };
-
-        // .0=left, .1=right, .2=top, .3=bottom));
-        let mut vs = vec![];
-        for (id2, e2) in edges {
-            if e1 == e2 {
-                continue;
-            }
-
-            if mtch(&e1.0, e2) || mtch(&e1.0.iter().rev().copied().collect(), e2) {
-                vs.push((0, *id2));
-            } else if mtch(&e1.1, e2) || mtch(&e1.1.iter().rev().copied().collect(), e2) {
-                vs.push((1, *id2));
-            } else if mtch(&e1.2, e2) || mtch(&e1.2.iter().rev().copied().collect(), e2) {
-                vs.push((2, *id2));
-            } else if mtch(&e1.3, e2) || mtch(&e1.3.iter().rev().copied().collect(), e2) {
-                vs.push((3, *id2));
-            }
+    let mut counts: BTreeMap<usize, usize> = tiles.keys().map(|id| (*id, 0)).collect();
+    for ids in owners.values().filter(|ids| ids.len() > 1) {
+        for id in ids {
+            *counts.get_mut(id).unwrap() += ids.len() - 1;
         }
-        neighbors.insert(*id1, vs);
     }
-    neighbors
+    counts
 }
 
-fn solution1(data: &[String]) -> usize {
-    let tiles = get_tiles(data);
-    let edges = get_edges(&tiles);
-    // corners are unique with 2 neighbors
-    get_neighbors(&edges)
-        .iter()
-        .filter(|(_, v)| v.len() == 2)
-        .map(|(id, _)| id)
-        .product()
+// A square arrangement of oriented tiles
+struct Jigsaw {
+    n: usize,
+    placed: Vec<(usize, Array2<bool>)>,
 }
 
-// given an ordered edge, lookup and print the matix of id's
-#[allow(dead_code)]
-fn debug_print_id(ordered_edge_ids: &[usize], neighbors: &HashMap<usize, Vec<(usize, usize)>>) -> Vec<usize> {
-    let mut row = ordered_edge_ids.to_owned();
-    let mut visited = ordered_edge_ids.to_owned();
-
-    // neighbors is a pairwise lookup of id => List of adjacent matching details
-    // the length of the list is the number of adjacents each described as a tuple
-    //  (
-    //      matched id,
-    //      edge position of the current "data order - as read": .0=left, .1=right, .2=top, .3=bottom
-    //  )
-
-    // sqrt to get size
-    let sz = (neighbors.len() as f64).sqrt() as usize;
-
-    // collect the ids of the next row by walking the given row looking for the edge not yet visited
-    for _ in 0..sz - 1 {
-        let mut tmp = vec![];
-        for id in &row {
-            let adj = neighbors[id]
-                .iter()
-                .find(|(_, adj_id)| !visited.contains(adj_id))
-                .unwrap();
-            visited.push(adj.1);
-            tmp.push(adj.1);
+impl Jigsaw {
+    // backtracking placement in reading order, every internal edge has to match
+    fn assemble(tiles: &BTreeMap<usize, Array2<bool>>) -> Result<Jigsaw, Box<dyn Error>> {
+        let n = (1..).find(|n| n * n >= tiles.len()).unwrap();
+        if n * n != tiles.len() {
+            return Err(format!("{} tiles can't make a square", tiles.len()).into());
         }
-        row = tmp;
-    }
-
-    // display an id grid
-    for i in 0..sz {
-        println!(
-            "{}",
-            visited
-                .iter()
-                .skip(i * sz)
-                .take(sz)
-                .map(|n| n.to_string())
-                .collect::<Vec<_>>()
-                .join("\t")
-        );
-    }
-    visited
-}
 
-#[allow(dead_code)]
-fn debug_print_grid(grid: &Array2<bool>) {
-    for r in 0..grid.nrows() {
-        for c in 0..grid.ncols() {
-            if grid[[r, c]] {
-                print!("#");
-            } else {
-                print!(".");
+        // every orientation of every tile indexed by its top and left edges
+        let mut oriented = vec![];
+        let mut by_top: HashMap<Vec<bool>, Vec<usize>> = HashMap::new();
+        let mut by_left: HashMap<Vec<bool>, Vec<usize>> = HashMap::new();
+        for (id, mat) in tiles {
+            for k in 0..8 {
+                let mat = orient(mat, k);
+                by_top.entry(edge(&mat, 0)).or_default().push(oriented.len());
+                by_left.entry(edge(&mat, 3)).or_default().push(oriented.len());
+                oriented.push((*id, mat));
             }
         }
-        println!();
+
+        // corners are the likely top left tiles so try them first
+        let counts = neighbor_counts(tiles);
+        let mut first: Vec<usize> = (0..oriented.len()).collect();
+        first.sort_by_key(|i| counts[&oriented[*i].0]);
+
+        let mut jigsaw = Jigsaw { n, placed: vec![] };
+        let mut used = HashSet::new();
+        match jigsaw.place(&oriented, &first, &by_top, &by_left, &mut used) {
+            true => Ok(jigsaw),
+            false => Err("the tiles don't fit together".into()),
+        }
     }
 
-    println!();
-    let grid = flip_horizontal_axis(grid);
-    for r in 0..grid.nrows() {
-        for c in 0..grid.ncols() {
-            if grid[[r, c]] {
-                print!("#");
-            } else {
-                print!(".");
+    fn place(
+        &mut self,
+        oriented: &[(usize, Array2<bool>)],
+        first: &[usize],
+        by_top: &HashMap<Vec<bool>, Vec<usize>>,
+        by_left: &HashMap<Vec<bool>, Vec<usize>>,
+        used: &mut HashSet<usize>,
+    ) -> bool {
+        let i = self.placed.len();
+        if i == self.n * self.n {
+            return true;
+        }
+        let (r, c) = (i / self.n, i % self.n);
+        let above = (r > 0).then(|| edge(&self.placed[i - self.n].1, 2));
+        let left = (c > 0).then(|| edge(&self.placed[i - 1].1, 1));
+
+        let candidates = match (&left, &above) {
+            (Some(e), _) => by_left.get(e).map(|v| v.as_slice()).unwrap_or_default(),
+            (None, Some(e)) => by_top.get(e).map(|v| v.as_slice()).unwrap_or_default(),
+            (None, None) => first,
+        };
+        for candidate in candidates {
+            let (id, mat) = &oriented[*candidate];
+            if used.contains(id) || above.as_ref().is_some_and(|e| *e != edge(mat, 0)) {
+                continue;
+            }
+            used.insert(*id);
+            self.placed.push((*id, mat.clone()));
+            if self.place(oriented, first, by_top, by_left, used) {
+                return true;
             }
+            self.placed.pop();
+            used.remove(id);
         }
-        println!();
+        false
     }
-}
 
-#[rustfmt::skip]
-fn solution2(data: &[String]) -> usize {
-    let mut tiles = get_tiles(data);
-    let edges = get_edges(&tiles);
-    let neighbors = get_neighbors(&edges);
-    let sz = (edges.len() as f64).sqrt() as usize;
-
-    //                  #
-    //#    ##    ##    ###
-    // #  #  #  #  #  #
-    let mut monster = Array::from_elem((3, 20), false);
-    monster[[0, 18]] = true;
-    monster[[1, 0]] = true;
-    monster[[1, 5]] = true;
-    monster[[1, 6]] = true;
-    monster[[1, 11]] = true;
-    monster[[1, 12]] = true;
-    monster[[1, 17]] = true;
-    monster[[1, 18]] = true;
-    monster[[1, 19]] = true;
-    monster[[2, 1]] = true;
-    monster[[2, 4]] = true;
-    monster[[2, 7]] = true;
-    monster[[2, 10]] = true;
-    monster[[2, 13]] = true;
-    monster[[2, 16]] = true;
-    let monster_true_count = monster.iter().filter(|b| **b).count();
-
-    // there are 4 tiles with 2 adjacents (the corners)
-    let corners = neighbors.iter().filter(|(_, v)| v.len() == 2).collect::<Vec<_>>();
-    assert_eq!(corners.len(), 4);
-
-    // select one of the corners to logically be top-left with adjacents "right" and "down"
-    let random_corner = rand::thread_rng().gen_range(0..corners.len());
-    let (id, adjacents) = corners[random_corner];
-
-    let tile = &tiles[id].mat;
-    match (adjacents[0].0, adjacents[1].0) {
-        // the current "data read" order: .0=left, .1=right, .2=top, .3=bottom));
-        (l, t) if (l, t) == (0, 2) => { tiles.insert(*id, Tile { mat: rotate_flip(0, true, true, tile) }); }
-        (t, l) if (t, l) == (2, 0) => { tiles.insert(*id, Tile { mat: rotate_flip(90, true, false, tile) }); }
-
-        (l, b) if (l, b) == (0, 3) => { tiles.insert(*id, Tile { mat: rotate_flip(0, false, true, tile) }); }
-        (b, l) if (b, l) == (3, 0) => { tiles.insert(*id, Tile { mat: rotate_flip(-90, false, false, tile) }); }
-
-        (r, t) if (r, t) == (1, 2) => { tiles.insert(*id, Tile { mat: rotate_flip(0, true, false, tile) }); }
-        (t, r) if (t, r) == (2, 1) => { tiles.insert(*id, Tile { mat: rotate_flip(90, false, false, tile) }); }
-
-        (r, b) if (r, b) == (1, 3) => { tiles.insert(*id, Tile { mat: rotate_flip(0, false, false, tile) }); }
-        (b, r) if (b, r) == (3, 1) => { tiles.insert(*id, Tile { mat: rotate_flip(-90, true, false, tile) }); }
-        _ => unreachable!(),
+    // the tiles with their borders removed
+    fn image(&self) -> Array2<bool> {
+        let m = self.placed[0].1.nrows() - 2;
+        Array::from_shape_fn((self.n * m, self.n * m), |(i, j)| {
+            self.placed[(i / m) * self.n + j / m].1[[1 + i % m, 1 + j % m]]
+        })
     }
+}
 
-    let mut id = *id;
-    let mut first_row = vec![id];
-    let mut right_edge = adjacents[0].0;
+// The '#' cells of an ASCII template, anything else is a don't care
+struct Pattern {
+    cells: Vec<(usize, usize)>,
+    nrows: usize,
+    ncols: usize,
+}
 
-    for _ in 0..sz - 1 {
-        let right_id = neighbors[&id]
+impl Pattern {
+    fn new(lines: &[&str]) -> Result<Pattern, Box<dyn Error>> {
+        let cells: Vec<_> = lines
             .iter()
-            .filter(|(e, _)| *e == right_edge)
-            .map(|(_, id)| *id)
-            .next()
-            .unwrap();
-        let left_edge = neighbors[&right_id]
-            .iter()
-            .find(|(_, i)| *i == id)
-            .map(|(e, _)| *e)
-            .unwrap();
-
-        // align tile
-        let flip = tiles[&id].edge_vector(1) != tiles[&right_id].edge_vector(left_edge);
-        let tile = &tiles[&right_id].mat;
-        match left_edge {
-            0 => { tiles.insert(right_id, Tile { mat: rotate_flip(0, flip, false, tile) }); }
-            1 => { tiles.insert(right_id, Tile { mat: rotate_flip(0, flip, true, tile) }); }
-            2 => { tiles.insert(right_id, Tile { mat: rotate_flip(-90, !flip, false, tile) }); }
-            3 => { tiles.insert(right_id, Tile { mat: rotate_flip(90, flip, false, tile) }); }
-            _ => unreachable!(),
+            .enumerate()
+            .flat_map(|(r, line)| {
+                line.char_indices()
+                    .filter(|(_, ch)| *ch == '#')
+                    .map(move |(c, _)| (r, c))
+            })
+            .collect();
+        if cells.is_empty() {
+            return Err("a pattern needs at least one '#'".into());
         }
-
-        right_edge = match left_edge {
-            0 => 1, // left -> right
-            1 => 0, // right -> left
-            2 => 3, // top -> bottom
-            3 => 2, // bottom -> top
-            _ => unreachable!(),
-        };
-
-        id = right_id;
-        first_row.push(id);
+        Ok(Pattern {
+            nrows: cells.iter().map(|p| p.0).max().unwrap() + 1,
+            ncols: cells.iter().map(|p| p.1).max().unwrap() + 1,
+            cells,
+        })
     }
 
-    //debug_print_id(&first_row, &neighbors);
-
-    let mut row = first_row.clone();
-    let mut visited = first_row.clone();
-
-    // collect the ids of the next row by walking the given row looking for the edge not yet visited
-    for _ in 0..sz - 1 {
-        let mut tmp = vec![];
-        for tid in &row {
-            let adj = neighbors[tid]
-                .iter()
-                .find(|(_, adj_id)| !visited.contains(adj_id))
-                .unwrap();
-            let down_id = adj.1;
-            let top_edge = neighbors[&down_id]
-                .iter()
-                .find(|(_, i)| i == tid)
-                .map(|(e, _)| *e)
-                .unwrap();
-
-            let flip = tiles[tid].edge_vector(3) != tiles[&down_id].edge_vector(top_edge);
-            let tile = &tiles[&down_id].mat;
-            match top_edge {
-                0 => { tiles.insert( down_id, Tile { mat: rotate_flip(90, false, !flip, tile) }); }
-                1 => { tiles.insert( down_id, Tile { mat: rotate_flip(-90, false, flip, tile) }); }
-                2 => { tiles.insert( down_id, Tile { mat: rotate_flip(0, false, flip, tile) }); }
-                3 => { tiles.insert( down_id, Tile { mat: rotate_flip(0, true, flip, tile) }); }
-                _ => unreachable!(),
-            }
-            visited.push(down_id);
-            tmp.push(down_id);
+    // top left corners of every match in the image as it is
+    fn matches(&self, image: &Array2<bool>) -> Vec<(usize, usize)> {
+        if image.nrows() < self.nrows || image.ncols() < self.ncols {
+            return vec![];
         }
-        row = tmp;
-    }
-
-    // everything is rotated, grab the inner data (remove the border)
-    let tile = tiles[&visited[0]].inner_data();
-    let mut grid = Array::from_elem((sz * tile.nrows(), sz * tile.ncols()), false);
-    let mut r = 0;
-    let mut c = 0;
-    for id in visited {
-        let array = tiles[&id].inner_data();
-        for i in 0..array.nrows() {
-            for j in 0..array.ncols() {
-                if array[[i, j]] {
-                    grid[[r + i, c + j]] = true;
+        let mut found = vec![];
+        for r in 0..=image.nrows() - self.nrows {
+            for c in 0..=image.ncols() - self.ncols {
+                if self.cells.iter().all(|(i, j)| image[[r + i, c + j]]) {
+                    found.push((r, c));
                 }
             }
         }
-        c += array.ncols();
+        found
+    }
 
-        if c == grid.ncols() {
-            r += array.nrows();
-            c = 0;
-        }
+    // the image orientation with the most matches, (orientation, matches)
+    fn search(&self, image: &Array2<bool>) -> (usize, Vec<(usize, usize)>) {
+        (0..8)
+            .map(|k| (k, self.matches(&orient(image, k))))
+            .max_by_key(|(k, found)| (found.len(), std::cmp::Reverse(*k)))
+            .unwrap()
     }
 
-    //debug_print_grid(&grid);
-
-    // look for the sea monster
-    let orig_grid = grid.clone();
-    let true_count = grid.iter().filter(|b| **b).count();
-    let mut max_monsters = 0;
-
-    for rot in [0, 90, 180, 270] {
-        for vflip in [false, true] {
-            let mut mcount = 0;
-            grid = rotate_flip(rot, false, vflip, &orig_grid.to_owned());
-            for r in 0..grid.nrows() - monster.nrows() {
-                for c in 0..grid.ncols() - monster.ncols() {
-                    let mut count = 0;
-                    for i in 0..monster.nrows() {
-                        for j in 0..monster.ncols() {
-                            if monster[[i, j]] && grid[[r + i, c + j]] {
-                                count += 1;
-                            }
-                        }
-                    }
-                    if count == monster_true_count {
-                        mcount += 1;
-                    }
-                }
-            }
-            max_monsters = max_monsters.max(mcount);
-        }
+    // every image cell covered by the matches
+    fn covered(&self, found: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+        found
+            .iter()
+            .flat_map(|(r, c)| self.cells.iter().map(move |(i, j)| (r + i, c + j)))
+            .collect()
     }
-    true_count - max_monsters * monster_true_count
+}
+
+fn solution1(data: &[String]) -> Result<usize, Box<dyn Error>> {
+    let tiles = get_tiles(data)?;
+    let corners: Vec<usize> = neighbor_counts(&tiles)
+        .into_iter()
+        .filter(|(_, count)| *count == 2)
+        .map(|(id, _)| id)
+        .collect();
+    if corners.len() != 4 {
+        return Err(format!("expected 4 corner tiles, found {}", corners.len()).into());
+    }
+    Ok(corners.iter().product())
+}
+
+// water roughness, '#' cells that aren't part of any sea monster
+fn solution2(data: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
+    let jigsaw = Jigsaw::assemble(&get_tiles(data)?)?;
+    let monster = Pattern::new(&SEA_MONSTER)?;
+    let image = jigsaw.image();
+    let (k, found) = monster.search(&image);
+    let image = orient(&image, k);
+    let covered = monster.covered(&found);
+
+    if dumper.is_enabled() {
+        let picture = Array::from_shape_fn(image.dim(), |p| match (image[p], covered.contains(&p)) {
+            (true, true) => 'O',
+            (true, false) => '#',
+            _ => '.',
+        });
+        let rows = || {
+            picture
+                .rows()
+                .into_iter()
+                .map(|row| row.into_iter().copied().collect::<Vec<_>>())
+        };
+        let text: String = rows()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect();
+        dumper.text("image.txt", &text)?;
+        let palette = Palette::new(BLUE).with('#', CYAN).with('O', BLACK).with('.', GRAY);
+        dumper.grid("image.png", rows(), &palette)?;
+    }
+    Ok(image.iter().filter(|b| **b).count() - covered.len())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -453,8 +299,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&puzzle_lines))?;
-    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&puzzle_lines))?;
+    let dumper = Dumper::from_args(&args).with_scale(4);
+    writeln!(stdout, "Answer Part 1 = {:?}", solution1(&puzzle_lines)?)?;
+    writeln!(stdout, "Answer Part 2 = {:?}", solution2(&puzzle_lines, &dumper)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -474,24 +321,84 @@ mod tests {
     #[test]
     fn part1_example() {
         let data = get_data("input-example");
-        assert_eq!(20899048083289, solution1(&data));
+        assert_eq!(20899048083289, solution1(&data).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let data = get_data("input-actual");
-        assert_eq!(60145080587029, solution1(&data));
+        assert_eq!(60145080587029, solution1(&data).unwrap());
     }
 
     #[test]
     fn part2_example() {
         let data = get_data("input-example");
-        assert_eq!(273, solution2(&data));
+        assert_eq!(273, solution2(&data, &Dumper::default()).unwrap());
     }
 
     #[test]
     fn part2_actual() {
         let data = get_data("input-actual");
-        assert_eq!(1901, solution2(&data));
+        assert_eq!(1901, solution2(&data, &Dumper::default()).unwrap());
+    }
+
+    #[test]
+    fn dihedral_group() {
+        let mat = Array::from_shape_vec((2, 3), (0..6).collect()).unwrap();
+        let all: HashSet<_> = (0..8).map(|k| orient(&mat, k)).collect();
+        assert_eq!(all.len(), 8);
+        assert_eq!(orient(&mat, 1), array![[3, 0], [4, 1], [5, 2]]);
+        assert_eq!(orient(&mat, 4), mat.t());
+        // closed under composition
+        for a in 0..8 {
+            for b in 0..8 {
+                assert!(all.contains(&orient(&orient(&mat, a), b)));
+            }
+        }
+    }
+
+    #[test]
+    fn assembly() -> Result<(), Box<dyn Error>> {
+        let tiles = get_tiles(&get_data("input-example"))?;
+        let jigsaw = Jigsaw::assemble(&tiles)?;
+        let n = jigsaw.n;
+        assert_eq!(n, 3);
+        for i in 0..n * n {
+            let (r, c) = (i / n, i % n);
+            let mat = &jigsaw.placed[i].1;
+            assert!(c == 0 || edge(&jigsaw.placed[i - 1].1, 1) == edge(mat, 3));
+            assert!(r == 0 || edge(&jigsaw.placed[i - n].1, 2) == edge(mat, 0));
+        }
+        let corners = [(0, 0), (0, n - 1), (n - 1, 0), (n - 1, n - 1)];
+        assert_eq!(
+            corners
+                .iter()
+                .map(|(r, c)| jigsaw.placed[r * n + c].0)
+                .product::<usize>(),
+            20899048083289
+        );
+
+        // the same layout every time
+        let again = Jigsaw::assemble(&tiles)?;
+        assert!(jigsaw.placed.iter().zip(&again.placed).all(|(a, b)| a == b));
+        Ok(())
+    }
+
+    #[test]
+    fn patterns() -> Result<(), Box<dyn Error>> {
+        let image = Jigsaw::assemble(&get_tiles(&get_data("input-example"))?)?.image();
+        let (_, monsters) = Pattern::new(&SEA_MONSTER)?.search(&image);
+        assert_eq!(monsters.len(), 2);
+
+        // an L shape is asymmetric so the orientations find different matches
+        let ell = Pattern::new(&["#.", "##"])?;
+        let counts: HashSet<_> = (0..8).map(|k| ell.matches(&orient(&image, k)).len()).collect();
+        assert!(counts.len() > 1);
+        assert_eq!(
+            Pattern::new(&["#"])?.matches(&image).len(),
+            image.iter().filter(|b| **b).count()
+        );
+        assert!(Pattern::new(&["..."]).is_err());
+        Ok(())
     }
 }
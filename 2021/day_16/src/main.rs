@@ -1,5 +1,6 @@
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
 
 const PACKET_HEADER: usize = 6;
@@ -88,7 +89,7 @@ pub enum TypeId {
     Operator(Payload),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Op {
     NUM(u64),
     SUM,
@@ -274,6 +275,233 @@ fn eval(packets: &[Packet]) -> Vec<u64> {
     stack
 }
 
+// The encoder, pretty-printer and S-expression parser are the inverse of decoding,
+// the tests use them to round-trip and hand craft transmissions
+//
+//   (+ (* 2 3) (min 4 5)) is SUM(PROD(2, 3), MIN(4, 5))
+
+const SYMBOLS: [(Op, &str); 7] = [
+    (Op::SUM, "+"),
+    (Op::PROD, "*"),
+    (Op::MIN, "min"),
+    (Op::MAX, "max"),
+    (Op::GT, ">"),
+    (Op::LT, "<"),
+    (Op::EQ, "="),
+];
+
+// The expression a packet stream evaluates, operators own their operands
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Num(u64),
+    Apply(Op, Vec<Expr>),
+}
+
+// How an operator says where its sub-packets end
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LengthType {
+    // length type ID 0, a 15-bit count of the sub-packet bits
+    Bits,
+    // length type ID 1, an 11-bit count of the sub-packets
+    Count,
+    // a count when there are few enough sub-packets, it's 4 bits shorter
+    Shortest,
+}
+
+impl Expr {
+    // one expression per top level packet
+    fn from_packets(packets: &[Packet]) -> Vec<Expr> {
+        let mut exprs = vec![];
+        let mut index = 0;
+        while index < packets.len() {
+            let p = &packets[index];
+            exprs.push(match (&p.id, p.op) {
+                (_, Op::NUM(n)) => Expr::Num(n),
+                (TypeId::Operator(Payload::BitLen(_)), op) => {
+                    Expr::Apply(op, Expr::from_packets(p.sub_packets.as_deref().unwrap_or_default()))
+                }
+                (_, op) => {
+                    let TypeId::Operator(Payload::SubPacketLen(n)) = p.id else {
+                        unreachable!()
+                    };
+                    let count = packets_needed(n, &packets[(index + 1)..]);
+                    let operands = Expr::from_packets(&packets[(index + 1)..(index + 1 + count)]);
+                    index += count;
+                    Expr::Apply(op, operands)
+                }
+            });
+            index += 1;
+        }
+        exprs
+    }
+}
+
+#[cfg(test)]
+impl Expr {
+    // the packet stream for this expression, every packet gets `version`
+    fn to_packets(&self, version: u8, length: LengthType) -> Result<Vec<Packet>, Box<dyn Error>> {
+        match self {
+            Expr::Num(n) => {
+                let nibbles = (64 - n.leading_zeros() as usize).div_ceil(4).max(1);
+                Ok(vec![Packet {
+                    version,
+                    id: TypeId::Literal(5 * nibbles),
+                    op: Op::NUM(*n),
+                    sub_packets: None,
+                }])
+            }
+            Expr::Apply(op, operands) => {
+                if operands.is_empty() || matches!(op, Op::GT | Op::LT | Op::EQ) && operands.len() != 2 {
+                    return Err(format!("{self}: wrong number of operands").into());
+                }
+                let mut subs = vec![];
+                for e in operands {
+                    subs.extend(e.to_packets(version, length)?);
+                }
+                let nbits = encode_bits(&subs).len();
+                let count = match length {
+                    LengthType::Bits => false,
+                    LengthType::Count => true,
+                    LengthType::Shortest => operands.len() < 1 << 11,
+                };
+                match count {
+                    true if operands.len() >= 1 << 11 => {
+                        Err(format!("{} operands don't fit 11 bits", operands.len()).into())
+                    }
+                    true => Ok([Packet {
+                        version,
+                        id: TypeId::Operator(Payload::SubPacketLen(operands.len())),
+                        op: *op,
+                        sub_packets: None,
+                    }]
+                    .into_iter()
+                    .chain(subs)
+                    .collect()),
+                    false if nbits >= 1 << 15 => Err(format!("{nbits} sub-packet bits don't fit 15 bits").into()),
+                    false => Ok(vec![Packet {
+                        version,
+                        id: TypeId::Operator(Payload::BitLen(nbits)),
+                        op: *op,
+                        sub_packets: Some(subs),
+                    }]),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{n}"),
+            Expr::Apply(op, operands) => {
+                let symbol = SYMBOLS.iter().find(|(o, _)| o == op).map(|(_, s)| *s).unwrap_or("?");
+                write!(f, "({symbol}")?;
+                for e in operands {
+                    write!(f, " {e}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl std::str::FromStr for Expr {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn expr<'a, I: Iterator<Item = &'a str>>(tokens: &mut std::iter::Peekable<I>) -> Result<Expr, Box<dyn Error>> {
+            match tokens.next().ok_or("unexpected end of expression")? {
+                "(" => {
+                    let symbol = tokens.next().ok_or("missing operator")?;
+                    let op = SYMBOLS
+                        .iter()
+                        .find(|(_, s)| *s == symbol)
+                        .map(|(op, _)| *op)
+                        .ok_or(format!("unknown operator {symbol:?}"))?;
+                    let mut operands = vec![];
+                    while tokens.peek().is_some_and(|t| *t != ")") {
+                        operands.push(expr(tokens)?);
+                    }
+                    tokens.next().ok_or("missing ')'")?;
+                    Ok(Expr::Apply(op, operands))
+                }
+                ")" => Err("unexpected ')'".into()),
+                n => Ok(Expr::Num(n.parse().map_err(|_| format!("bad number {n:?}"))?)),
+            }
+        }
+
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let mut tokens = spaced.split_whitespace().peekable();
+        let e = expr(&mut tokens)?;
+        match tokens.next() {
+            Some(t) => Err(format!("unexpected {t:?} after the expression").into()),
+            None => Ok(e),
+        }
+    }
+}
+
+#[cfg(test)]
+fn push_num(bits: &mut Vec<u8>, value: u64, width: usize) {
+    bits.extend((0..width).rev().map(|i| (value >> i & 1) as u8));
+}
+
+// the inverse of get_packets
+#[cfg(test)]
+fn encode_bits(packets: &[Packet]) -> Vec<u8> {
+    let mut bits = vec![];
+    for p in packets {
+        push_num(&mut bits, p.version as u64, 3);
+        let type_id = match p.op {
+            Op::NUM(_) => 4,
+            op => SYMBOLS
+                .iter()
+                .position(|(o, _)| *o == op)
+                .map(|i| if i < 4 { i } else { i + 1 })
+                .unwrap(),
+        };
+        push_num(&mut bits, type_id as u64, 3);
+        match (&p.id, p.op) {
+            (TypeId::Literal(n), Op::NUM(value)) => {
+                let groups = n / 5;
+                for g in (0..groups).rev() {
+                    bits.push((g > 0) as u8);
+                    push_num(&mut bits, value >> (4 * g), 4);
+                }
+            }
+            (TypeId::Operator(Payload::BitLen(_)), _) => {
+                let sub = encode_bits(p.sub_packets.as_deref().unwrap_or_default());
+                bits.push(0);
+                push_num(&mut bits, sub.len() as u64, 15);
+                bits.extend(sub);
+            }
+            (TypeId::Operator(Payload::SubPacketLen(n)), _) => {
+                bits.push(1);
+                push_num(&mut bits, *n as u64, 11);
+            }
+            (TypeId::Literal(_), op) => panic!("literal packet with operator {op:?}"),
+        }
+    }
+    bits
+}
+
+// hex transmission, zero padded to whole hex digits
+#[cfg(test)]
+fn encode(packets: &[Packet]) -> String {
+    encode_bits(packets)
+        .chunks(4)
+        .map(|nibble| {
+            let value = nibble
+                .iter()
+                .chain([0, 0, 0].iter())
+                .take(4)
+                .fold(0, |acc, b| acc << 1 | *b as u32);
+            char::from_digit(value, 16).unwrap().to_ascii_uppercase()
+        })
+        .collect()
+}
+
 fn solution1(packets: &[Packet]) -> u64 {
     let mut total = 0;
     for p in packets {
@@ -308,6 +536,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     let bits = get_bits(&puzzle_lines[0]);
     let packets = get_packets(&bits);
     //println!("packets = {:#?}", packets);
+    if args.get_flag("verbose") {
+        for e in Expr::from_packets(&packets) {
+            writeln!(stdout, "{e}")?;
+        }
+    }
     writeln!(stdout, "Answer Part 1 = {:?}", solution1(&packets))?;
     writeln!(stdout, "Answer Part 2 = {:?}", solution2(&packets))?;
 
@@ -561,4 +794,60 @@ mod tests {
         let packets = get_packets(&bits);
         assert_eq!(solution2(&packets), 1392637195518);
     }
+
+    #[test]
+    fn round_trip() {
+        for data in [
+            "D2FE28",
+            "C200B40A82",
+            "04005AC33890",
+            "9C0141080250320F1802104A08",
+            "38006F45291200",
+            "EE00D40C823060",
+        ] {
+            let packets = get_packets(&get_bits(data));
+            let hex = encode(&packets);
+            assert!(
+                data.starts_with(&hex) && data[hex.len()..].chars().all(|c| c == '0'),
+                "{data} {hex}"
+            );
+            assert_eq!(get_packets(&get_bits(&hex)), packets);
+        }
+
+        let data = get_data("input-actual");
+        let packets = get_packets(&get_bits(&data[0]));
+        let exprs = Expr::from_packets(&packets);
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(exprs[0].to_string().parse::<Expr>().unwrap(), exprs[0]);
+        assert_eq!(get_packets(&get_bits(&encode(&packets))), packets);
+    }
+
+    #[test]
+    fn s_expressions() -> Result<(), Box<dyn Error>> {
+        let exprs = Expr::from_packets(&get_packets(&get_bits("9C0141080250320F1802104A08")));
+        assert_eq!(exprs[0].to_string(), "(= (+ 1 3) (* 2 2))");
+
+        let e: Expr = "(+ (* 2 3) (min 4 5))".parse()?;
+        assert_eq!(e.to_string(), "(+ (* 2 3) (min 4 5))");
+        for length in [LengthType::Bits, LengthType::Count, LengthType::Shortest] {
+            let packets = e.to_packets(3, length)?;
+            let decoded = get_packets(&get_bits(&encode(&packets)));
+            assert_eq!(decoded, packets);
+            assert_eq!(Expr::from_packets(&decoded), std::slice::from_ref(&e));
+            assert_eq!(solution2(&decoded), 10);
+            assert_eq!(solution1(&decoded), 3 * 7);
+        }
+
+        // edge cases: a literal needing all 16 nibbles and a single operand operator
+        let e: Expr = format!("(max (< {} 0) (+ 7))", u64::MAX).parse()?;
+        let packets = e.to_packets(7, LengthType::Shortest)?;
+        assert_eq!(solution2(&get_packets(&get_bits(&encode(&packets)))), 7);
+
+        for bad in ["", "(+ 1", "(+ 1))", "(% 1 2)", "(+ x)", "1 2"] {
+            assert!(bad.parse::<Expr>().is_err(), "{bad}");
+        }
+        assert!("(< 1 2 3)".parse::<Expr>()?.to_packets(0, LengthType::Count).is_err());
+        assert!("(+)".parse::<Expr>()?.to_packets(0, LengthType::Count).is_err());
+        Ok(())
+    }
 }
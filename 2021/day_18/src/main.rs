@@ -1,184 +1,207 @@
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
+use std::ops::Add;
+use std::str::FromStr;
 
-#[macro_use]
-extern crate json;
-
-//
-// a lot of Json clone() and &mut reference passing in this one
+// A Snail Number is a pair whose elements are regular numbers or pairs, [[1,2],3]
 //
-// using json::JsonValue for the abstraction to represent a Snail Number (see type below)
-// a SnailNum is a pair -- a 2 element vector: [ SnailNum[0], SnailNum[1] ]
-//
-// the subset of JsonValue used to test for a number, list, or create a list
-//    JsonValue.is_number()
-//    JsonValue.is_array()
-//    macro array![] to create a new list
-
-type SnailNum = json::JsonValue;
-
-const MAX_DEPTH: usize = 4;
-
-// consume the input data, returning a Vec of SnailNum
-fn get_data(data: &[String]) -> Vec<SnailNum> {
-    let mut nums = vec![];
-    for line in data {
-        nums.push(json::parse(line).expect("unparsable SnailNum"));
-    }
-    nums
-}
-
-// return the JsonValue as an unsigned integer
-fn jint(n: &json::JsonValue) -> u64 {
-    match *n {
-        json::JsonValue::Number(x) => {
-            let f: f64 = x.into();
-            f as u64
-        }
-        _ => panic!("{}: not a JsonValue::Number", n.dump()),
-    }
-}
-
-// To split a regular number, replace it with a pair; the left element of the pair
-// should be the regular number divided by two and rounded down, while the right
-// element of the pair should be the regular number divided by two and rounded up.
-// For example, 10 becomes [5,5], 11 becomes [5,6], 12 becomes [6,6], and so on.
-fn split_values(n: u64) -> SnailNum {
-    let x = (n as f64) / 2.0;
-    array![x.floor(), x.ceil()]
+// It's stored flat as its regular numbers from left to right, each with the number of
+// pairs enclosing it. [[1,2],3] is (1, 2), (2, 2), (3, 1). Every reduction step only
+// touches neighbouring numbers in this order so no tree walking is needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SnailNum {
+    nums: Vec<(u32, u8)>,
 }
 
-// If any regular number is 10 or greater, the leftmost such regular number splits.
-fn split(n: &mut SnailNum) -> bool {
-    for i in 0..=1 {
-        if n[i].is_array() {
-            if split(&mut n[i]) {
-                return true;
+const MAX_DEPTH: u8 = 4;
+
+impl FromStr for SnailNum {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn element(s: &[u8], i: &mut usize, depth: u8, nums: &mut Vec<(u32, u8)>) -> Result<(), Box<dyn Error>> {
+            match s.get(*i) {
+                Some(b'[') if depth > MAX_DEPTH => {
+                    Err(format!("pair nested inside more than {MAX_DEPTH} pairs at {i}").into())
+                }
+                Some(b'[') => {
+                    *i += 1;
+                    element(s, i, depth + 1, nums)?;
+                    expect(s, i, b',')?;
+                    element(s, i, depth + 1, nums)?;
+                    expect(s, i, b']')
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let start = *i;
+                    while s.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+                        *i += 1;
+                    }
+                    nums.push((std::str::from_utf8(&s[start..*i])?.parse()?, depth));
+                    Ok(())
+                }
+                _ => Err(format!("expected '[' or a number at {i}").into()),
             }
-        } else {
-            let x = jint(&n[i]);
-            if x > 9 {
-                n[i] = split_values(x);
-                return true;
+        }
+        fn expect(s: &[u8], i: &mut usize, c: u8) -> Result<(), Box<dyn Error>> {
+            match s.get(*i) == Some(&c) {
+                true => {
+                    *i += 1;
+                    Ok(())
+                }
+                false => Err(format!("expected {:?} at {i}", c as char).into()),
             }
         }
-    }
 
-    false
+        let s = s.trim().as_bytes();
+        let (mut i, mut nums) = (0, vec![]);
+        if s.first() != Some(&b'[') {
+            return Err("a snail number is a pair".into());
+        }
+        element(s, &mut i, 0, &mut nums)?;
+        if i != s.len() {
+            return Err(format!("unexpected trailing input at {i}").into());
+        }
+        Ok(SnailNum { nums })
+    }
 }
 
-// helper to add a value to a number
-//   i == 0 (left)
-//   i == 1 (right)
-fn add_to_nearest(n: &mut SnailNum, i: usize, val: u64) {
-    if n[i].is_number() {
-        n[i] = (jint(&n[i]) + val).into();
-    } else {
-        add_to_nearest(&mut n[i], i, val);
+// rebuild the pairs from the depths, two neighbours at the same depth are a pair
+impl fmt::Display for SnailNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn element(f: &mut fmt::Formatter<'_>, nums: &[(u32, u8)], i: &mut usize, depth: u8) -> fmt::Result {
+            let (value, d) = nums[*i];
+            if d == depth {
+                *i += 1;
+                return write!(f, "{value}");
+            }
+            write!(f, "[")?;
+            element(f, nums, i, depth + 1)?;
+            write!(f, ",")?;
+            element(f, nums, i, depth + 1)?;
+            write!(f, "]")
+        }
+        element(f, &self.nums, &mut 0, 0)
     }
 }
 
-// To explode a pair, the pair's left value is added to the first regular number
-// to the left of the exploding pair (if any), and the pair's right value is added
-// to the first regular number to the right of the exploding pair (if any).
-// Exploding pairs will always consist of two regular numbers. Then, the entire
-// exploding pair is replaced with the regular number 0.
-fn explode_it(n: &mut SnailNum, depth: usize) -> Option<(u64, u64)> {
-    if depth == MAX_DEPTH && n[0].is_number() && n[1].is_number() {
-        return Some((jint(&n[0]), jint(&n[1])));
+impl SnailNum {
+    // the pair [self, other] without reducing it
+    fn join(&self, other: &SnailNum) -> SnailNum {
+        SnailNum {
+            nums: self.nums.iter().chain(&other.nums).map(|(v, d)| (*v, d + 1)).collect(),
+        }
     }
 
-    if n[0].is_array() {
-        if let Some(pair) = explode_it(&mut n[0], depth + 1) {
-            if depth == MAX_DEPTH - 1 {
-                n[0] = 0.into();
-            }
-
-            // add pair.1 to the 1st pair.0 found in n[1]
-            if n[1].is_array() {
-                add_to_nearest(&mut n[1], 0, pair.1);
-            } else {
-                n[1] = (jint(&n[1]) + pair.1).into();
-            }
-
-            // pair.1 has just been added, zero it for subsequent additions
-            return Some((pair.0, 0));
+    // If any pair is nested inside four pairs, the leftmost such pair explodes.
+    //
+    // To explode a pair, the pair's left value is added to the first regular number
+    // to the left of the exploding pair (if any), and the pair's right value is added
+    // to the first regular number to the right of the exploding pair (if any).
+    // Exploding pairs will always consist of two regular numbers. Then, the entire
+    // exploding pair is replaced with the regular number 0.
+    //
+    // The first two neighbours at the same depth are always a pair, anything to their
+    // left would contain an earlier one.
+    fn explode(&mut self) -> bool {
+        let Some(i) = self
+            .nums
+            .windows(2)
+            .position(|w| w[0].1 > MAX_DEPTH && w[0].1 == w[1].1)
+        else {
+            return false;
+        };
+        let ((left, depth), (right, _)) = (self.nums[i], self.nums[i + 1]);
+        if i > 0 {
+            self.nums[i - 1].0 += left;
+        }
+        if let Some(next) = self.nums.get_mut(i + 2) {
+            next.0 += right;
         }
+        self.nums.splice(i..i + 2, [(0, depth - 1)]);
+        true
     }
 
-    if n[1].is_array() {
-        if let Some(pair) = explode_it(&mut n[1], depth + 1) {
-            if depth == MAX_DEPTH - 1 {
-                n[1] = 0.into();
-            }
+    // If any regular number is 10 or greater, the leftmost such regular number splits.
+    //
+    // To split a regular number, replace it with a pair; the left element of the pair
+    // should be the regular number divided by two and rounded down, while the right
+    // element of the pair should be the regular number divided by two and rounded up.
+    fn split(&mut self) -> bool {
+        let Some(i) = self.nums.iter().position(|(v, _)| *v >= 10) else {
+            return false;
+        };
+        let (value, depth) = self.nums[i];
+        self.nums
+            .splice(i..=i, [(value / 2, depth + 1), (value - value / 2, depth + 1)]);
+        true
+    }
 
-            // add pair.0 to the 1st pair.1 found in n[0]
-            if n[0].is_array() {
-                add_to_nearest(&mut n[0], 1, pair.0);
-            } else {
-                n[0] = (jint(&n[0]) + pair.0).into();
+    // To reduce a snailfish number, you must repeatedly do the first action
+    // in this list that applies to the snailfish number:
+    //
+    //   If any pair is nested inside four pairs, the leftmost such pair explodes.
+    //   If any regular number is 10 or greater, the leftmost such regular number splits.
+    //
+    // `trace` collects the number after each action in the puzzle's format
+    fn reduce(&mut self, mut trace: Option<&mut Vec<String>>) {
+        loop {
+            let action = match self.explode() {
+                true => "explode: ",
+                false => match self.split() {
+                    true => "split:   ",
+                    false => break,
+                },
+            };
+            if let Some(t) = trace.as_mut() {
+                t.push(format!("after {action} {self}"));
             }
-
-            // pair.0 has just been added, zero it for subsequent additions
-            return Some((0, pair.1));
         }
     }
 
-    None
-}
-
-// If any pair is nested inside four pairs, the leftmost such pair explodes.
-fn explode(n: &mut SnailNum) {
-    while explode_it(n, 0).is_some() {}
-}
-
-// To reduce a snailfish number, you must repeatedly do the first action
-// in this list that applies to the snailfish number:
-//
-//   If any pair is nested inside four pairs, the leftmost such pair explodes.
-//   If any regular number is 10 or greater, the leftmost such regular number splits.
-//
-// Once no action in the above list applies, the snailfish number is reduced.
-//
-// During reduction, at most one action applies, after which the process returns
-// to the top of the list of actions. For example, if split produces a pair that
-// meets the explode criteria, that pair explodes before other splits occur.
-fn reduce(n: &mut SnailNum) {
-    loop {
-        explode(n);
-        if !split(n) {
-            break;
+    // The magnitude of a pair is 3 times the magnitude of its left element plus 2 times the
+    // magnitude of its right element. The magnitude of a regular number is just that number.
+    fn magnitude(&self) -> u64 {
+        let mut stack: Vec<(u64, u8)> = vec![];
+        for (value, depth) in &self.nums {
+            stack.push((*value as u64, *depth));
+            while let [.., (left, d1), (right, d2)] = stack[..] {
+                if d1 != d2 {
+                    break;
+                }
+                stack.truncate(stack.len() - 2);
+                stack.push((3 * left + 2 * right, d1 - 1));
+            }
         }
+        stack[0].0
     }
 }
 
-// The magnitude of a pair is 3 times the magnitude of its left element plus 2 times the
-// magnitude of its right element. The magnitude of a regular number is just that number.
-fn magnitude(n: &SnailNum) -> u64 {
-    match n.is_number() {
-        true => jint(n),
-        false => 3 * magnitude(&n[0]) + 2 * magnitude(&n[1]),
+// add 2 Snail Numbers and return the reduced number
+impl Add for &SnailNum {
+    type Output = SnailNum;
+    fn add(self, other: &SnailNum) -> SnailNum {
+        let mut sum = self.join(other);
+        sum.reduce(None);
+        sum
     }
 }
 
-// add 2 Snail Numbers and return the reduced number
-fn add_reduce(a: &SnailNum, b: &SnailNum) -> SnailNum {
-    let mut add = array![a.clone(), b.clone()];
-    reduce(&mut add);
-    add.clone()
+// consume the input data, returning a Vec of SnailNum
+fn get_data(data: &[String]) -> Result<Vec<SnailNum>, Box<dyn Error>> {
+    data.iter().map(|line| line.parse()).collect()
 }
 
 fn solution1(nums: &[SnailNum]) -> u64 {
-    magnitude(&nums.iter().skip(1).fold(nums[0].clone(), |acc, n| add_reduce(&acc, n)))
+    nums.iter().skip(1).fold(nums[0].clone(), |acc, n| &acc + n).magnitude()
 }
 
 fn solution2(nums: &[SnailNum]) -> u64 {
     let mut best = 0;
-    for a in nums {
-        for b in nums {
-            if a != b {
-                best = best.max(magnitude(&add_reduce(a, b)));
+    for (i, a) in nums.iter().enumerate() {
+        for (j, b) in nums.iter().enumerate() {
+            if i != j {
+                best = best.max((a + b).magnitude());
             }
         }
     }
@@ -201,7 +224,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let snail_nums = get_data(&puzzle_lines);
+    let snail_nums = get_data(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 1 = {:?}", solution1(&snail_nums))?;
     writeln!(stdout, "Answer Part 2 = {:?}", solution2(&snail_nums))?;
 
@@ -218,77 +241,81 @@ mod tests {
     fn get_test_data(filename: &str) -> Vec<SnailNum> {
         let file = std::path::PathBuf::from(filename);
         let data = read_trimmed_data_lines::<String>(Some(&file)).unwrap();
-        get_data(&data)
+        get_data(&data).unwrap()
+    }
+
+    fn snail(s: &str) -> SnailNum {
+        s.parse().unwrap()
     }
 
     #[test]
     fn test1() {
-        let mut snail_num = json::parse("[[[[[9,8],1],2],3],4]").unwrap();
-        explode(&mut snail_num);
-        let expect = json::parse("[[[[0,9],2],3],4]").unwrap();
+        let mut snail_num = snail("[[[[[9,8],1],2],3],4]");
+        while snail_num.explode() {}
+        let expect = snail("[[[[0,9],2],3],4]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test2() {
-        let mut snail_num = json::parse("[7,[6,[5,[4,[3,2]]]]]").unwrap();
-        explode(&mut snail_num);
-        let expect = json::parse("[7,[6,[5,[7,0]]]]").unwrap();
+        let mut snail_num = snail("[7,[6,[5,[4,[3,2]]]]]");
+        while snail_num.explode() {}
+        let expect = snail("[7,[6,[5,[7,0]]]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test3() {
-        let mut snail_num = json::parse("[[6,[5,[4,[3,2]]]],1]").unwrap();
-        explode(&mut snail_num);
-        let expect = json::parse("[[6,[5,[7,0]]],3]").unwrap();
+        let mut snail_num = snail("[[6,[5,[4,[3,2]]]],1]");
+        while snail_num.explode() {}
+        let expect = snail("[[6,[5,[7,0]]],3]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test4() {
-        let mut snail_num = json::parse("[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]").unwrap();
-        explode(&mut snail_num);
-        let expect = json::parse("[[3,[2,[8,0]]],[9,[5,[7,0]]]]").unwrap();
+        let mut snail_num = snail("[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]");
+        while snail_num.explode() {}
+        let expect = snail("[[3,[2,[8,0]]],[9,[5,[7,0]]]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test5() {
-        let mut snail_num = json::parse("[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]").unwrap();
-        explode(&mut snail_num);
-        let expect = json::parse("[[[[0,7],4],[15,[0,13]]],[1,1]]").unwrap();
+        let mut snail_num = snail("[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+        while snail_num.explode() {}
+        let expect = snail("[[[[0,7],4],[15,[0,13]]],[1,1]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test6() {
-        let mut snail_num = json::parse("[[[[0,7],4],[15,[0,13]]],[1,1]]").unwrap();
-        split(&mut snail_num);
-        let expect = json::parse("[[[[0,7],4],[[7,8],[0,13]]],[1,1]]").unwrap();
+        let mut snail_num = snail("[[[[0,7],4],[15,[0,13]]],[1,1]]");
+        snail_num.split();
+        let expect = snail("[[[[0,7],4],[[7,8],[0,13]]],[1,1]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test7() {
-        let mut snail_num = json::parse("[[[[0,7],4],[[7,8],[0,13]]],[1,1]]").unwrap();
-        split(&mut snail_num);
-        let expect = json::parse("[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]").unwrap();
+        let mut snail_num = snail("[[[[0,7],4],[[7,8],[0,13]]],[1,1]]");
+        snail_num.split();
+        let expect = snail("[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test8() {
-        let mut snail_num = json::parse("[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]").unwrap();
-        reduce(&mut snail_num);
-        let expect = json::parse("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]").unwrap();
+        let mut snail_num = snail("[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+        snail_num.reduce(None);
+        let expect = snail("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
         assert_eq!(snail_num, expect);
     }
 
     #[test]
     fn test9() {
-        let snail_num = json::parse("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]").unwrap();
-        assert_eq!(magnitude(&snail_num), 1384);
+        let snail_num = snail("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+        assert_eq!(snail_num.magnitude(), 1384);
     }
 
     #[test]
@@ -314,4 +341,59 @@ mod tests {
         let data = get_test_data("input-actual");
         assert_eq!(solution2(&data), 4659);
     }
+
+    #[test]
+    fn round_trip() {
+        for line in read_trimmed_data_lines::<String>(Some(&"input-example".into())).unwrap() {
+            assert_eq!(snail(&line).to_string(), line);
+        }
+        assert_eq!(snail("[[1,2],[[3,4],5]]").magnitude(), 143);
+        for bad in [
+            "",
+            "7",
+            "[1,2",
+            "[1,2]]",
+            "[1;2]",
+            "[[1,2]]",
+            "[[[[[[1,2],3],4],5],6],7]",
+        ] {
+            assert!(bad.parse::<SnailNum>().is_err(), "{bad}");
+        }
+        assert!(format!("{}1{}", "[".repeat(300), ",1]".repeat(300))
+            .parse::<SnailNum>()
+            .is_err());
+    }
+
+    #[test]
+    fn deep_explode() {
+        // the first number past the limit, 1, is the left of a pair holding another pair
+        let mut n = snail("[1,1]").join(&snail("[[[[1,[2,3]],4],5],6]"));
+        let mut trace = vec![];
+        n.reduce(Some(&mut trace));
+        assert_eq!(
+            trace,
+            [
+                "after explode:  [[1,1],[[[[3,0],7],5],6]]",
+                "after explode:  [[1,4],[[[0,7],5],6]]",
+            ]
+        );
+    }
+
+    #[test]
+    fn reduction_trace() {
+        let mut sum = snail("[[[[4,3],4],4],[7,[[8,4],9]]]").join(&snail("[1,1]"));
+        assert_eq!(sum.to_string(), "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+        let mut trace = vec![];
+        sum.reduce(Some(&mut trace));
+        assert_eq!(
+            trace,
+            [
+                "after explode:  [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[15,[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+            ]
+        );
+    }
 }
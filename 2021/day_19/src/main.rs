@@ -1,171 +1,229 @@
+use general::render::Dumper;
+use general::rotation::{Rotation, Vector};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
 
-const ROTATIONS: usize = 24;
-// http://www.euclideanspace.com/maths/algebra/matrix/transforms/examples/index.htm
+// scanners overlap when they see at least this many of the same beacons
+const OVERLAP: usize = 12;
+
+fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn manhattan(a: Vector, b: Vector) -> i64 {
+    sub(a, b).iter().map(|d| d.abs()).sum()
+}
 
 #[derive(Debug, Clone, PartialEq)]
 struct Scanner {
-    id: u8,
-    beacons: Vec<(i64, i64, i64)>,
-    distances: HashMap<(i64, i64, i64), usize>,
+    id: usize,
+    beacons: Vec<Vector>,
+    // squared distances between every pair of beacons, the same however the scanner is turned
+    fingerprint: HashMap<i64, usize>,
 }
+
 impl Scanner {
-    fn new(id: u8, beacons: Vec<(i64, i64, i64)>) -> Self {
-        let mut distances = HashMap::new();
+    fn new(id: usize, beacons: Vec<Vector>) -> Self {
+        let mut fingerprint = HashMap::new();
         for i in 0..beacons.len() {
             for j in (i + 1)..beacons.len() {
-                let tup = (
-                    (beacons[i].0 - beacons[j].0),
-                    (beacons[i].1 - beacons[j].1),
-                    (beacons[i].2 - beacons[j].2),
-                );
-                assert!(!distances.contains_key(&tup));
-                distances.insert(tup, i);
+                let d = sub(beacons[i], beacons[j]);
+                *fingerprint.entry(d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).or_default() += 1;
             }
         }
+        Self {
+            id,
+            beacons,
+            fingerprint,
+        }
+    }
 
-        Self { id, beacons, distances }
+    // 12 shared beacons make 66 shared pair distances, fewer rules out an overlap
+    fn may_overlap(&self, other: &Scanner) -> bool {
+        let shared: usize = self
+            .fingerprint
+            .iter()
+            .map(|(d, n)| (*n).min(other.fingerprint.get(d).copied().unwrap_or(0)))
+            .sum();
+        shared >= OVERLAP * (OVERLAP - 1) / 2
     }
-}
-impl Scanner {
-    fn rotate(&self, rotation_index: usize) -> Self {
-        let mut beacons = vec![];
-        for (x, y, z) in &self.beacons {
-            let (x, y, z) = (*x, *y, *z);
-            let rotated_beacon = match rotation_index {
-                0 => (x, y, z),
-                1 => (x, -z, y),
-                2 => (x, -y, -z),
-                3 => (x, z, -y),
-                4 => (-x, -y, z),
-                5 => (-x, -z, -y),
-                6 => (-x, y, -z),
-                7 => (-x, z, y),
-                8 => (y, x, -z),
-                9 => (y, -x, z),
-                10 => (y, z, x),
-                11 => (y, -z, -x),
-                12 => (-y, x, z),
-                13 => (-y, -x, -z),
-                14 => (-y, -z, x),
-                15 => (-y, z, -x),
-                16 => (z, x, y),
-                17 => (z, -x, -y),
-                18 => (z, -y, x),
-                19 => (z, y, -x),
-                20 => (-z, x, -y),
-                21 => (-z, -x, y),
-                22 => (-z, y, x),
-                23 => (-z, -y, -x),
-                _ => panic!("invalid index {rotation_index}"),
-            };
-            beacons.push(rotated_beacon);
+
+    // the transform taking `other`'s coordinates into ours, voting on the offset for each rotation
+    fn align(&self, other: &Scanner) -> Option<Transform> {
+        for rotation in Rotation::all() {
+            let turned: Vec<_> = other.beacons.iter().map(|b| rotation.apply(*b)).collect();
+            let mut votes: HashMap<Vector, usize> = HashMap::new();
+            for a in &self.beacons {
+                for b in &turned {
+                    let n = votes.entry(sub(*a, *b)).or_default();
+                    *n += 1;
+                    if *n >= OVERLAP {
+                        return Some(Transform {
+                            rotation,
+                            offset: sub(*a, *b),
+                        });
+                    }
+                }
+            }
         }
-        Self::new(self.id, beacons)
+        None
     }
 }
-impl Scanner {
-    fn relpos(&self, other: &Scanner) -> Option<(i64, i64, i64)> {
-        let k1 = self.distances.keys().copied().collect::<HashSet<_>>();
-        let k2 = other.distances.keys().copied().collect::<HashSet<_>>();
-        let dkeys = k1.intersection(&k2).copied().collect::<HashSet<_>>();
-
-        match dkeys.len() > 11 {
-            true => {
-                let tup = dkeys.iter().next().unwrap();
-                let a = *self.distances.get(tup).unwrap();
-                let b = *other.distances.get(tup).unwrap();
-                Some((
-                    self.beacons[a].0 - other.beacons[b].0,
-                    self.beacons[a].1 - other.beacons[b].1,
-                    self.beacons[a].2 - other.beacons[b].2,
-                ))
-            }
-            false => None,
+
+// x -> rotation x + offset
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Transform {
+    rotation: Rotation,
+    offset: Vector,
+}
+
+impl Transform {
+    fn apply(&self, v: Vector) -> Vector {
+        add(self.rotation.apply(v), self.offset)
+    }
+
+    // apply `other` first and then `self`
+    fn compose(&self, other: &Transform) -> Transform {
+        Transform {
+            rotation: self.rotation.compose(&other.rotation),
+            offset: self.apply(other.offset),
+        }
+    }
+
+    fn inverse(&self) -> Transform {
+        let rotation = self.rotation.inverse();
+        Transform {
+            rotation,
+            offset: rotation.apply(sub([0, 0, 0], self.offset)),
         }
     }
 }
 
-fn get_data(data: &[String]) -> Vec<Scanner> {
-    let re = Regex::new(r"--\s+scanner\s+(-?\d+)").unwrap();
+fn get_data(data: &[String]) -> Result<Vec<Scanner>, Box<dyn Error>> {
+    let re = Regex::new(r"--\s+scanner\s+(\d+)").unwrap();
     let mut scanners = vec![];
-    let mut beacons = vec![];
-    let mut id = 0;
+    let mut current: Option<(usize, Vec<Vector>)> = None;
     for line in data.iter().filter(|s| !s.trim().is_empty()) {
-        if line.contains("scanner") {
-            if !beacons.is_empty() {
+        if let Some(captures) = re.captures(line) {
+            if let Some((id, beacons)) = current.take() {
                 scanners.push(Scanner::new(id, beacons));
             }
-            let captures = re.captures(line).unwrap();
-            id = captures.get(1).map(|s| s.as_str().parse::<u8>().unwrap()).unwrap();
-            beacons = vec![];
+            current = Some((captures[1].parse()?, vec![]));
             continue;
         }
         let coords = line
             .split(',')
-            .map(|s| s.trim().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        assert_eq!(coords.len(), 3);
-        beacons.push((coords[0], coords[1], coords[2]));
+            .map(|s| s.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let beacon: Vector = coords.try_into().map_err(|_| format!("expected x,y,z: {line}"))?;
+        current
+            .as_mut()
+            .ok_or(format!("beacon before a scanner: {line}"))?
+            .1
+            .push(beacon);
     }
-    scanners.push(Scanner::new(id, beacons));
-    scanners
+    if let Some((id, beacons)) = current {
+        scanners.push(Scanner::new(id, beacons));
+    }
+    if scanners.iter().enumerate().any(|(i, s)| s.id != i) {
+        return Err("scanners should be numbered 0, 1, 2, ...".into());
+    }
+    Ok(scanners)
 }
 
-fn solutions(scanners: &[Scanner]) -> (usize, u64) {
-    let mut positions = vec![];
-    let mut rotations = HashMap::new();
-
-    for i in 0..scanners.len() {
-        positions.push((0, 0, 0));
-        rotations.insert(i, 0);
-    }
-    let mut solved = HashSet::new();
-    let mut tested = HashSet::new();
-    solved.insert(0);
+// Every scanner's position and the beacons they see, relative to scanner 0
+struct Map {
+    scanners: Vec<Vector>,
+    beacons: BTreeSet<Vector>,
+}
 
-    while solved.len() != scanners.len() {
-        for i in solved.clone() {
-            for j in 0..scanners.len() {
-                if i == j || solved.contains(&j) || tested.contains(&(i, j)) || tested.contains(&(j, i)) {
+impl Map {
+    // align overlapping pairs then walk the alignment graph out from scanner 0
+    fn build(scanners: &[Scanner]) -> Result<Map, Box<dyn Error>> {
+        let mut graph: Vec<Vec<(usize, Transform)>> = vec![vec![]; scanners.len()];
+        for i in 0..scanners.len() {
+            for j in (i + 1)..scanners.len() {
+                if !scanners[i].may_overlap(&scanners[j]) {
                     continue;
                 }
-                tested.insert((i, j));
-                let rscan = scanners[i].rotate(*rotations.get(&i).expect("no rotation"));
-                for k in 0..ROTATIONS {
-                    if let Some(pos) = rscan.relpos(&scanners[j].rotate(k)) {
-                        rotations.insert(j, k);
-                        solved.insert(j);
-                        positions[j] = (pos.0 + positions[i].0, pos.1 + positions[i].1, pos.2 + positions[i].2);
-                        break;
-                    }
+                if let Some(t) = scanners[i].align(&scanners[j]) {
+                    graph[i].push((j, t));
+                    graph[j].push((i, t.inverse()));
                 }
             }
         }
-    }
 
-    let mut beacons = HashSet::<(i64, i64, i64)>::new();
-    for i in 0..scanners.len() {
-        for b in &scanners[i].rotate(*rotations.get(&i).expect("no rotation")).beacons {
-            beacons.insert((b.0 + positions[i].0, b.1 + positions[i].1, b.2 + positions[i].2));
+        let mut to_zero: Vec<Option<Transform>> = vec![None; scanners.len()];
+        to_zero[0] = Some(Transform::default());
+        let mut workq = VecDeque::from([0]);
+        while let Some(i) = workq.pop_front() {
+            let t = to_zero[i].unwrap();
+            for (j, tj) in &graph[i] {
+                if to_zero[*j].is_none() {
+                    to_zero[*j] = Some(t.compose(tj));
+                    workq.push_back(*j);
+                }
+            }
         }
+        let to_zero: Vec<Transform> = to_zero
+            .iter()
+            .enumerate()
+            .map(|(i, t)| t.ok_or(format!("scanner {i} doesn't overlap the others")))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Map {
+            scanners: to_zero.iter().map(|t| t.offset).collect(),
+            beacons: scanners
+                .iter()
+                .zip(&to_zero)
+                .flat_map(|(s, t)| s.beacons.iter().map(|b| t.apply(*b)))
+                .collect(),
+        })
     }
 
-    let mut best = 0;
-    for i in 0..positions.len() {
-        for j in (i + 1)..positions.len() {
-            best = best.max(
-                (positions[i].0 - positions[j].0).abs()
-                    + (positions[i].1 - positions[j].1).abs()
-                    + (positions[i].2 - positions[j].2).abs(),
-            );
+    fn largest_distance(&self) -> i64 {
+        let s = &self.scanners;
+        (0..s.len())
+            .flat_map(|i| (i + 1..s.len()).map(move |j| manhattan(s[i], s[j])))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn write(&self, dumper: &Dumper) -> io::Result<()> {
+        dumper.text("scanners.txt", &listing(&self.scanners))?;
+        dumper.text("beacons.txt", &listing(&self.beacons))
+    }
+}
+
+// one x,y,z per line like the puzzle input
+fn listing<'a, I: IntoIterator<Item = &'a Vector>>(points: I) -> String {
+    points
+        .into_iter()
+        .map(|p| format!("{},{},{}\n", p[0], p[1], p[2]))
+        .collect()
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, p) in self.scanners.iter().enumerate() {
+            writeln!(f, "scanner {i} at {},{},{}", p[0], p[1], p[2])?;
         }
+        write!(f, "{} beacons", self.beacons.len())
     }
-    (beacons.len(), best as u64)
+}
+
+fn solutions(scanners: &[Scanner], dumper: &Dumper) -> Result<(usize, i64), Box<dyn Error>> {
+    let map = Map::build(scanners)?;
+    map.write(dumper)?;
+    Ok((map.beacons.len(), map.largest_distance()))
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -184,8 +242,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let scanners = get_data(&puzzle_lines);
-    let (s1, s2) = solutions(&scanners);
+    let scanners = get_data(&puzzle_lines)?;
+    let (s1, s2) = solutions(&scanners, &Dumper::from_args(&args))?;
     writeln!(stdout, "Answer Part 1 = {s1}")?;
     writeln!(stdout, "Answer Part 2 = {s2}")?;
 
@@ -203,30 +261,55 @@ mod tests {
     fn get_test_data(filename: &str) -> Vec<Scanner> {
         let file = std::path::PathBuf::from(filename);
         let data = read_trimmed_data_lines::<String>(Some(&file)).unwrap();
-        get_data(&data)
+        get_data(&data).unwrap()
     }
 
     #[test]
     fn part1_example() {
         let scanners = get_test_data("input-example");
-        assert_eq!(solutions(&scanners).0, 79);
+        assert_eq!(solutions(&scanners, &Dumper::default()).unwrap().0, 79);
     }
 
     #[test]
     fn part1_actual() {
         let scanners = get_test_data("input-actual");
-        assert_eq!(solutions(&scanners).0, 457);
+        assert_eq!(solutions(&scanners, &Dumper::default()).unwrap().0, 457);
     }
 
     #[test]
     fn part2_example() {
         let scanners = get_test_data("input-example");
-        assert_eq!(solutions(&scanners).1, 3621);
+        assert_eq!(solutions(&scanners, &Dumper::default()).unwrap().1, 3621);
     }
 
     #[test]
     fn part2_actual() {
         let scanners = get_test_data("input-actual");
-        assert_eq!(solutions(&scanners).1, 13243);
+        assert_eq!(solutions(&scanners, &Dumper::default()).unwrap().1, 13243);
+    }
+
+    #[test]
+    fn scanner_positions() {
+        let scanners = get_test_data("input-example");
+        assert!(scanners[0].may_overlap(&scanners[1]));
+        assert!(!scanners[0].may_overlap(&scanners[2]));
+
+        let map = Map::build(&scanners).unwrap();
+        assert_eq!(
+            map.scanners,
+            [
+                [0, 0, 0],
+                [68, -1246, -43],
+                [1105, -1205, 1229],
+                [-92, -2380, -20],
+                [-20, -1133, 1061]
+            ]
+        );
+        assert!(map.beacons.contains(&[459, -707, 401]));
+        assert!(map.to_string().ends_with("scanner 4 at -20,-1133,1061\n79 beacons"));
+
+        // the transform and its inverse undo each other
+        let t = scanners[0].align(&scanners[1]).unwrap();
+        assert_eq!(t.inverse().compose(&t), Transform::default());
     }
 }
@@ -1,3 +1,4 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use ndarray::{s, Array, Array2, ArrayView};
 use std::error::Error;
@@ -88,9 +89,10 @@ fn enhance(image: &Array2<bool>, algorithm: &[bool]) -> Array2<bool> {
     enhanced
 }
 
-fn solution(image: &Array2<bool>, algorithm: &[bool], n: usize) -> usize {
+fn solution(image: &Array2<bool>, algorithm: &[bool], n: usize, anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let mut new_image = image.clone();
     for i in 0..n {
+        display(anim, &format!("Enhance {i}/{n}"), &new_image)?;
         /*
         new_image = match i % 2 == 0 {
             true => enhance(&new_image, algorithm),
@@ -106,21 +108,20 @@ fn solution(image: &Array2<bool>, algorithm: &[bool], n: usize) -> usize {
                 .to_owned();
         }
     }
-    new_image.into_iter().filter(|p| *p).count()
+    display(anim, &format!("Enhance {n}/{n}"), &new_image)?;
+    Ok(new_image.into_iter().filter(|p| *p).count())
 }
 
-#[allow(dead_code)]
-fn display(image: &Array2<bool>) {
-    for i in 0..image.nrows() {
-        for j in 0..image.ncols() {
-            match image[[i, j]] {
-                true => print!("#"),
-                false => print!("."),
-            };
-        }
-        println!();
-    }
-    println!();
+fn display(anim: &mut Animation, title: &str, image: &Array2<bool>) -> io::Result<()> {
+    anim.draw(
+        title,
+        image.rows().into_iter().map(|row| {
+            row.into_iter().map(|p| match p {
+                true => '#',
+                false => '.',
+            })
+        }),
+    )
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -141,8 +142,19 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let (algorithm, image) = get_data(&puzzle_lines);
 
-    writeln!(stdout, "Answer Part 1 = {}", solution(&image, &algorithm, 2))?;
-    writeln!(stdout, "Answer Part 2 = {}", solution(&image, &algorithm, 50))?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('#', Color::BrightWhite)
+        .with_color('.', Color::Gray);
+    writeln!(
+        stdout,
+        "Answer Part 1 = {}",
+        solution(&image, &algorithm, 2, &mut anim)?
+    )?;
+    writeln!(
+        stdout,
+        "Answer Part 2 = {}",
+        solution(&image, &algorithm, 50, &mut anim)?
+    )?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -164,27 +176,36 @@ mod tests {
     fn part1_example() {
         let data = get_test_data("input-example");
         let (algorithm, image) = get_data(&data);
-        assert_eq!(35, solution(&image, &algorithm, 2));
+        assert_eq!(35, solution(&image, &algorithm, 2, &mut Animation::default()).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let data = get_test_data("input-actual");
         let (algorithm, image) = get_data(&data);
-        assert_eq!(5432, solution(&image, &algorithm, 2));
+        assert_eq!(
+            5432,
+            solution(&image, &algorithm, 2, &mut Animation::default()).unwrap()
+        );
     }
 
     #[test]
     fn part2_example() {
         let data = get_test_data("input-example");
         let (algorithm, image) = get_data(&data);
-        assert_eq!(3351, solution(&image, &algorithm, 50));
+        assert_eq!(
+            3351,
+            solution(&image, &algorithm, 50, &mut Animation::default()).unwrap()
+        );
     }
 
     #[test]
     fn part2_actual() {
         let data = get_test_data("input-actual");
         let (algorithm, image) = get_data(&data);
-        assert_eq!(16016, solution(&image, &algorithm, 50));
+        assert_eq!(
+            16016,
+            solution(&image, &algorithm, 50, &mut Animation::default()).unwrap()
+        );
     }
 }
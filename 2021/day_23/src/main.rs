@@ -1,77 +1,299 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
 
-// 0 0 0 0 0 0 0 0 0 0 0  <- Hallway
-//     0   2   4   6      <- Starting Positions
-//     1   3   5   7      <- Starting Positions
-#[derive(Clone, Debug)]
-#[allow(dead_code)]
-struct Amphipod {
-    base_cost: u64,
-    total_cost: u64,
-    //start_pos: usize,
-    hallway_pos: Option<usize>,
-    home: bool,
+// #############
+// #...........#  <- Hallway 0..=10
+// ###B#C#B#D###  <- Rooms 0..=3, doors at hallway 2, 4, 6, 8
+//   #A#D#C#A#    <- depth 2 (part 1) or 4 (part 2)
+//   #########
+const HALLWAY: usize = 11;
+const ROOMS: usize = 4;
+const EMPTY: u8 = b'.';
+
+// amphipods never stop on the space immediately outside any room
+const STOPS: [usize; 7] = [0, 1, 3, 5, 7, 9, 10];
+
+fn door(room: usize) -> usize {
+    2 + 2 * room
 }
 
-fn get_data(data: &[String]) -> [Vec<Amphipod>; 4] {
-    let mut amphipods = vec![];
-
-    for line in data {
-        for c in line.chars() {
-            match c {
-                'A' => amphipods.push(1),
-                'B' => amphipods.push(10),
-                'C' => amphipods.push(100),
-                'D' => amphipods.push(1000),
-                _ => continue,
-            };
+fn energy(kind: u8) -> usize {
+    10_usize.pow((kind - b'A') as u32)
+}
+
+// hallway cells followed by each room from the top down
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Burrow {
+    depth: usize,
+    cells: Vec<u8>,
+}
+
+// amphipod `kind` moves `steps` spaces between two cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Move {
+    kind: u8,
+    from: usize,
+    to: usize,
+    steps: usize,
+}
+
+impl Move {
+    fn energy(&self) -> usize {
+        self.steps * energy(self.kind)
+    }
+}
+
+impl Burrow {
+    fn parse(data: &[String]) -> Result<Self, Box<dyn Error>> {
+        let rows: Vec<Vec<u8>> = data
+            .iter()
+            .map(|line| line.bytes().filter(|c| (b'A'..=b'D').contains(c)).collect::<Vec<u8>>())
+            .filter(|r| !r.is_empty())
+            .collect();
+        if rows.is_empty() || rows.iter().any(|r| r.len() != ROOMS) {
+            return Err("expected rows of 4 amphipods in the rooms".into());
+        }
+        let depth = rows.len();
+        let mut cells = vec![EMPTY; HALLWAY + ROOMS * depth];
+        for (d, row) in rows.iter().enumerate() {
+            for (r, kind) in row.iter().enumerate() {
+                cells[HALLWAY + r * depth + d] = *kind;
+            }
+        }
+        for kind in b'A'..=b'D' {
+            if cells.iter().filter(|c| **c == kind).count() != depth {
+                return Err(format!("expected {depth} amphipods of type {}", kind as char).into());
+            }
         }
+        Ok(Burrow { depth, cells })
     }
 
-    let mut base_a = vec![];
-    let mut base_b = vec![];
-    let mut base_c = vec![];
-    let mut base_d = vec![];
+    // part 2 inserts two rows between the first and last rows of the diagram
+    fn unfold(data: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut data = data.to_vec();
+        let last = data
+            .iter()
+            .rposition(|l| l.contains(|c: char| c.is_ascii_uppercase()))
+            .ok_or("no rooms")?;
+        data.splice(last..last, ["#D#C#B#A#".to_string(), "#D#B#A#C#".to_string()]);
+        Burrow::parse(&data)
+    }
+
+    fn room_cell(&self, room: usize, d: usize) -> usize {
+        HALLWAY + room * self.depth + d
+    }
+
+    fn room(&self, room: usize) -> &[u8] {
+        &self.cells[self.room_cell(room, 0)..self.room_cell(room + 1, 0)]
+    }
 
-    for (i, amp) in amphipods.iter().enumerate() {
-        let a = Amphipod {
-            base_cost: *amp,
-            total_cost: 0,
-            hallway_pos: None,
-            home: false,
+    // a room amphipods can move into, holding nothing but its own kind
+    fn is_ready(&self, room: usize) -> bool {
+        let kind = b'A' + room as u8;
+        self.room(room).iter().all(|c| *c == EMPTY || *c == kind)
+    }
+
+    fn is_solved(&self) -> bool {
+        (0..ROOMS).all(|r| self.room(r).iter().all(|c| *c == b'A' + r as u8))
+    }
+
+    // the hallway between two positions, excluding `from`, is empty
+    fn hallway_clear(&self, from: usize, to: usize) -> bool {
+        let (lo, hi) = match from < to {
+            true => (from + 1, to),
+            false => (to, from - 1),
         };
-        match i % 4 {
-            0 => base_a.push(a),
-            1 => base_b.push(a),
-            2 => base_c.push(a),
-            _ => base_d.push(a),
+        self.cells[lo..=hi].iter().all(|c| *c == EMPTY)
+    }
+
+    fn moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+
+        // into its own room, as deep as possible, which is always best when possible
+        for h in 0..HALLWAY {
+            let kind = self.cells[h];
+            if kind == EMPTY {
+                continue;
+            }
+            let room = (kind - b'A') as usize;
+            if self.is_ready(room) && self.hallway_clear(h, door(room)) {
+                let d = self.room(room).iter().rposition(|c| *c == EMPTY).unwrap();
+                return vec![Move {
+                    kind,
+                    from: h,
+                    to: self.room_cell(room, d),
+                    steps: h.abs_diff(door(room)) + d + 1,
+                }];
+            }
+        }
+
+        // the top amphipod of a room still holding strangers out to a hallway stop
+        for room in (0..ROOMS).filter(|r| !self.is_ready(*r)) {
+            let Some(d) = self.room(room).iter().position(|c| *c != EMPTY) else {
+                continue;
+            };
+            let kind = self.room(room)[d];
+            for h in STOPS {
+                if self.hallway_clear(door(room), h) && self.cells[h] == EMPTY {
+                    moves.push(Move {
+                        kind,
+                        from: self.room_cell(room, d),
+                        to: h,
+                        steps: d + 1 + h.abs_diff(door(room)),
+                    });
+                }
+            }
+        }
+        moves
+    }
+
+    fn apply(&self, m: &Move) -> Burrow {
+        let mut next = self.clone();
+        next.cells[m.to] = m.kind;
+        next.cells[m.from] = EMPTY;
+        next
+    }
+
+    // energy to walk every amphipod home ignoring the others, it never overestimates
+    fn heuristic(&self) -> usize {
+        let mut total = 0;
+        for (i, kind) in self.cells.iter().enumerate().filter(|(_, c)| **c != EMPTY) {
+            let home = (kind - b'A') as usize;
+            let steps = match i < HALLWAY {
+                true => i.abs_diff(door(home)) + 1,
+                false => {
+                    let (room, d) = ((i - HALLWAY) / self.depth, (i - HALLWAY) % self.depth);
+                    match room == home {
+                        true => 0,
+                        false => d + 1 + door(room).abs_diff(door(home)) + 1,
+                    }
+                }
+            };
+            total += steps * energy(*kind);
         }
+        total
     }
 
-    [base_a, base_b, base_c, base_d]
+    // where a cell is drawn in the diagram, (row, col)
+    fn position(&self, i: usize) -> (usize, usize) {
+        match i < HALLWAY {
+            true => (1, i + 1),
+            false => (2 + (i - HALLWAY) % self.depth, door((i - HALLWAY) / self.depth) + 1),
+        }
+    }
+
+    fn diagram(&self) -> Vec<Vec<char>> {
+        let mut rows = vec![vec!['#'; HALLWAY + 2]; self.depth + 3];
+        for row in rows.iter_mut().skip(3) {
+            for c in [0, 1, HALLWAY, HALLWAY + 1] {
+                row[c] = ' ';
+            }
+        }
+        for (i, c) in self.cells.iter().enumerate() {
+            let (r, col) = self.position(i);
+            rows[r][col] = *c as char;
+        }
+        rows
+    }
+}
+
+impl fmt::Display for Burrow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.diagram() {
+            writeln!(f, "{}", row.iter().collect::<String>().trim_end())?;
+        }
+        Ok(())
+    }
 }
 
-#[allow(dead_code)]
-fn solved(amps: &[Vec<Amphipod>; 4]) -> bool {
-    for a in amps {
-        for b in a {
-            if !b.home {
-                return false;
+// least energy to organize the amphipods and the moves that do it, A* search
+fn organize(start: &Burrow) -> Result<(usize, Vec<Move>), Box<dyn Error>> {
+    let mut best = HashMap::from([(start.clone(), 0)]);
+    let mut came_from: HashMap<Burrow, (Burrow, Move)> = HashMap::new();
+    let mut workq = BinaryHeap::from([(Reverse(start.heuristic()), 0, start.clone())]);
+
+    while let Some((_, energy, burrow)) = workq.pop() {
+        if burrow.is_solved() {
+            let mut moves = vec![];
+            let mut cur = burrow;
+            while let Some((prev, m)) = came_from.get(&cur) {
+                moves.push(*m);
+                cur = prev.clone();
             }
+            moves.reverse();
+            return Ok((energy, moves));
+        }
+        if energy > best[&burrow] {
+            continue;
         }
+        for m in burrow.moves() {
+            let next = burrow.apply(&m);
+            let e = energy + m.energy();
+            if best.get(&next).is_none_or(|b| e < *b) {
+                best.insert(next.clone(), e);
+                came_from.insert(next.clone(), (burrow.clone(), m));
+                workq.push((Reverse(e + next.heuristic()), e, next));
+            }
+        }
+    }
+    Err("the amphipods can't be organized".into())
+}
+
+// one line per move with the (row, column) cells in the diagram and the running total
+fn listing(burrow: &Burrow, moves: &[Move]) -> Vec<String> {
+    let mut total = 0;
+    moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            total += m.energy();
+            format!(
+                "move {}: {} {:?} -> {:?}, {} energy (total {total})",
+                i + 1,
+                m.kind as char,
+                burrow.position(m.from),
+                burrow.position(m.to),
+                m.energy()
+            )
+        })
+        .collect()
+}
+
+// replay the solution one move per frame
+fn animate(start: &Burrow, moves: &[Move], anim: &mut Animation) -> Result<(), Box<dyn Error>> {
+    if !anim.is_enabled() {
+        return Ok(());
+    }
+    let mut burrow = start.clone();
+    anim.draw("start", burrow.diagram())?;
+    for (m, title) in moves.iter().zip(listing(start, moves)) {
+        burrow = burrow.apply(m);
+        anim.draw(&title, burrow.diagram())?;
     }
-    true
+    Ok(())
 }
 
-fn solution1(_amps: &[Vec<Amphipod>; 4]) -> i64 {
-    // Solved by hand, still toying with an implementation
-    11608
+// `steps` collects the move listing
+fn solve(burrow: &Burrow, anim: &mut Animation, steps: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    let (energy, moves) = organize(burrow)?;
+    animate(burrow, &moves, anim)?;
+    if let Some(steps) = steps {
+        steps.extend(listing(burrow, &moves));
+    }
+    Ok(energy)
 }
-fn solution2(_amps: &[Vec<Amphipod>; 4]) -> i64 {
-    // Solved by hand, still toying with an implementation
-    46754
+
+fn solution1(data: &[String], anim: &mut Animation, steps: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    solve(&Burrow::parse(data)?, anim, steps)
+}
+
+fn solution2(data: &[String], anim: &mut Animation, steps: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    solve(&Burrow::unfold(data)?, anim, steps)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -90,9 +312,23 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let amphipods = get_data(&puzzle_lines);
-    writeln!(stdout, "Answer Part 1 = {}", solution1(&amphipods))?;
-    writeln!(stdout, "Answer Part 2 = {}", solution2(&amphipods))?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('A', Color::BrightRed)
+        .with_color('B', Color::BrightGreen)
+        .with_color('C', Color::BrightYellow)
+        .with_color('D', Color::BrightBlue);
+    let verbose = args.get_flag("verbose");
+    let mut steps = vec![];
+    let answer = solution1(&puzzle_lines, &mut anim, verbose.then_some(&mut steps))?;
+    for line in steps.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 1 = {answer}")?;
+    let answer = solution2(&puzzle_lines, &mut anim, verbose.then_some(&mut steps))?;
+    for line in steps {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {answer}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -104,20 +340,67 @@ fn main() -> Result<(), Box<dyn Error>> {
 mod tests {
     use super::*;
 
-    fn get_test_data(filename: &str) -> [Vec<Amphipod>; 4] {
+    fn get_test_data(filename: &str) -> Vec<String> {
         let file = std::path::PathBuf::from(filename);
-        get_data(&read_trimmed_data_lines::<String>(Some(&file)).unwrap())
+        read_trimmed_data_lines::<String>(Some(&file)).unwrap()
+    }
+
+    #[test]
+    fn part1_example() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-example");
+        assert_eq!(12521, solution1(&data, &mut Animation::default(), None)?);
+        Ok(())
     }
 
     #[test]
-    fn part1_actual() {
-        let amphipods = get_test_data("input-example");
-        assert_eq!(11608, solution1(&amphipods));
+    fn part1_actual() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-actual");
+        assert_eq!(11608, solution1(&data, &mut Animation::default(), None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn part2_example() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-example");
+        assert_eq!(44169, solution2(&data, &mut Animation::default(), None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn part2_actual() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-actual");
+        assert_eq!(46754, solution2(&data, &mut Animation::default(), None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn burrow() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-example");
+        let burrow = Burrow::unfold(&data)?;
+        assert_eq!(burrow.depth, 4);
+        assert_eq!(
+            burrow.to_string(),
+            "#############\n#...........#\n###B#C#B#D###\n  #D#C#B#A#\n  #D#B#A#C#\n  #A#D#C#A#\n  #########\n"
+        );
+
+        // the solution replays to the organized burrow with the same total energy
+        let burrow = Burrow::parse(&data)?;
+        let (energy, moves) = organize(&burrow)?;
+        let end = moves.iter().fold(burrow.clone(), |b, m| b.apply(m));
+        assert!(end.is_solved());
+        assert_eq!(moves.iter().map(|m| m.energy()).sum::<usize>(), energy);
+        assert!(burrow.heuristic() <= energy);
+        Ok(())
     }
 
     #[test]
-    fn part2_actual() {
-        let amphipods = get_test_data("input-actual");
-        assert_eq!(46754, solution2(&amphipods));
+    fn move_listing() -> Result<(), Box<dyn Error>> {
+        let data = get_test_data("input-example");
+        let mut steps = vec![];
+        assert_eq!(12521, solution1(&data, &mut Animation::default(), Some(&mut steps))?);
+        assert_eq!(steps.len(), 12);
+        assert_eq!(steps[0], "move 1: D (2, 9) -> (1, 10), 2000 energy (total 2000)");
+        assert_eq!(steps[11], "move 12: B (1, 4) -> (2, 5), 20 energy (total 12521)");
+        Ok(())
     }
 }
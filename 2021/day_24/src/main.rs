@@ -52,7 +52,7 @@ fn compute(alu: &mut ALU, instructions: &[String]) {
     }
 }
 
-fn monad1(instructions: &[String], input: &[i64; 14]) -> i64 {
+fn monad1(instructions: &[String], input: &[i64]) -> i64 {
     let mut alu = ALU {
         input: input.to_vec(),
         index: 0,
@@ -70,163 +70,113 @@ fn monad1(instructions: &[String], input: &[i64; 14]) -> i64 {
     alu.z
 }
 
-fn monad2(inp: &[i64; 14]) -> i64 {
-    let mut w = inp[0];
-    let mut z = w + 8;
-
-    w = inp[1];
-    z = z * 26 + w + 13;
-
-    w = inp[2];
-    z = z * 26 + w + 2;
-
-    w = inp[3];
-    //assert_eq!(z % 26, w);
-    z = match z % 26 == w {
-        true => z / 26,
-        false => z * 26 + w + 7,
-    };
-
-    w = inp[4];
-    z = z * 26 + w + 11;
-
-    w = inp[5];
-    z = z * 26 + w + 4;
-
-    w = inp[6];
-    z = z * 26 + w + 13;
-
-    w = inp[7];
-    //assert_eq!(z % 26 - 8, w);
-    z = match z % 26 - 8 == w {
-        true => z / 26,
-        false => z * 26 + w + 13,
-    };
-
-    w = inp[8];
-    //assert_eq!(z % 26 - 9, w);
-    z = match z % 26 - 9 == w {
-        true => z / 26,
-        false => z * 26 + w + 10,
-    };
+// Every digit is processed by the same 18 instruction block, only 3 constants differ
+//
+//   x = z % 26 + add_x != w
+//   z = z / div
+//   if x { z = z * 26 + w + add_y }
+//
+// z is a base 26 stack. div 1 blocks always push (add_x >= 10 can't equal a digit),
+// div 26 blocks pop and must not push again, so the popped digit plus its add_y
+// plus this block's add_x has to equal this digit.
+const BLOCK: [&str; 18] = [
+    "inp w", "mul x 0", "add x z", "mod x 26", "div z _", "add x _", "eql x w", "eql x 0", "mul y 0", "add y 25",
+    "mul y x", "add y 1", "mul z y", "mul y 0", "add y w", "add y _", "mul y x", "add z y",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Block {
+    div: i64,
+    add_x: i64,
+    add_y: i64,
+}
 
-    w = inp[9];
-    z = z * 26 + w + 1;
+fn get_blocks(instructions: &[String]) -> Result<Vec<Block>, Box<dyn Error>> {
+    if instructions.is_empty() || !instructions.len().is_multiple_of(BLOCK.len()) {
+        return Err(format!("expected blocks of {} instructions", BLOCK.len()).into());
+    }
+    let mut blocks = vec![];
+    for (b, chunk) in instructions.chunks(BLOCK.len()).enumerate() {
+        let mut params = vec![];
+        for (line, template) in chunk.iter().zip(BLOCK) {
+            match template.strip_suffix('_') {
+                Some(prefix) if line.starts_with(prefix) => params.push(line[prefix.len()..].parse::<i64>()?),
+                None if line == template => (),
+                _ => return Err(format!("block {b}: expected {template:?}, found {line:?}").into()),
+            }
+        }
+        blocks.push(Block {
+            div: params[0],
+            add_x: params[1],
+            add_y: params[2],
+        });
+    }
+    Ok(blocks)
+}
 
-    w = inp[10];
-    //assert_eq!(z % 26, w);
-    z = match z % 26 == w {
-        true => z / 26,
-        false => z * 26 + w + 2,
-    };
+// digit[j] = digit[i] + delta
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Constraint {
+    i: usize,
+    j: usize,
+    delta: i64,
+}
 
-    w = inp[11];
-    //assert_eq!(z % 26 - 5, w);
-    z = match z % 26 - 5 == w {
-        true => z / 26,
-        false => z * 26 + w + 14,
-    };
+// pair each popping block with the block that pushed its value
+fn get_constraints(blocks: &[Block]) -> Result<Vec<Constraint>, Box<dyn Error>> {
+    let mut stack = vec![];
+    let mut constraints = vec![];
+    for (j, block) in blocks.iter().enumerate() {
+        match block.div {
+            1 if block.add_x >= 10 => stack.push(j),
+            26 => {
+                let i = stack.pop().ok_or_else(|| format!("block {j} pops an empty stack"))?;
+                constraints.push(Constraint {
+                    i,
+                    j,
+                    delta: blocks[i].add_y + block.add_x,
+                });
+            }
+            _ => return Err(format!("block {j} is neither a push nor a pop: {block:?}").into()),
+        }
+    }
+    match stack.is_empty() {
+        true => Ok(constraints),
+        false => Err(format!("blocks {stack:?} are never popped, z can't end at 0").into()),
+    }
+}
 
-    w = inp[12];
-    //assert_eq!(z % 26 - 6, w);
-    z = match z % 26 - 6 == w {
-        true => z / 26,
-        false => z * 26 + w + 6,
-    };
+// the largest or smallest model number satisfying every constraint, digits 1-9
+fn model_number(blocks: &[Block], largest: bool) -> Result<Vec<i64>, Box<dyn Error>> {
+    let mut digits = vec![0; blocks.len()];
+    for c in get_constraints(blocks)? {
+        if c.delta.abs() > 8 {
+            return Err(format!("no digits satisfy digit[{}] = digit[{}] + {}", c.j, c.i, c.delta).into());
+        }
+        digits[c.i] = match largest {
+            true => 9.min(9 - c.delta),
+            false => 1.max(1 - c.delta),
+        };
+        digits[c.j] = digits[c.i] + c.delta;
+    }
+    Ok(digits)
+}
 
-    w = inp[13];
-    //assert_eq!(z % 26 - 12, w);
-    z = match z % 26 - 12 == w {
-        true => z / 26,
-        false => z * 26 + w + 14,
-    };
-    z
+// check the model number by running the program and return it as a number
+fn solve(instructions: &[String], largest: bool) -> Result<i64, Box<dyn Error>> {
+    let digits = model_number(&get_blocks(instructions)?, largest)?;
+    if monad1(instructions, &digits) != 0 {
+        return Err(format!("{digits:?} was rejected by MONAD").into());
+    }
+    Ok(digits.iter().fold(0, |n, d| n * 10 + d))
 }
 
-fn brute_force(instructions: &[String]) -> Vec<[i64; 14]> {
-    let mut solutions = vec![];
-    let digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+fn part1(instructions: &[String]) -> Result<i64, Box<dyn Error>> {
+    solve(instructions, true)
+}
 
-    for i in &digits {
-        let zi = i + 8;
-        for j in &digits {
-            let zj = zi * 26 + j + 13;
-            for k in &digits {
-                let zk = zj * 26 + k + 2;
-                for l in &digits {
-                    if zk % 26 == *l {
-                        let zl = zk / 26;
-                        for m in &digits {
-                            let zm = zl * 26 + m + 11;
-                            for n in &digits {
-                                let zn = zm * 26 + n + 4;
-                                for o in &digits {
-                                    let zo = zn * 26 + o + 13;
-                                    for p in &digits {
-                                        if zo % 26 - 8 == *p {
-                                            let zp = zo / 26;
-                                            for q in &digits {
-                                                if zp % 26 - 9 == *q {
-                                                    let zq = zp / 26;
-                                                    for r in &digits {
-                                                        let zr = zq * 26 + r + 1;
-                                                        for s in &digits {
-                                                            if zr % 26 == *s {
-                                                                let zs = zr / 26;
-                                                                for t in &digits {
-                                                                    if zs % 26 - 5 == *t {
-                                                                        let zt = zs / 26;
-                                                                        for u in &digits {
-                                                                            if zt % 26 - 6 == *u {
-                                                                                let zu = zt / 26;
-                                                                                for v in &digits {
-                                                                                    if zu % 26 - 12 == *v {
-                                                                                        //println!("{},{},{},{},{},{},{},{},{},{},{},{},{},{}", i, j, k, l, m, n, o, p, q, r, s, t, u, v);
-                                                                                        assert_eq!(
-                                                                                            monad1(
-                                                                                                instructions,
-                                                                                                &[
-                                                                                                    *i, *j, *k, *l, *m,
-                                                                                                    *n, *o, *p, *q, *r,
-                                                                                                    *s, *t, *u, *v
-                                                                                                ]
-                                                                                            ),
-                                                                                            0
-                                                                                        );
-                                                                                        assert_eq!(
-                                                                                            monad2(&[
-                                                                                                *i, *j, *k, *l, *m, *n,
-                                                                                                *o, *p, *q, *r, *s, *t,
-                                                                                                *u, *v
-                                                                                            ]),
-                                                                                            0
-                                                                                        );
-                                                                                        solutions.push([
-                                                                                            *i, *j, *k, *l, *m, *n, *o,
-                                                                                            *p, *q, *r, *s, *t, *u, *v,
-                                                                                        ]);
-                                                                                    }
-                                                                                }
-                                                                            }
-                                                                        }
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    solutions
+fn part2(instructions: &[String]) -> Result<i64, Box<dyn Error>> {
+    solve(instructions, false)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -245,9 +195,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let solutions = brute_force(&puzzle_lines);
-    writeln!(stdout, "Answer Part 1 = {:?}", solutions.last())?;
-    writeln!(stdout, "Answer Part 2 = {:?}", solutions.first())?;
+    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
+    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -265,20 +214,57 @@ mod tests {
     }
 
     #[test]
-    fn part1_actual() {
+    fn part1_actual() -> Result<(), Box<dyn Error>> {
         let instructions = get_test_data("input-actual");
-        let solutions = brute_force(&instructions);
-        assert_eq!(Some(&[9, 2, 7, 9, 3, 9, 4, 9, 4, 8, 9, 9, 9, 5]), solutions.last());
+        assert_eq!(part1(&instructions)?, 92793949489995);
         assert_eq!(0, monad1(&instructions, &[9, 2, 7, 9, 3, 9, 4, 9, 4, 8, 9, 9, 9, 5]));
-        assert_eq!(0, monad2(&[9, 2, 7, 9, 3, 9, 4, 9, 4, 8, 9, 9, 9, 5]));
+        Ok(())
     }
 
     #[test]
-    fn part2_actual() {
+    fn part2_actual() -> Result<(), Box<dyn Error>> {
         let instructions = get_test_data("input-actual");
-        let solutions = brute_force(&instructions);
-        assert_eq!(Some(&[5, 1, 1, 3, 1, 6, 1, 6, 1, 1, 2, 7, 8, 1]), solutions.first());
+        assert_eq!(part2(&instructions)?, 51131616112781);
         assert_eq!(0, monad1(&instructions, &[5, 1, 1, 3, 1, 6, 1, 6, 1, 1, 2, 7, 8, 1]));
-        assert_eq!(0, monad2(&[5, 1, 1, 3, 1, 6, 1, 6, 1, 1, 2, 7, 8, 1]));
+        Ok(())
+    }
+
+    // a MONAD listing from (div, add_x, add_y) per digit
+    fn listing(params: &[(i64, i64, i64)]) -> Vec<String> {
+        params
+            .iter()
+            .flat_map(|(div, add_x, add_y)| {
+                let mut p = [div, add_x, add_y].into_iter();
+                BLOCK.map(|line| match line.strip_suffix('_') {
+                    Some(prefix) => format!("{prefix}{}", p.next().unwrap()),
+                    None => line.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constraints() -> Result<(), Box<dyn Error>> {
+        // nested: digit[1] pairs with digit[2], digit[0] with digit[3]
+        let instructions = listing(&[(1, 12, 4), (1, 11, 0), (26, -3, 9), (26, -7, 5)]);
+        let blocks = get_blocks(&instructions)?;
+        assert_eq!(
+            get_constraints(&blocks)?,
+            vec![
+                Constraint { i: 1, j: 2, delta: -3 },
+                Constraint { i: 0, j: 3, delta: -3 }
+            ]
+        );
+        assert_eq!(part1(&instructions)?, 9966);
+        assert_eq!(part2(&instructions)?, 4411);
+
+        // unbalanced pushes and pops
+        assert!(get_constraints(&get_blocks(&listing(&[(1, 12, 4), (1, 11, 0)]))?).is_err());
+        assert!(get_constraints(&get_blocks(&listing(&[(26, -3, 4)]))?).is_err());
+        // digits 10 apart
+        assert!(part1(&listing(&[(1, 12, 4), (26, -14, 0)])).is_err());
+        // not a MONAD
+        assert!(get_blocks(&instructions[1..]).is_err());
+        Ok(())
     }
 }
@@ -1,3 +1,4 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use ndarray::{Array, Array2, ArrayView};
 use std::error::Error;
@@ -32,29 +33,27 @@ fn get_image(data: &[String]) -> Array2<Cell> {
     grid
 }
 
-#[allow(dead_code)]
-fn display(image: &Array2<Cell>) {
-    for row in image.rows() {
-        for elem in row {
-            match elem {
-                Cell::East => print!(">"),
-                Cell::South => print!("v"),
-                Cell::Empty => print!("."),
-            };
-        }
-        println!();
-    }
-    println!();
+fn display(anim: &mut Animation, image: &Array2<Cell>) -> io::Result<()> {
+    anim.draw(
+        "Sea Cucumber",
+        image.rows().into_iter().map(|row| {
+            row.into_iter().map(|elem| match elem {
+                Cell::East => '>',
+                Cell::South => 'v',
+                Cell::Empty => '.',
+            })
+        }),
+    )
 }
 
-fn solution1(image: &Array2<Cell>) -> usize {
-    //display(&image);
+fn solution1(image: &Array2<Cell>, anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let nrows = image.nrows();
     let ncols = image.ncols();
     let mut new_image = image.clone();
     let mut steps = 0;
 
     loop {
+        display(anim, &new_image)?;
         let mut stuck = true;
         steps += 1;
 
@@ -95,7 +94,7 @@ fn solution1(image: &Array2<Cell>) -> usize {
             break;
         }
     }
-    steps
+    Ok(steps)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -115,7 +114,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     // ==============================================================
 
     let image = get_image(&puzzle_lines);
-    writeln!(stdout, "Answer Part 1 = {}", solution1(&image))?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('>', Color::BrightYellow)
+        .with_color('v', Color::BrightCyan)
+        .with_color('.', Color::Blue);
+    writeln!(stdout, "Answer Part 1 = {}", solution1(&image, &mut anim)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -135,12 +138,12 @@ mod tests {
     #[test]
     fn part1_example() {
         let image = get_test_data("input-example");
-        assert_eq!(58, solution1(&image));
+        assert_eq!(58, solution1(&image, &mut Animation::default()).unwrap());
     }
 
     #[test]
     fn part1_actual() {
         let image = get_test_data("input-actual");
-        assert_eq!(360, solution1(&image));
+        assert_eq!(360, solution1(&image, &mut Animation::default()).unwrap());
     }
 }
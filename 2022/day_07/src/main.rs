@@ -1,102 +1,222 @@
 use general::{get_args, read_data_lines, reset_sigpipe};
 use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
-use std::path::PathBuf;
 
 #[derive(Debug)]
-enum Item {
-    File(PathBuf, usize),
+enum Kind {
+    Dir {
+        children: BTreeMap<String, usize>,
+        listed: bool,
+    },
+    File,
 }
-impl Item {
-    fn size(&self) -> usize {
-        match self {
-            Self::File(_, size) => *size,
+
+// a file's size, or for a directory the cached total size of everything below it
+#[derive(Debug)]
+struct Node {
+    name: String,
+    parent: Option<usize>,
+    size: usize,
+    kind: Kind,
+}
+
+// A directory tree rebuilt from a terminal transcript, nodes[0] is "/"
+#[derive(Debug)]
+struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    fn from_transcript(commands: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut fs = FileSystem {
+            nodes: vec![Node {
+                name: "/".to_string(),
+                parent: None,
+                size: 0,
+                kind: Kind::Dir {
+                    children: BTreeMap::new(),
+                    listed: false,
+                },
+            }],
+        };
+        let mut cwd = 0;
+        let mut listing = false;
+
+        for (n, line) in commands.iter().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let err = |msg: String| -> Box<dyn Error> { format!("line {}: {msg}", n + 1).into() };
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[..] {
+                ["$", "cd", "/"] => (cwd, listing) = (0, false),
+                ["$", "cd", ".."] => {
+                    cwd = fs.nodes[cwd].parent.ok_or_else(|| err("cd .. from /".into()))?;
+                    listing = false;
+                }
+                ["$", "cd", name] => {
+                    cwd = match fs.child(cwd, name) {
+                        Some(c) if matches!(fs.nodes[c].kind, Kind::Dir { .. }) => c,
+                        Some(c) => return Err(err(format!("cd into file {}", fs.path(c)))),
+                        None => return Err(err(format!("cd into unknown directory {name} in {}", fs.path(cwd)))),
+                    };
+                    listing = false;
+                }
+                ["$", "ls"] => {
+                    if let Kind::Dir { listed, .. } = &mut fs.nodes[cwd].kind {
+                        if *listed {
+                            return Err(err(format!("{} is listed twice", fs.path(cwd))));
+                        }
+                        *listed = true;
+                    }
+                    listing = true;
+                }
+                ["$", ..] => return Err(err(format!("unknown command {line:?}"))),
+                [attr, name] if listing => {
+                    let (size, kind) = match attr {
+                        "dir" => (
+                            0,
+                            Kind::Dir {
+                                children: BTreeMap::new(),
+                                listed: false,
+                            },
+                        ),
+                        _ => (
+                            attr.parse::<usize>().map_err(|e| err(format!("{e}: {line:?}")))?,
+                            Kind::File,
+                        ),
+                    };
+                    if fs.child(cwd, name).is_some() {
+                        return Err(err(format!("{name} is listed twice in {}", fs.path(cwd))));
+                    }
+                    fs.nodes.push(Node {
+                        name: name.to_string(),
+                        parent: Some(cwd),
+                        size,
+                        kind,
+                    });
+                    let id = fs.nodes.len() - 1;
+                    if let Kind::Dir { children, .. } = &mut fs.nodes[cwd].kind {
+                        children.insert(name.to_string(), id);
+                    }
+                }
+                _ => return Err(err(format!("unexpected output {line:?}"))),
+            }
+        }
+
+        // children always come after their parent so one backwards pass totals every directory
+        for id in (1..fs.nodes.len()).rev() {
+            let (size, parent) = (fs.nodes[id].size, fs.nodes[id].parent.unwrap());
+            fs.nodes[parent].size += size;
         }
+        Ok(fs)
     }
-}
 
-#[rustfmt::skip]
-fn build_filesystem_view(commands: &[String]) -> Result<BTreeMap<String, Item>, Box<dyn Error>> {
-    let mut fs = BTreeMap::new();
-    let mut root = PathBuf::new();
-    root.push("/");
-    fs.insert(root.display().to_string(), Item::File(root.clone(), 0));
+    fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        match &self.nodes[dir].kind {
+            Kind::Dir { children, .. } => children.get(name).copied(),
+            Kind::File => None,
+        }
+    }
 
-    for line in commands {
-        if line.starts_with("$ ls") {
-            continue;
+    fn path(&self, id: usize) -> String {
+        match self.nodes[id].parent {
+            None => "/".to_string(),
+            Some(0) => format!("/{}", self.nodes[id].name),
+            Some(p) => format!("{}/{}", self.path(p), self.nodes[id].name),
         }
+    }
 
-        let mut path = PathBuf::new();
-        path.push(root.clone());
+    fn used(&self) -> usize {
+        self.nodes[0].size
+    }
 
-        // hash key: path as a string
-        let hashkey = |path: PathBuf| path.display().to_string();
+    // (path, total size) of every directory
+    fn dirs(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| matches!(n.kind, Kind::Dir { .. }))
+            .map(|(id, n)| (self.path(id), n.size))
+    }
 
-        if line.starts_with("$ cd") {
-            // update path
-            match &line[5..] {
-                ".." => { path.pop(); }
-                arg => { path.push(arg); }
-            }
+    // the smallest directory which frees at least `needed` bytes when deleted
+    fn smallest_dir_freeing(&self, needed: usize) -> Option<(String, usize)> {
+        self.dirs()
+            .filter(|(_, size)| *size >= needed)
+            .min_by_key(|(_, size)| *size)
+    }
 
-            // set the root pointer from the path
-            let pathkey = hashkey(path.clone());
-            root = match fs.get(&pathkey) {
-                Some(Item::File(path, _)) => path.to_path_buf(),
-                _ => return Err(Box::from(format!("Unknown directory: {pathkey}"))),
-            };
-        } else {
-            let mut listing = line.split_whitespace();
-            if let (Some(attr), Some(name)) = (listing.next(), listing.next()) {
-                // update path
-                path.push(name);
-
-                // insert the full path of the item with a size: dir(0) or filesize
-                let pathkey = hashkey(path.clone());
-                let size = match attr == "dir" {
-                    true => 0,
-                    false => attr.parse::<usize>()?,
-                };
-                fs.insert(pathkey, Item::File(path, size));
+    // like "du -h /", directories after their contents
+    fn du(&self) -> String {
+        let mut out = String::new();
+        self.du_walk(0, &mut out);
+        out
+    }
+
+    fn du_walk(&self, id: usize, out: &mut String) {
+        if let Kind::Dir { children, .. } = &self.nodes[id].kind {
+            for c in children.values() {
+                self.du_walk(*c, out);
             }
+            out.push_str(&format!("{}\t{}\n", human(self.nodes[id].size), self.path(id)));
         }
     }
 
-    Ok(fs)
+    fn tree_walk(&self, id: usize, prefix: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Kind::Dir { children, .. } = &self.nodes[id].kind else {
+            return Ok(());
+        };
+        for (i, c) in children.values().enumerate() {
+            let last = i + 1 == children.len();
+            let node = &self.nodes[*c];
+            let branch = if last { "└── " } else { "├── " };
+            match node.kind {
+                Kind::Dir { .. } => writeln!(f, "{prefix}{branch}{}/ ({})", node.name, node.size)?,
+                Kind::File => writeln!(f, "{prefix}{branch}{} ({})", node.name, node.size)?,
+            }
+            let indent = if last { "    " } else { "│   " };
+            self.tree_walk(*c, &format!("{prefix}{indent}"), f)?;
+        }
+        Ok(())
+    }
 }
 
-fn get_dir_size(dir: &str, fs: &BTreeMap<String, Item>) -> usize {
-    fs.iter()
-        .filter(|(path, _)| path.starts_with(dir))
-        .map(|(_, item)| item.size())
-        .sum::<usize>()
+// like "tree", with sizes in bytes
+impl fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "/ ({})", self.used())?;
+        self.tree_walk(0, "", f)
+    }
 }
 
-fn get_dir_sizes(fs: &BTreeMap<String, Item>) -> Vec<usize> {
-    fs.iter()
-        .map(|(path, _)| get_dir_size(&(path.to_owned() + "/"), fs))
-        .collect::<Vec<_>>()
+// sizes in powers of 1024 rounded up, 1 decimal below 10, e.g. 584, 4.0K, 23M
+fn human(size: usize) -> String {
+    let mut value = size as f64;
+    for unit in ["", "K", "M", "G", "T"] {
+        if value < 1024.0 || unit == "T" {
+            return match (unit, value < 10.0) {
+                ("", _) => format!("{size}"),
+                (_, true) => format!("{:.1}{unit}", (value * 10.0).ceil() / 10.0),
+                (_, false) => format!("{}{unit}", value.ceil()),
+            };
+        }
+        value /= 1024.0;
+    }
+    unreachable!()
 }
 
 fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let fs = build_filesystem_view(puzzle_lines)?;
-    Ok(get_dir_sizes(&fs)
-        .iter()
-        .filter(|s| *s <= &100000)
-        .sum::<usize>())
+    let fs = FileSystem::from_transcript(puzzle_lines)?;
+    Ok(fs.dirs().map(|(_, size)| size).filter(|s| *s <= 100000).sum::<usize>())
 }
 
 fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let fs = build_filesystem_view(puzzle_lines)?;
-    let free = 70000000 - get_dir_size("/", &fs);
-    Ok(get_dir_sizes(&fs)
-        .iter()
-        .filter(|s| free + *s >= 30000000)
-        .copied()
-        .min()
-        .expect("no solution"))
+    let fs = FileSystem::from_transcript(puzzle_lines)?;
+    let free = 70000000_usize.checked_sub(fs.used()).ok_or("the disk is overfull")?;
+    match fs.smallest_dir_freeing(30000000_usize.saturating_sub(free)) {
+        Some((_, size)) => Ok(size),
+        None => Err("no directory frees enough space".into()),
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -115,6 +235,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
+    // the directory tree and its disk usage
+    if args.get_flag("verbose") {
+        let fs = FileSystem::from_transcript(&puzzle_lines)?;
+        write!(stdout, "{fs}{}", fs.du())?;
+    }
     writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
     writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
 
@@ -160,4 +285,29 @@ mod tests {
         assert_eq!(part2(&puzzle_lines)?, 1623571);
         Ok(())
     }
+
+    #[test]
+    fn reports() -> Result<(), Box<dyn Error>> {
+        let fs = FileSystem::from_transcript(&get_data("input-example"))?;
+        let tree = fs.to_string();
+        assert!(tree.starts_with("/ (48381165)\n├── a/ (94853)\n│   ├── e/ (584)\n│   │   └── i (584)\n"));
+        assert!(tree.ends_with("    └── k (7214296)\n"));
+        assert_eq!(fs.du(), "584\t/a/e\n93K\t/a\n24M\t/d\n47M\t/\n");
+        assert_eq!(fs.smallest_dir_freeing(90000), Some(("/a".to_string(), 94853)));
+        Ok(())
+    }
+
+    #[test]
+    fn impossible_transcripts() {
+        let check =
+            |lines: &[&str]| FileSystem::from_transcript(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert!(check(&["$ cd /", "$ ls", "dir a", "$ cd a", "$ cd ..", "$ cd /"]).is_ok());
+        assert!(check(&["$ cd /", "$ cd a"]).is_err());
+        assert!(check(&["$ cd /", "$ ls", "1 a", "$ cd a"]).is_err());
+        assert!(check(&["$ cd /", "$ cd .."]).is_err());
+        assert!(check(&["$ cd /", "$ ls", "1 a", "$ ls"]).is_err());
+        assert!(check(&["$ cd /", "$ ls", "1 a", "dir a"]).is_err());
+        assert!(check(&["$ cd /", "1 a"]).is_err());
+        assert!(check(&["$ rm -rf /"]).is_err());
+    }
 }
@@ -1,160 +1,298 @@
 use general::{get_args, read_data_lines, reset_sigpipe};
 use regex::Regex;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
 
+// the right hand side of "Operation: new = ...", e.g. old * old or old * 19 + 3
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Old,
+    Const(u64),
+    Op(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr {
+    // usual precedence, * and / before + and -, left to right otherwise
+    fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        let tokens = s.split_whitespace().collect::<Vec<_>>();
+        let operand = |t: &str| match t {
+            "old" => Ok(Expr::Old),
+            n => n
+                .parse()
+                .map(Expr::Const)
+                .map_err(|_| format!("bad operand {n:?} in {s:?}")),
+        };
+        if tokens.len() % 2 == 0 {
+            return Err(format!("bad operation {s:?}").into());
+        }
+
+        // terms separated by + and -, each a product of operands
+        let mut terms: Vec<(char, Expr)> = vec![];
+        let mut product = operand(tokens[0])?;
+        for pair in tokens[1..].chunks(2) {
+            let rhs = operand(pair[1])?;
+            match pair[0] {
+                "*" | "/" => product = Expr::Op(Box::new(product), pair[0].chars().next().unwrap(), Box::new(rhs)),
+                "+" | "-" => {
+                    terms.push((pair[0].chars().next().unwrap(), product));
+                    product = rhs;
+                }
+                op => return Err(format!("bad operator {op:?} in {s:?}").into()),
+            }
+        }
+        terms.push(('+', product));
+
+        let mut terms = terms.into_iter();
+        let (mut op, mut expr) = terms.next().unwrap();
+        for (next_op, term) in terms {
+            expr = Expr::Op(Box::new(expr), op, Box::new(term));
+            op = next_op;
+        }
+        Ok(expr)
+    }
+
+    fn has_division(&self) -> bool {
+        match self {
+            Expr::Op(a, op, b) => *op == '/' || a.has_division() || b.has_division(),
+            _ => false,
+        }
+    }
+
+    // new worry level, reduced modulo `modulus` when given, errors rather than overflowing
+    fn eval(&self, old: u64, modulus: Option<u64>) -> Result<u64, Box<dyn Error>> {
+        let reduce = |n: u64| match modulus {
+            Some(m) => n % m,
+            None => n,
+        };
+        Ok(match self {
+            Expr::Old => reduce(old),
+            Expr::Const(n) => reduce(*n),
+            Expr::Op(a, op, b) => {
+                let (a, b) = (a.eval(old, modulus)?, b.eval(old, modulus)?);
+                let n = match (op, modulus) {
+                    ('+', _) => a.checked_add(b),
+                    ('-', Some(m)) => a.checked_add(m - b),
+                    ('-', None) => a.checked_sub(b),
+                    ('*', _) => a.checked_mul(b),
+                    ('/', None) if b == 0 => return Err(format!("division by zero evaluating {a} / {b}").into()),
+                    ('/', None) => a.checked_div(b),
+                    _ => return Err(format!("can't evaluate {a} {op} {b} modulo {modulus:?}").into()),
+                };
+                reduce(n.ok_or_else(|| format!("worry level overflow evaluating {a} {op} {b}"))?)
+            }
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Const(n) => write!(f, "{n}"),
+            Expr::Op(a, op, b) => write!(f, "({a} {op} {b})"),
+        }
+    }
+}
+
 #[rustfmt::skip]
 #[derive(Clone, Debug)]
 pub struct Monkey {
     id: usize,             // monkey
     items: Vec<u64>,       // Starting items lists your worry level for each item the
                            // monkey is currently holding in the order they will be inspected.
-    operation_op: String,  // operation shows how your worry level changes
+    operation: Expr,       // operation shows how your worry level changes
                            // as that monkey inspects an item.
                            // (An operation like new = old * 5 means that your worry level
                            // after the monkey inspected the item is five times whatever your
                            // worry level was before inspection.)
-    operation_var: Option<u64>,
     test: u64,             // Test shows how the monkey uses your worry
                            // level to decide where to throw an item next.
     if_true: usize,        // If true shows what happens with an item if the Test was true.
     if_false: usize,       // If false shows what happens with an item if the Test was false.
 }
-impl Monkey {
+
+impl Default for Monkey {
     fn default() -> Self {
         Self {
             id: 0,
             items: vec![],
-            operation_op: "".into(),
-            operation_var: None,
-            test: 0,
+            operation: Expr::Old,
+            test: 1,
             if_true: 0,
             if_false: 0,
         }
     }
-    fn calc(&self, item: u64, decrease_worry_factor: u64) -> (usize, u64) {
-        let var = match self.operation_var {
-            Some(num) => num,
-            _ => item, // "old"
-        };
-
-        let worry_level = match self.operation_op == "*" {
-            true => item * var / decrease_worry_factor,
-            _ => (item + var) / decrease_worry_factor,
-        };
-
-        match worry_level % self.test {
-            0 => (self.if_true, worry_level),
-            _ => (self.if_false, worry_level),
-        }
-    }
 }
 
 fn get_monkeys(puzzle_lines: &[String]) -> Result<Vec<Monkey>, Box<dyn Error>> {
     let monkey_re = Regex::new(r"Monkey (\d+):")?;
     let items_re = Regex::new(r"^\s+Starting items: (.*)")?;
-    let operation_re = Regex::new(r"^\s+Operation: new = old\s+(\S+)\s+(\S+)")?;
+    let operation_re = Regex::new(r"^\s+Operation: new = (.*)")?;
     let test_re = Regex::new(r"^\s+Test: divisible by (\d+)")?;
     let if_true_re = Regex::new(r"^\s+If true: throw to monkey (\d+)")?;
     let if_false_re = Regex::new(r"^\s+If false: throw to monkey (\d+)")?;
 
     let mut monkeys = vec![];
     let mut monkey = Monkey::default();
+    let mut started = false;
 
     for line in puzzle_lines {
-        if monkey_re.is_match(line) {
-            let captures = monkey_re.captures(line).unwrap();
-            monkey.id = captures
-                .get(1)
-                .map(|s| s.as_str().parse::<usize>().unwrap())
-                .unwrap();
-        } else if items_re.is_match(line) {
-            let captures = items_re.captures(line).unwrap();
-            monkey.items = captures
-                .get(1)
-                .map(|s| s.as_str())
-                .unwrap()
+        if let Some(captures) = monkey_re.captures(line) {
+            monkey.id = captures[1].parse()?;
+            started = true;
+        } else if let Some(captures) = items_re.captures(line) {
+            monkey.items = captures[1]
                 .split(',')
-                .map(|n| n.trim().parse::<u64>().unwrap())
-                .collect::<Vec<_>>()
-        } else if operation_re.is_match(line) {
-            let captures = operation_re.captures(line).unwrap();
-            monkey.operation_op = captures.get(1).map(|s| s.as_str()).unwrap().to_string();
-            monkey.operation_var = captures
-                .get(2)
-                .map(|s| {
-                    if s.as_str() == "old" {
-                        None
-                    } else {
-                        Some(s.as_str().parse::<u64>().unwrap())
-                    }
-                })
-                .unwrap()
-        } else if test_re.is_match(line) {
-            let captures = test_re.captures(line).unwrap();
-            monkey.test = captures
-                .get(1)
-                .map(|s| s.as_str().parse::<u64>().unwrap())
-                .unwrap()
-        } else if if_true_re.is_match(line) {
-            let captures = if_true_re.captures(line).unwrap();
-            monkey.if_true = captures
-                .get(1)
-                .map(|s| s.as_str().parse::<usize>().unwrap())
-                .unwrap()
-        } else if if_false_re.is_match(line) {
-            let captures = if_false_re.captures(line).unwrap();
-            monkey.if_false = captures
-                .get(1)
-                .map(|s| s.as_str().parse::<usize>().unwrap())
-                .unwrap()
+                .map(|n| n.trim().parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()?;
+        } else if let Some(captures) = operation_re.captures(line) {
+            monkey.operation = Expr::parse(&captures[1])?;
+        } else if let Some(captures) = test_re.captures(line) {
+            monkey.test = captures[1].parse()?;
+        } else if let Some(captures) = if_true_re.captures(line) {
+            monkey.if_true = captures[1].parse()?;
+        } else if let Some(captures) = if_false_re.captures(line) {
+            monkey.if_false = captures[1].parse()?;
         } else if line.trim().is_empty() {
-            monkeys.push(monkey.clone())
+            if started {
+                monkeys.push(std::mem::take(&mut monkey));
+                started = false;
+            }
+        } else {
+            return Err(format!("unexpected line {line:?}").into());
         }
     }
-    monkeys.push(monkey);
+    if started {
+        monkeys.push(monkey);
+    }
     Ok(monkeys)
 }
 
-fn inspect(monkeys: &[Monkey], rounds: usize, part: u8) -> Result<usize, Box<dyn Error>> {
-    // puzzle part 1: divide by 3
-    // puzzle part 2: no decrease (divide by 1)
-    let decrease_worry_factor = if part == 1 { 3 } else { 1 };
+// what happens to your worry level after a monkey inspects an item
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Relief {
+    // puzzle part 1: divided by 3
+    Divide(u64),
+    // puzzle part 2: unchanged, it is only kept modulo the product of all the tests
+    Modulo,
+}
+
+#[derive(Clone, Debug)]
+struct Troop {
+    monkeys: Vec<Monkey>,
+    relief: Relief,
+    modulus: Option<u64>,
+    inspections: Vec<usize>,
+}
+
+impl Troop {
+    fn new(monkeys: &[Monkey], relief: Relief) -> Result<Self, Box<dyn Error>> {
+        for (i, m) in monkeys.iter().enumerate() {
+            if m.id != i {
+                return Err(format!("monkey {} is listed in position {i}", m.id).into());
+            }
+            if m.test == 0 || m.if_true >= monkeys.len() || m.if_false >= monkeys.len() {
+                return Err(format!("monkey {i} has a bad test").into());
+            }
+        }
 
-    // multiply the mod tests for all monkeys, e.g. 13 * 17 * 19 * 23
-    let lcm: u64 = monkeys.iter().map(|m| m.test).product();
+        // every test still sees the same remainders when worry levels are kept modulo
+        // the product of all the tests, e.g. 13 * 17 * 19 * 23
+        let modulus = match relief {
+            Relief::Divide(0) => return Err("can't divide worry levels by 0".into()),
+            Relief::Divide(_) => None,
+            Relief::Modulo => {
+                if let Some(m) = monkeys.iter().find(|m| m.operation.has_division()) {
+                    return Err(format!("monkey {} divides, worry can't be kept modulo the tests", m.id).into());
+                }
+                let product = monkeys
+                    .iter()
+                    .try_fold(1_u64, |p, m| p.checked_mul(m.test))
+                    .ok_or("product of the tests overflows")?;
+                Some(product)
+            }
+        };
 
-    let mut monkeys = monkeys.to_vec();
-    let mut inspected = vec![0; monkeys.len()];
+        Ok(Self {
+            monkeys: monkeys.to_vec(),
+            relief,
+            modulus,
+            inspections: vec![0; monkeys.len()],
+        })
+    }
 
-    for _ in 0..rounds {
-        for i in 0..monkeys.len() {
+    fn round(&mut self) -> Result<(), Box<dyn Error>> {
+        for i in 0..self.monkeys.len() {
             // count items to throw (per monkey)
-            inspected[i] += monkeys[i].items.len();
+            self.inspections[i] += self.monkeys[i].items.len();
 
-            // monkey business
-            for item in monkeys[i].items.clone() {
-                let (j, worry_level) = monkeys[i].calc(item, decrease_worry_factor);
-                monkeys[j].items.push(worry_level % lcm)
+            // monkey business, all items are thrown
+            for item in std::mem::take(&mut self.monkeys[i].items) {
+                let monkey = &self.monkeys[i];
+                let worry_level = match self.relief {
+                    Relief::Divide(n) => monkey.operation.eval(item, None)? / n,
+                    Relief::Modulo => monkey.operation.eval(item, self.modulus)?,
+                };
+                let j = match worry_level % monkey.test {
+                    0 => monkey.if_true,
+                    _ => monkey.if_false,
+                };
+                self.monkeys[j].items.push(worry_level);
             }
+        }
+        Ok(())
+    }
+
+    // times each monkey inspected an item
+    fn inspections(&self) -> &[usize] {
+        &self.inspections
+    }
 
-            // all items were thrown
-            monkeys[i].items.clear()
+    // product of the two most active monkeys' inspections
+    fn monkey_business(&self) -> usize {
+        let mut inspected = self.inspections().to_vec();
+        inspected.sort_by(|a, b| b.cmp(a));
+        inspected.iter().take(2).product()
+    }
+}
+
+// inspection histogram, one bar per monkey scaled to the busiest
+impl fmt::Display for Troop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max = self.inspections.iter().max().copied().unwrap_or(0).max(1);
+        for (m, n) in self.monkeys.iter().zip(&self.inspections) {
+            writeln!(f, "Monkey {} {:>8} {}", m.id, n, "#".repeat(n * 50 / max))?;
         }
+        Ok(())
     }
+}
 
-    inspected.sort_by(|a, b| b.cmp(a));
-    Ok(inspected[0] * inspected[1])
+// `histogram` collects the inspection histogram after the last round
+fn inspect(
+    monkeys: &[Monkey],
+    rounds: usize,
+    relief: Relief,
+    histogram: Option<&mut Vec<String>>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut troop = Troop::new(monkeys, relief)?;
+    for _ in 0..rounds {
+        troop.round()?;
+    }
+    if let Some(h) = histogram {
+        h.extend(troop.to_string().lines().map(String::from));
+    }
+    Ok(troop.monkey_business())
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part1(puzzle_lines: &[String], histogram: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
     let m = get_monkeys(puzzle_lines)?;
-    inspect(&m, 20, 1)
+    inspect(&m, 20, Relief::Divide(3), histogram)
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part2(puzzle_lines: &[String], histogram: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
     let m = get_monkeys(puzzle_lines)?;
-    inspect(&m, 10000, 2)
+    inspect(&m, 10000, Relief::Modulo, histogram)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -173,8 +311,18 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
-    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
+    let verbose = args.get_flag("verbose");
+    let mut histogram = vec![];
+    let n = part1(&puzzle_lines, verbose.then_some(&mut histogram))?;
+    for line in histogram.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 1 = {n}")?;
+    let n = part2(&puzzle_lines, verbose.then_some(&mut histogram))?;
+    for line in histogram {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -194,28 +342,66 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part1(&puzzle_lines)?, 10605);
+        assert_eq!(part1(&puzzle_lines, None)?, 10605);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part1(&puzzle_lines)?, 58056);
+        assert_eq!(part1(&puzzle_lines, None)?, 58056);
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part2(&puzzle_lines)?, 2713310158);
+        assert_eq!(part2(&puzzle_lines, None)?, 2713310158);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part2(&puzzle_lines)?, 15048718170);
+        assert_eq!(part2(&puzzle_lines, None)?, 15048718170);
+        Ok(())
+    }
+
+    #[test]
+    fn operations() -> Result<(), Box<dyn Error>> {
+        let e = Expr::parse("old * old + 3 * old - 2")?;
+        assert_eq!(e.to_string(), "(((old * old) + (3 * old)) - 2)");
+        assert_eq!(e.eval(5, None)?, 38);
+        assert_eq!(e.eval(5, Some(7))?, 38 % 7);
+        assert_eq!(Expr::parse("old / 2 - 1")?.eval(9, None)?, 3);
+        assert!(Expr::parse("old - 10")?.eval(9, None).is_err());
+        assert!(Expr::parse("old * old")?.eval(u64::MAX / 2, None).is_err());
+        assert!(Expr::parse("old ^ 2").is_err());
+        let e = Expr::parse("old / 0")?.eval(9, None).unwrap_err();
+        assert_eq!(e.to_string(), "division by zero evaluating 9 / 0");
+        Ok(())
+    }
+
+    #[test]
+    fn inspections() -> Result<(), Box<dyn Error>> {
+        let monkeys = get_monkeys(&get_data("input-example"))?;
+        let mut troop = Troop::new(&monkeys, Relief::Modulo)?;
+        for _ in 0..20 {
+            troop.round()?;
+        }
+        assert_eq!(troop.inspections(), &[99, 97, 8, 103]);
+        assert!(troop.to_string().starts_with("Monkey 0       99 ####"));
+
+        let mut histogram = vec![];
+        assert_eq!(part1(&get_data("input-example"), Some(&mut histogram))?, 10605);
+        assert_eq!(histogram.len(), 4);
+        assert!(histogram[3].starts_with("Monkey 3      105 #"));
+
+        // worry can't be reduced modulo the tests when a monkey divides
+        let mut monkeys = monkeys;
+        monkeys[1].operation = Expr::parse("old / 2")?;
+        assert!(Troop::new(&monkeys, Relief::Modulo).is_err());
+        assert!(Troop::new(&monkeys, Relief::Divide(3)).is_ok());
         Ok(())
     }
 }
@@ -1,94 +1,152 @@
 use general::{get_args, read_data_lines, reset_sigpipe};
 use std::cmp::Ordering;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
+use std::str::FromStr;
 
-#[macro_use]
-extern crate json;
-
-type List = json::JsonValue;
-// using json::JsonValue as a List abstraction
-//
-// the subset of JsonValue methods/macros used:
-//    JsonValue.is_number()
-//    JsonValue.is_array()
-//    JsonValue.is_null()
-//    JsonValue.as_u64()
-//    macro array![] to create a new list
-
-// consume the input data, returning a Vec of List pairs
-fn get_data(data: &[String]) -> Vec<(List, List)> {
-    let lists = data
-        .iter()
-        .filter(|line| !line.is_empty())
-        .map(|line| json::parse(line).expect("unparsable List"))
-        .collect::<Vec<_>>();
-
-    assert!(lists.len() % 2 == 0, "expecting pairs");
-
-    // create pairs
-    lists
-        .iter()
-        .step_by(2)
-        .zip(lists.iter().skip(1).step_by(2))
-        .map(|(a, b)| (a.clone(), b.clone()))
-        .collect()
+// Packets compare by the puzzle's ordering, which makes equality looser than structure:
+// an integer equals the one element list holding it, so 2 == [2] == [[2]]
+#[derive(Clone, Debug)]
+enum Packet {
+    Int(u64),
+    List(Vec<Packet>),
 }
 
-fn compare(left: &List, right: &List) -> Ordering {
-    match (left, right) {
-        (l, r) if l.is_number() && r.is_number() => l.as_u64().cmp(&r.as_u64()),
-        (l, r) if l.is_array() && r.is_array() => {
-            for i in 0..l.len().max(r.len()) {
-                if l[i].is_null() && r[i].is_null() {
-                    return Ordering::Equal;
-                } else if l[i].is_null() {
-                    return Ordering::Less;
-                } else if r[i].is_null() {
-                    return Ordering::Greater;
+// a cursor over the line, packets are built directly from the bytes
+fn parse(s: &[u8], i: &mut usize) -> Result<Packet, Box<dyn Error>> {
+    match s.get(*i) {
+        Some(b'[') => {
+            *i += 1;
+            let mut list = vec![];
+            if s.get(*i) == Some(&b']') {
+                *i += 1;
+                return Ok(Packet::List(list));
+            }
+            loop {
+                list.push(parse(s, i)?);
+                match s.get(*i) {
+                    Some(b',') => *i += 1,
+                    Some(b']') => {
+                        *i += 1;
+                        return Ok(Packet::List(list));
+                    }
+                    _ => return Err(format!("expected ',' or ']' at {i}").into()),
                 }
+            }
+        }
+        Some(c) if c.is_ascii_digit() => {
+            let mut n: u64 = 0;
+            while let Some(d) = s.get(*i).filter(|c| c.is_ascii_digit()) {
+                n = n
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add((d - b'0') as u64))
+                    .ok_or("integer overflow")?;
+                *i += 1;
+            }
+            Ok(Packet::Int(n))
+        }
+        _ => Err(format!("expected '[' or an integer at {i}").into()),
+    }
+}
 
-                let ordering = compare(&l[i], &r[i]);
-                if ordering != Ordering::Equal {
-                    return ordering;
+impl FromStr for Packet {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().as_bytes();
+        let mut i = 0;
+        let packet = parse(s, &mut i)?;
+        if i != s.len() {
+            return Err(format!("unexpected trailing input at {i}").into());
+        }
+        Ok(packet)
+    }
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Packet::Int(n) => write!(f, "{n}"),
+            Packet::List(list) => {
+                write!(f, "[")?;
+                for (i, p) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{p}")?;
                 }
+                write!(f, "]")
             }
-            Ordering::Equal
         }
-        (l, r) if l.is_number() => compare(&array![l.as_u64()], r),
-        (l, r) => {
-            assert!(r.is_number());
-            compare(l, &array![r.as_u64()])
+    }
+}
+
+// integers compare as numbers, lists element by element with the shorter list first
+// when they agree, and an integer compared with a list is treated as a one element list
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Packet::Int(a), Packet::Int(b)) => a.cmp(b),
+            (Packet::List(a), Packet::List(b)) => a.iter().cmp(b.iter()),
+            (Packet::Int(_), Packet::List(b)) => std::slice::from_ref(self).iter().cmp(b.iter()),
+            (Packet::List(a), Packet::Int(_)) => a.iter().cmp(std::slice::from_ref(other).iter()),
         }
     }
 }
 
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// equal under the ordering, so 2 == [2] == [[2]]
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Packet {}
+
+// consume the input data, returning a Vec of Packet pairs
+fn get_data(data: &[String]) -> Result<Vec<(Packet, Packet)>, Box<dyn Error>> {
+    let packets = data
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse())
+        .collect::<Result<Vec<Packet>, _>>()?;
+    if packets.len() % 2 != 0 {
+        return Err("expecting pairs".into());
+    }
+
+    // create pairs
+    Ok(packets.chunks(2).map(|p| (p[0].clone(), p[1].clone())).collect())
+}
+
 fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    Ok(get_data(puzzle_lines)
+    Ok(get_data(puzzle_lines)?
         .iter()
         .enumerate()
-        .map(|(i, p)| (i, compare(&p.0, &p.1)))
-        .filter(|(_, c)| *c == Ordering::Less)
+        .filter(|(_, p)| p.0 < p.1)
         .map(|(i, _)| i + 1)
         .sum())
 }
 
 fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let markers = [array!([[2]]), array!([[6]])];
+    let markers: [Packet; 2] = ["[[2]]".parse()?, "[[6]]".parse()?];
 
-    let mut packets = get_data(puzzle_lines)
-        .iter()
-        .flat_map(|p| [p.0.clone(), p.1.clone()])
-        .chain(markers.clone())
+    let packets = get_data(puzzle_lines)?
+        .into_iter()
+        .flat_map(|p| [p.0, p.1])
         .collect::<Vec<_>>();
 
-    packets.sort_by(compare);
-
-    Ok(packets
-        .into_iter()
+    // a marker's sorted position is the packets before it plus the earlier markers,
+    // counting rather than searching since packets like [2] compare equal to [[2]]
+    Ok(markers
+        .iter()
         .enumerate()
-        .filter(|(_, p)| markers.contains(p))
-        .map(|(i, _)| i + 1)
+        .map(|(k, m)| packets.iter().filter(|p| *p < m).count() + k + 1)
         .product())
 }
 
@@ -153,4 +211,67 @@ mod tests {
         assert_eq!(part2(&puzzle_lines)?, 21836);
         Ok(())
     }
+
+    // a small linear congruential generator so the property tests are repeatable
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % n
+        }
+
+        fn packet(&mut self, depth: usize) -> Packet {
+            match depth == 0 || self.below(3) == 0 {
+                true => Packet::Int(self.below(4)),
+                false => Packet::List((0..self.below(4)).map(|_| self.packet(depth - 1)).collect()),
+            }
+        }
+    }
+
+    #[test]
+    fn part2_equal_to_dividers() -> Result<(), Box<dyn Error>> {
+        // [2] and [[[6]]] compare equal to the dividers, which take the first place among ties
+        let puzzle_lines: Vec<String> = ["[2]", "[1]", "", "[[[6]]]", "[7]"].map(String::from).into();
+        assert_eq!(part2(&puzzle_lines)?, 2 * 4);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn Error>> {
+        for line in get_data("input-actual").iter().filter(|l| !l.is_empty()) {
+            assert_eq!(line.parse::<Packet>()?.to_string(), *line);
+        }
+        for bad in ["", "[", "[1,]", "[1]]", "[a]", "[1 2]", "[99999999999999999999]"] {
+            assert!(bad.parse::<Packet>().is_err(), "{bad}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn total_order() {
+        let mut rng = Lcg(13);
+        let packets: Vec<Packet> = (0..60).map(|_| rng.packet(3)).collect();
+        for a in &packets {
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            for b in &packets {
+                // antisymmetric
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a} {b}");
+                for c in &packets {
+                    // transitive
+                    if a <= b && b <= c {
+                        assert!(a <= c, "{a} {b} {c}");
+                    }
+                }
+            }
+        }
+
+        let mut sorted = packets.clone();
+        sorted.sort();
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!("[[2]]".parse::<Packet>().unwrap(), Packet::Int(2));
+    }
 }
@@ -1,146 +1,211 @@
 use general::{get_args, read_data_lines, reset_sigpipe};
+use num::{BigRational, ToPrimitive, Zero};
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
+use std::rc::Rc;
 
-fn get_data(data: &[String]) -> (HashMap<String, i64>, HashMap<String, Vec<String>>) {
-    let mut monkey_values = HashMap::new();
-    let mut monkey_exp = HashMap::new();
-
-    for line in data {
-        let values = line.split(':').collect::<Vec<&str>>();
-        let monkey_name = values[0].to_string();
-        let values = values[1].split_whitespace().collect::<Vec<&str>>();
-        if values.len() == 1 {
-            monkey_values.insert(monkey_name, values[0].parse::<i64>().unwrap());
-        } else {
-            monkey_exp.insert(
-                monkey_name,
-                values.into_iter().map(String::from).collect::<Vec<_>>(),
-            );
-        }
-    }
-    (monkey_values, monkey_exp)
+// what a monkey yells, either a number or an operation on two other monkeys
+#[derive(Debug, Clone)]
+enum Job {
+    Number(i64),
+    Operation(String, char, String),
 }
 
-// we have 2 maps
-//   1. monkey_values: key="monkey name", value=concrete i64 from an evaluation.
-//      (pre-populated with all k,v given to us)
-//   2. monkey_expressions: key="monkey name", value=["some monkey name", "+-*/", "some monkey name"]
-// returns the value for a "monkey name"
-#[rustfmt::skip]
-fn solve(
-    name: &str,
-    monkey_values: &HashMap<String, i64>,
-    monkey_expressions: &HashMap<String, Vec<String>>,
-) -> HashMap<String, i64> {
-    let mut values = monkey_values.clone();
-    let expressions = monkey_expressions;
-
-    // loop until name evaluates, could be forever
-    while !values.contains_key(name) {
-        for (k, v) in expressions {
-            if !values.contains_key(k) {
-                if let (Some(a), Some(b)) = (values.get(&v[0]), values.get(&v[2])) {
-                    let k = k.to_string();
-                    match v[1].as_ref() {
-                        "+" => { values.insert(k, a + b); }
-                        "-" => { values.insert(k, a - b); }
-                        "*" => { values.insert(k, a * b); }
-                        "/" => { values.insert(k, a / b); }
-                        _ => unreachable!(),
-                    }
-                }
+fn get_data(data: &[String]) -> Result<HashMap<String, Job>, Box<dyn Error>> {
+    let mut jobs = HashMap::new();
+    for line in data {
+        let (monkey_name, job) = line.split_once(':').ok_or_else(|| format!("bad monkey {line:?}"))?;
+        let values = job.split_whitespace().collect::<Vec<&str>>();
+        let job = match values[..] {
+            [n] => Job::Number(n.parse::<i64>()?),
+            [a, op, b] if op.len() == 1 && "+-*/".contains(op) => {
+                Job::Operation(a.to_string(), op.chars().next().unwrap(), b.to_string())
             }
-        }
+            _ => return Err(format!("bad job {line:?}").into()),
+        };
+        jobs.insert(monkey_name.trim().to_string(), job);
     }
-    values
+    Ok(jobs)
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<i64, Box<dyn Error>> {
-    let (monkey_values, monkey_expressions) = get_data(puzzle_lines);
-    let monkey_values = solve("root", &monkey_values, &monkey_expressions);
-    Ok(monkey_values["root"])
+// A symbolic expression, subexpressions are shared so the monkey tree is a DAG
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(BigRational),
+    Var(String),
+    Op(Rc<Expr>, char, Rc<Expr>),
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<i64, Box<dyn Error>> {
-    let (values, expressions) = get_data(puzzle_lines);
-
-    // save a copy
-    let orig_values = values.clone();
-
-    //
-    // this seems wonky with multiple solutions ???
-    // i guess the yell "immediately" means take the lowest
-    //
-
-    // grab the 2 monkey names which "root" depends on
-    let (ma, mb) = match expressions.get("root") {
-        Some(v) => (v[0].to_string(), v[2].to_string()),
-        _ => unreachable!(),
-    };
+fn apply(a: &BigRational, op: char, b: &BigRational) -> Result<BigRational, Box<dyn Error>> {
+    Ok(match op {
+        '+' => a + b,
+        '-' => a - b,
+        '*' => a * b,
+        '/' if b.is_zero() => return Err("division by zero".into()),
+        '/' => a / b,
+        _ => return Err(format!("unknown operator {op:?}").into()),
+    })
+}
 
-    // values "ma,mb" are a function of "humn"
-    // solve part1 to get initial guestimates
-    let mut values = solve("root", &values, &expressions);
+impl Expr {
+    fn number(&self) -> Option<&BigRational> {
+        match self {
+            Expr::Num(n) => Some(n),
+            _ => None,
+        }
+    }
 
-    // determine lo, hi ordering
-    let (ma, mb) = if values[&ma] < values[&mb] {
-        (ma, mb)
-    } else {
-        (mb, ma)
-    };
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Var(v) => v == name,
+            Expr::Op(a, _, b) => a.contains(name) || b.contains(name),
+        }
+    }
 
-    // take a swag at bounding where to search for "humn" by
-    // increasing values["humn"] looking for ma,mb to cross over
-    let mut humn = values["humn"];
-    let mut prev_humn = humn;
-    while values[&ma] < values[&mb] {
-        prev_humn = values["humn"]; // lower bound on exit
-        humn *= 2; // increase upper bound
+    // fold constant subexpressions and drop identities like x * 1 and x + 0
+    fn simplify(self: &Rc<Self>) -> Result<Rc<Expr>, Box<dyn Error>> {
+        let mut cache = HashMap::new();
+        self.simplify_cached(&mut cache)
+    }
 
-        // evaluate the expressions
-        values = orig_values.clone();
-        values.insert("humn".to_string(), humn);
-        values = solve("root", &values, &expressions);
+    fn simplify_cached(
+        self: &Rc<Self>,
+        cache: &mut HashMap<*const Expr, Rc<Expr>>,
+    ) -> Result<Rc<Expr>, Box<dyn Error>> {
+        if let Some(e) = cache.get(&Rc::as_ptr(self)) {
+            return Ok(e.clone());
+        }
+        let simplified = match self.as_ref() {
+            Expr::Op(a, op, b) => {
+                let (a, b) = (a.simplify_cached(cache)?, b.simplify_cached(cache)?);
+                let one = BigRational::from_integer(1.into());
+                match (a.number(), *op, b.number()) {
+                    (Some(x), _, Some(y)) => Rc::new(Expr::Num(apply(x, *op, y)?)),
+                    (_, '+' | '-', Some(y)) if y.is_zero() => a,
+                    (_, '*' | '/', Some(y)) if *y == one => a,
+                    (Some(x), '+', _) if x.is_zero() => b,
+                    (Some(x), '*', _) if *x == one => b,
+                    _ => Rc::new(Expr::Op(a, *op, b)),
+                }
+            }
+            _ => self.clone(),
+        };
+        cache.insert(Rc::as_ptr(self), simplified.clone());
+        Ok(simplified)
     }
+}
 
-    //println!("now searching for humn between {prev_humn} - {humn}");
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{n}"),
+            Expr::Var(v) => write!(f, "{v}"),
+            Expr::Op(a, op, b) => write!(f, "({a} {op} {b})"),
+        }
+    }
+}
 
-    let (mut lo, mut hi) = (prev_humn, humn);
-    let mut mid = 0;
-    while values[&ma] != values[&mb] {
-        mid = lo + (hi - lo) / 2;
+// build the expression for `name`, `unknown` is left as a variable
+fn build(
+    name: &str,
+    unknown: Option<&str>,
+    jobs: &HashMap<String, Job>,
+    built: &mut HashMap<String, Rc<Expr>>,
+) -> Result<Rc<Expr>, Box<dyn Error>> {
+    if let Some(e) = built.get(name) {
+        return Ok(e.clone());
+    }
+    let e = match jobs.get(name) {
+        _ if Some(name) == unknown => Rc::new(Expr::Var(name.to_string())),
+        Some(Job::Number(n)) => Rc::new(Expr::Num(BigRational::from_integer((*n).into()))),
+        Some(Job::Operation(a, op, b)) => {
+            let a = build(a, unknown, jobs, built)?;
+            let b = build(b, unknown, jobs, built)?;
+            Rc::new(Expr::Op(a, *op, b))
+        }
+        None => return Err(format!("unknown monkey {name:?}").into()),
+    };
+    built.insert(name.to_string(), e.clone());
+    Ok(e)
+}
 
-        // evaluate the expressions at `mid`
-        values = orig_values.clone();
-        values.insert("humn".to_string(), mid);
-        values = solve("root", &values, &expressions);
+// the two sides root compares once `unknown` is a variable, simplified
+fn equation(jobs: &HashMap<String, Job>, unknown: &str) -> Result<(Rc<Expr>, Rc<Expr>), Box<dyn Error>> {
+    let Some(Job::Operation(a, _, b)) = jobs.get("root") else {
+        return Err("root must be an operation".into());
+    };
+    let mut built = HashMap::new();
+    let lhs = build(a, Some(unknown), jobs, &mut built)?.simplify()?;
+    let rhs = build(b, Some(unknown), jobs, &mut built)?.simplify()?;
+    Ok((lhs, rhs))
+}
 
-        //println!("tried {mid} distance between root monkeys {ma} - {mb} = {}", values[&ma] - values[&mb]);
+// solve lhs = rhs for `unknown` by inverting the operations on the path down to it
+fn solve(lhs: &Rc<Expr>, rhs: &Rc<Expr>, unknown: &str) -> Result<BigRational, Box<dyn Error>> {
+    let (mut lhs, mut value) = match (lhs.contains(unknown), rhs.contains(unknown), lhs.number(), rhs.number()) {
+        (true, false, _, Some(n)) => (lhs.clone(), n.clone()),
+        (false, true, Some(n), _) => (rhs.clone(), n.clone()),
+        (false, false, _, _) => return Err(format!("{unknown} doesn't appear in {lhs} = {rhs}").into()),
+        _ => return Err(format!("can't isolate {unknown} in {lhs} = {rhs}").into()),
+    };
 
-        // pick a side
-        (lo, hi) = match values[&ma] < values[&mb] {
-            true => (mid, hi),
-            false => (lo, mid),
+    loop {
+        let next = match lhs.as_ref() {
+            Expr::Var(_) => return Ok(value),
+            Expr::Op(a, op, b) => match (a.number(), *op, b.number()) {
+                // unknown on the left, x op b = value
+                (None, '+', Some(b)) => (a, &value - b),
+                (None, '-', Some(b)) => (a, &value + b),
+                (None, '*', Some(b)) => (a, apply(&value, '/', b)?),
+                (None, '/', Some(b)) => (a, &value * b),
+                // unknown on the right, a op x = value
+                (Some(a), '+', None) => (b, &value - a),
+                (Some(a), '-', None) => (b, a - &value),
+                (Some(a), '*', None) => (b, apply(&value, '/', a)?),
+                (Some(a), '/', None) => (b, apply(a, '/', &value)?),
+                _ => return Err(format!("can't isolate {unknown} in {lhs}").into()),
+            },
+            Expr::Num(_) => unreachable!(),
         };
+        (lhs, value) = (next.0.clone(), next.1);
     }
+}
 
-    // i'm getting multiple solutions ???
-    let mut solutions = vec![];
-    for wtf in mid - 10..mid + 10 {
-        values = orig_values.clone();
-        values.insert("humn".to_string(), wtf);
-        values = solve("root", &values, &expressions);
-        if values[&ma] == values[&mb] {
-            solutions.push(wtf);
-        }
+fn to_integer(n: BigRational) -> Result<i64, Box<dyn Error>> {
+    match n.is_integer() {
+        true => n
+            .to_integer()
+            .to_i64()
+            .ok_or_else(|| format!("{n} doesn't fit an i64").into()),
+        false => Err(format!("{n} is not an integer").into()),
     }
+}
 
-    //println!("solutions = {:?}", solutions);
+// the value root yells
+fn part1(puzzle_lines: &[String]) -> Result<i64, Box<dyn Error>> {
+    let jobs = get_data(puzzle_lines)?;
+    let root = build("root", None, &jobs, &mut HashMap::new())?.simplify()?;
+    match root.number() {
+        Some(n) => to_integer(n.clone()),
+        None => Err(format!("root didn't reduce to a number: {root}").into()),
+    }
+}
 
-    assert!(!solutions.is_empty());
-    Ok(solutions[0])
+// the value `unknown` must yell for both sides of root to be equal, `shown` collects the simplified equation
+fn solve_for(puzzle_lines: &[String], unknown: &str, shown: Option<&mut Vec<String>>) -> Result<i64, Box<dyn Error>> {
+    let jobs = get_data(puzzle_lines)?;
+    let (lhs, rhs) = equation(&jobs, unknown)?;
+    if let Some(shown) = shown {
+        shown.push(format!("{lhs} = {rhs}"));
+    }
+    to_integer(solve(&lhs, &rhs, unknown)?)
+}
+
+fn part2(puzzle_lines: &[String], shown: Option<&mut Vec<String>>) -> Result<i64, Box<dyn Error>> {
+    solve_for(puzzle_lines, "humn", shown)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -160,7 +225,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // ==============================================================
 
     writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
-    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
+    let mut shown = vec![];
+    let n = part2(&puzzle_lines, args.get_flag("verbose").then_some(&mut shown))?;
+    for line in shown {
+        writeln!(stdout, "{line}")?;
+    }
+    writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -177,6 +247,34 @@ mod tests {
         read_data_lines(Some(&file)).unwrap()
     }
 
+    #[test]
+    fn equation_example() -> Result<(), Box<dyn Error>> {
+        let jobs = super::get_data(&get_data("input-example"))?;
+        let (lhs, rhs) = equation(&jobs, "humn")?;
+        assert_eq!(lhs.to_string(), "((4 + (2 * (humn - 3))) / 4)");
+        assert_eq!(rhs.to_string(), "150");
+
+        let mut shown = vec![];
+        assert_eq!(part2(&get_data("input-example"), Some(&mut shown))?, 301);
+        assert_eq!(shown, ["((4 + (2 * (humn - 3))) / 4) = 150"]);
+        Ok(())
+    }
+
+    #[test]
+    fn other_unknowns() -> Result<(), Box<dyn Error>> {
+        let puzzle_lines = get_data("input-example");
+        // with humn yelling 5, (sllz + 2 * (5 - 3)) / 4 = 150
+        assert_eq!(solve_for(&puzzle_lines, "sllz", None)?, 596);
+        assert!(solve_for(&puzzle_lines, "root", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn non_integer_root() {
+        let puzzle_lines = ["root: a + b", "a: humn * c", "c: 2", "b: 7", "humn: 1"].map(String::from);
+        assert!(solve_for(&puzzle_lines, "humn", None).is_err());
+    }
+
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
@@ -194,14 +292,14 @@ mod tests {
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part2(&puzzle_lines)?, 301);
+        assert_eq!(part2(&puzzle_lines, None)?, 301);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part2(&puzzle_lines)?, 3343167719435);
+        assert_eq!(part2(&puzzle_lines, None)?, 3343167719435);
         Ok(())
     }
 }
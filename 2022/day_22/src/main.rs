@@ -1,5 +1,4 @@
-// this is so ugly having 2 different cube foldings, i don't know how to generalize it?
-//
+use general::render::{Dumper, Palette, BLACK, BLUE, CYAN, GRAY, GREEN, MAGENTA, ORANGE, RED, YELLOW};
 use general::{get_args, read_data_lines, reset_sigpipe};
 use ndarray::*;
 use std::error::Error;
@@ -8,11 +7,7 @@ use std::io::{self, Write};
 fn get_data(data: &[String]) -> (Vec<usize>, Vec<char>, Array2<char>) {
     let get_row = |s: &str| s.chars().collect::<Vec<_>>();
 
-    let m = data[0..data.len() - 2]
-        .iter()
-        .map(|s| s.len())
-        .max()
-        .unwrap();
+    let m = data[0..data.len() - 2].iter().map(|s| s.len()).max().unwrap();
     let mut grid = Array::from_elem((0, m + 2), ' ');
 
     let mut border = String::new();
@@ -89,13 +84,200 @@ fn gmove1(coord: (usize, usize), grid: &Array2<char>, direction: usize) -> Optio
     }
 }
 
-fn gmove2(
-    coord: (usize, usize),
-    grid: &Array2<char>,
-    faces_origin: &[(usize, usize)],
-    direction: usize,
+// a 3d unit vector along one of the cube axes
+type V3 = [i64; 3];
+
+fn neg(v: V3) -> V3 {
+    [-v[0], -v[1], -v[2]]
+}
+
+// one face of the net and where it ends up once folded
+//
+// normal points out of the cube, right and down are the directions of
+// increasing column and row on the face
+#[derive(Clone, Debug)]
+struct Face {
+    origin: (usize, usize),
+    normal: V3,
+    right: V3,
+    down: V3,
+}
+
+impl Face {
+    // the 3d direction of travel for a direction (0=right, 1=down, 2=left, 3=up)
+    fn heading(&self, direction: usize) -> V3 {
+        match direction {
+            0 => self.right,
+            1 => self.down,
+            2 => neg(self.right),
+            3 => neg(self.down),
+            _ => unreachable!(),
+        }
+    }
+
+    // the 3d direction of increasing offset along the edge crossed going `direction`
+    fn along(&self, direction: usize) -> V3 {
+        match direction % 2 {
+            0 => self.down,
+            _ => self.right,
+        }
+    }
+}
+
+// A cube net folded up so walking off any face edge lands on the adjoining face
+#[derive(Clone, Debug)]
+struct Cube {
     sz: usize,
-) -> Option<(usize, usize, usize)> {
+    faces: Vec<Face>,
+}
+
+impl Cube {
+    // fold any of the 11 cube nets, of any face size, from a padded grid
+    fn fold(grid: &Array2<char>) -> Result<Cube, Box<dyn Error>> {
+        let tiles = grid.iter().filter(|c| **c != ' ').count();
+        let sz = (1..).find(|sz| 6 * sz * sz >= tiles).unwrap();
+        if 6 * sz * sz != tiles {
+            return Err(format!("{tiles} tiles can't be 6 square faces").into());
+        }
+
+        // faces by their (row, col) position on the net counted in face sizes
+        let mut net = vec![];
+        for fr in 0..(grid.nrows() - 2) / sz {
+            for fc in 0..(grid.ncols() - 2) / sz {
+                let origin = (1 + fr * sz, 1 + fc * sz);
+                if grid[origin] == ' ' {
+                    continue;
+                }
+                if grid
+                    .slice(s![origin.0..origin.0 + sz, origin.1..origin.1 + sz])
+                    .iter()
+                    .any(|c| *c == ' ')
+                {
+                    return Err(format!("face at {origin:?} isn't a full {sz}x{sz} square").into());
+                }
+                net.push((fr, fc));
+            }
+        }
+        if net.len() != 6 {
+            return Err(format!("expected 6 faces of size {sz}, found {}", net.len()).into());
+        }
+
+        // roll the cube across the net, each neighbour tips over the shared edge
+        let mut faces: Vec<Option<Face>> = vec![None; 6];
+        faces[0] = Some(Face {
+            origin: (1 + net[0].0 * sz, 1 + net[0].1 * sz),
+            normal: [0, 0, 1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        });
+        let mut workq = vec![0];
+        while let Some(i) = workq.pop() {
+            let f = faces[i].clone().unwrap();
+            let (fr, fc) = net[i];
+            let neighbours = [
+                ((fr, fc + 1), f.right, neg(f.normal), f.down),
+                ((fr + 1, fc), f.down, f.right, neg(f.normal)),
+                ((fr, fc.wrapping_sub(1)), neg(f.right), f.normal, f.down),
+                ((fr.wrapping_sub(1), fc), neg(f.down), f.right, f.normal),
+            ];
+            for (at, normal, right, down) in neighbours {
+                if let Some(j) = net.iter().position(|p| *p == at) {
+                    if faces[j].is_none() {
+                        let origin = (1 + at.0 * sz, 1 + at.1 * sz);
+                        faces[j] = Some(Face {
+                            origin,
+                            normal,
+                            right,
+                            down,
+                        });
+                        workq.push(j);
+                    }
+                }
+            }
+        }
+        let faces: Vec<Face> = faces
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or("the faces aren't connected")?;
+        for (i, f) in faces.iter().enumerate() {
+            if faces[..i].iter().any(|g| g.normal == f.normal) {
+                return Err(format!("faces overlap at {:?} when folded, not a cube net", f.origin).into());
+            }
+        }
+
+        let cube = Cube { sz, faces };
+        cube.check()?;
+        Ok(cube)
+    }
+
+    fn origins(&self) -> Vec<(usize, usize)> {
+        self.faces.iter().map(|f| f.origin).collect()
+    }
+
+    fn face_at(&self, pos: (usize, usize)) -> &Face {
+        self.faces
+            .iter()
+            .find(|f| {
+                (f.origin.0..f.origin.0 + self.sz).contains(&pos.0)
+                    && (f.origin.1..f.origin.1 + self.sz).contains(&pos.1)
+            })
+            .expect("face plant")
+    }
+
+    // leave the face at `pos` going `direction` and return the position and direction on the adjoining face
+    fn wrap(&self, pos: (usize, usize), direction: usize) -> (usize, usize, usize) {
+        let src = self.face_at(pos);
+        let dst = self.faces.iter().find(|f| f.normal == src.heading(direction)).unwrap();
+        let dst_direction = (0..4).find(|d| dst.heading(*d) == neg(src.normal)).unwrap();
+
+        // offset along the shared edge, reversed when the faces run it in opposite directions
+        let offset = match direction % 2 {
+            0 => pos.0 - src.origin.0,
+            _ => pos.1 - src.origin.1,
+        };
+        let offset = match src.along(direction) == dst.along(dst_direction) {
+            true => offset,
+            false => self.sz - 1 - offset,
+        };
+
+        // enter through the edge opposite the direction of travel
+        let last = self.sz - 1;
+        let (dr, dc) = match dst_direction {
+            0 => (offset, 0),
+            1 => (0, offset),
+            2 => (offset, last),
+            3 => (last, offset),
+            _ => unreachable!(),
+        };
+        (dst.origin.0 + dr, dst.origin.1 + dc, dst_direction)
+    }
+
+    // walking off every edge cell and straight back must return to where it started
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        let last = self.sz - 1;
+        for f in &self.faces {
+            for direction in 0..4 {
+                for i in 0..self.sz {
+                    let (dr, dc) = match direction {
+                        0 => (i, last),
+                        1 => (last, i),
+                        2 => (i, 0),
+                        _ => (0, i),
+                    };
+                    let pos = (f.origin.0 + dr, f.origin.1 + dc);
+                    let (r, c, d) = self.wrap(pos, direction);
+                    let (br, bc, bd) = self.wrap((r, c), (d + 2) % 4);
+                    if (br, bc) != pos || bd != (direction + 2) % 4 {
+                        return Err(format!("leaving {pos:?} going {direction} doesn't come back").into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn gmove2(coord: (usize, usize), grid: &Array2<char>, cube: &Cube, direction: usize) -> Option<(usize, usize, usize)> {
     let (row, col) = match direction {
         0 => (coord.0, coord.1 + 1), // right
         1 => (coord.0 + 1, coord.1), // down
@@ -105,7 +287,7 @@ fn gmove2(
     };
 
     let (row, col, direction) = if grid[[row, col]] == ' ' {
-        transition(coord, direction, sz, faces_origin)
+        cube.wrap(coord, direction)
     } else {
         (row, col, direction)
     };
@@ -116,152 +298,7 @@ fn gmove2(
     }
 }
 
-fn transition(
-    src: (usize, usize),   // current position (r, c)
-    direction: usize,      // (0=right, 1=down, 2=left, 3=up)
-    sz: usize,             // face size (4, 50)
-    fo: &[(usize, usize)], // faces origin
-) -> (usize, usize, usize) {
-    let sz = sz - 1;
-
-    // what face is the input on?
-    // return the index by checking the origins
-    let mut face = None;
-    for (i, corner) in fo.iter().enumerate() {
-        if src.0 >= corner.0
-            && src.0 <= corner.0 + sz
-            && src.1 >= corner.1
-            && src.1 <= corner.1 + sz
-        {
-            face = Some(i);
-            break;
-        }
-    }
-    let face = face.expect("face plant");
-
-    // relative edge distances for output mapping
-    let (dr, dc) = (src.0 - fo[face].0, src.1 - fo[face].1);
-    let (rdr, rdc) = (sz - dr, sz - dc);
-
-    // TODO: Notes to self.
-    //   this is where i couldn't figure out a general solution to the shape
-    //   of the input i'm processing, or i missed the boat.
-    //
-    //   given the enumerated face origins, position and size, along with an
-    //   edge point and it's direction, it feels like i should be able to derive more.
-    //
-    //   So now this function is using a table populated with output faces and directions
-    //
-    //   1. i have the size of a face szXsz (puzzle example, actuals are 4,50)
-    //   2. i have distances from the origin for the "aligned/not-aligned" mappings.
-    //      "sz, dr, rdr" are used to map output rows
-    //      "sz, dc, rdc" are used to map output columns
-    //
-    //   we are only called when the grid position matches a space ' '
-    //      let (row, col, direction) = if grid[[row, col]] == ' ' {
-    //          transition(coord, direction, sz, faces_origin)
-    //      } else {
-    //          (row, col, direction)
-    //      };
-    //   so this crashes on maps which aren't the same ascii shape
-    //   i'm not sure how many different shapes can be formed unfolding a cube?
-    //
-    //   i eventually cut out shapes matching my input so i could hardcode the translation table
-    //
-    //
-    // Hard Coding:
-    // trans is an array of 6 elements (indexed by src face and hardcoded destination face)
-    // trans[face] is an array of 4 arrays to be indexed by the input direction
-    // the array elements info:
-    //   [ destination face, row offset, column offset, destination direction ]
-    //   [99, 99, 99, 99] represent unused directions for my input shapes
-    let trans = match sz < 49 {
-        true => [
-            // indexed by direction (0=right, 1=down, 2=left, 3=up)
-            [
-                [5, 0, rdr, 2], // logically: input face 1 (right) maps to -> destination face (6, left) or [5, _, _, 2]
-                [99, 99, 99, 99],
-                [2, 0, dr, 1],  // 1 left -> 3 down
-                [1, 0, rdc, 1], // 1 up -> 2 down
-            ],
-            [
-                [99, 99, 99, 99],
-                [4, sz, rdc, 3], // 2 down -> 5 up
-                [5, sz, rdr, 3], // 2 left -> 6 up
-                [0, 0, rdc, 1],  // 2 up -> 1 down
-            ],
-            [
-                [99, 99, 99, 99],
-                [4, rdc, 0, 0], // 3 down -> 5 right
-                [99, 99, 99, 99],
-                [0, dc, 0, 0], // 3 up -> 1 right
-            ],
-            [
-                [5, 0, rdr, 1], //  4 right -> 6 down
-                [99, 99, 99, 99],
-                [99, 99, 99, 99],
-                [99, 99, 99, 99],
-            ],
-            [
-                [99, 99, 99, 99],
-                [1, sz, rdc, 3], // 5 down -> 2 up
-                [2, sz, rdr, 3], // 5 left -> 3 up
-                [99, 99, 99, 99],
-            ],
-            [
-                [0, rdr, sz, 2], // 6 right -> 1 left
-                [1, rdc, 0, 0],  // 6 down -> 2 right
-                [99, 99, 99, 99],
-                [3, rdc, sz, 2], // 6 up -> 4 left
-            ],
-        ],
-        false => [
-            [
-                [99, 99, 99, 99],
-                [99, 99, 99, 99],
-                [3, rdr, 0, 0], // 1 left  -> 4 right
-                [5, dc, 0, 0],  // 1 up    -> 6 right
-            ],
-            [
-                [4, rdr, sz, 2], // 2 right -> 5 left
-                [2, dc, sz, 2],  // 2 down  -> 3 left
-                [99, 99, 99, 99],
-                [5, sz, dc, 3], // 2 up    -> 6 up
-            ],
-            [
-                [1, sz, dr, 3], // 3 right -> 2 up
-                [99, 99, 99, 99],
-                [3, 0, dr, 1], // 3 left  -> 4 down
-                [99, 99, 99, 99],
-            ],
-            [
-                [99, 99, 99, 99],
-                [99, 99, 99, 99],
-                [0, rdr, 0, 0], //  4 left -> 1 right
-                [2, dc, 0, 0],  //  4 up   -> 3 right
-            ],
-            [
-                [1, rdr, sz, 2], // 5 right -> 2 left
-                [5, dc, sz, 2],  // 5 down  -> 6 left
-                [99, 99, 99, 99],
-                [99, 99, 99, 99],
-            ],
-            [
-                [4, sz, dr, 3], // 6 right -> 5 up
-                [1, 0, dc, 1],  // 6 down  -> 2 down
-                [0, 0, dr, 1],  // 6 left  -> 1 down
-                [99, 99, 99, 99],
-            ],
-        ],
-    };
-
-    let info = trans[face][direction];
-    let dst_face = info[0];
-    assert!(dst_face < 6);
-    (fo[dst_face].0 + info[1], fo[dst_face].1 + info[2], info[3])
-}
-
-fn solve(puzzle_lines: &[String], part: usize) -> Result<usize, Box<dyn Error>> {
+fn solve(puzzle_lines: &[String], part: usize, dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
     let (steps, directions, grid) = get_data(puzzle_lines);
 
     // find start coord
@@ -274,37 +311,31 @@ fn solve(puzzle_lines: &[String], part: usize) -> Result<usize, Box<dyn Error>>
         break;
     }
 
-    let mut faces_origin = vec![];
-    let sz = (grid.nrows() - 2).max(grid.ncols() - 2) / 4;
-
-    for i in (1..grid.nrows()).step_by(sz) {
-        for j in (1..grid.ncols()).step_by(sz) {
-            if grid[[i, j]] == ' ' {
-                continue;
-            }
-            faces_origin.push((i, j));
-        }
-    }
+    // only part 2 needs the map to be a cube net
+    let cube = (part == 2).then(|| Cube::fold(&grid)).transpose()?;
 
+    let mut trail = vec![pos];
     let mut sc = 0;
     let mut dc = 0;
     let mut direction = 0;
     loop {
         for _ in 0..steps[sc] {
-            match part {
-                1 => {
+            match (part, &cube) {
+                (1, _) => {
                     // grid moves wrapping around
                     if let Some(coord) = gmove1(pos, &grid, direction) {
                         pos = coord;
+                        trail.push(pos);
                     } else {
                         break;
                     }
                 }
-                2 => {
+                (2, Some(cube)) => {
                     // grid moves walking the cube faces
-                    if let Some((r, c, d)) = gmove2(pos, &grid, &faces_origin, direction, sz) {
+                    if let Some((r, c, d)) = gmove2(pos, &grid, cube, direction) {
                         pos = (r, c);
                         direction = d;
+                        trail.push(pos);
                     } else {
                         break;
                     }
@@ -325,15 +356,61 @@ fn solve(puzzle_lines: &[String], part: usize) -> Result<usize, Box<dyn Error>>
         dc += 1;
     }
 
+    if dumper.is_enabled() {
+        let (origins, sz) = cube.as_ref().map_or((vec![], 0), |cube| (cube.origins(), cube.sz));
+        dump_image(&grid, &origins, sz, &trail, &format!("part{part}-walk.png"), dumper)?;
+    }
+
     Ok(1000 * pos.0 + 4 * pos.1 + direction)
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    solve(puzzle_lines, 1)
+// the map with open tiles colored by cube face, if folded, and the walked path overlaid
+fn dump_image(
+    grid: &Array2<char>,
+    faces_origin: &[(usize, usize)],
+    sz: usize,
+    trail: &[(usize, usize)],
+    name: &str,
+    dumper: &Dumper,
+) -> Result<(), Box<dyn Error>> {
+    let mut picture = grid.clone();
+    for (f, origin) in faces_origin.iter().enumerate() {
+        let face = char::from_digit(f as u32 + 1, 10).unwrap();
+        for i in origin.0..origin.0 + sz {
+            for j in origin.1..origin.1 + sz {
+                if picture[[i, j]] == '.' {
+                    picture[[i, j]] = face;
+                }
+            }
+        }
+    }
+    for p in trail {
+        picture[[p.0, p.1]] = '@';
+    }
+
+    let palette = Palette::new(BLACK)
+        .with('#', GRAY)
+        .with('1', BLUE)
+        .with('2', GREEN)
+        .with('3', YELLOW)
+        .with('4', MAGENTA)
+        .with('5', CYAN)
+        .with('6', ORANGE)
+        .with('@', RED);
+    dumper.grid(
+        name,
+        picture.rows().into_iter().map(|row| row.into_iter().copied()),
+        &palette,
+    )?;
+    Ok(())
+}
+
+fn part1(puzzle_lines: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
+    solve(puzzle_lines, 1, dumper)
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    solve(puzzle_lines, 2)
+fn part2(puzzle_lines: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
+    solve(puzzle_lines, 2, dumper)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -352,8 +429,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
-    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
+    let dumper = Dumper::from_args(&args).with_scale(4);
+    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines, &dumper)?)?;
+    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines, &dumper)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -373,28 +451,91 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part1(&puzzle_lines)?, 6032);
+        assert_eq!(part1(&puzzle_lines, &Dumper::default())?, 6032);
+        Ok(())
+    }
+
+    #[test]
+    fn part1_flat() -> Result<(), Box<dyn Error>> {
+        // not a cube net, so only the flat wrapping of part 1 applies
+        let puzzle_lines: Vec<String> = ["..#.", "....", "", "5R1L1"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(part1(&puzzle_lines, &Dumper::default())?, 1000 * 2 + 4 * 3);
+        assert!(part2(&puzzle_lines, &Dumper::default()).is_err());
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part1(&puzzle_lines)?, 26558);
+        assert_eq!(part1(&puzzle_lines, &Dumper::default())?, 26558);
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part2(&puzzle_lines)?, 5031);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 5031);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part2(&puzzle_lines)?, 110400);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 110400);
+        Ok(())
+    }
+
+    // expand a net drawn in faces ('x') into tiles with a single step move list
+    fn net(faces: &[&str], sz: usize) -> Vec<String> {
+        let mut lines = vec![];
+        for row in faces {
+            let line: String = row
+                .chars()
+                .flat_map(|c| std::iter::repeat_n(if c == 'x' { '.' } else { ' ' }, sz))
+                .collect();
+            lines.extend(std::iter::repeat_n(line.trim_end().to_string(), sz));
+        }
+        lines.extend(["".to_string(), "1".to_string()]);
+        lines
+    }
+
+    #[test]
+    fn example_edges() -> Result<(), Box<dyn Error>> {
+        let (_, _, grid) = super::get_data(&get_data("input-example"));
+        let cube = Cube::fold(&grid)?;
+        assert_eq!(cube.sz, 4);
+        // A -> B and C -> D from the puzzle description
+        assert_eq!(cube.wrap((6, 12), 0), (9, 15, 1));
+        assert_eq!(cube.wrap((12, 11), 1), (8, 2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn every_net_folds() -> Result<(), Box<dyn Error>> {
+        let nets: [&[&str]; 11] = [
+            &["x", "xxxx", "x"],
+            &["x", "xxxx", ".x"],
+            &["x", "xxxx", "..x"],
+            &["x", "xxxx", "...x"],
+            &[".x", "xxxx", ".x"],
+            &[".x", "xxxx", "..x"],
+            &["xx", ".xxx", ".x"],
+            &["xx", ".xxx", "..x"],
+            &["xx", ".xxx", "...x"],
+            &["xx", ".xx", "..xx"],
+            &["xxx", "..xxx"],
+        ];
+        for faces in nets {
+            for sz in [1, 3] {
+                let (_, _, grid) = super::get_data(&net(faces, sz));
+                assert_eq!(Cube::fold(&grid)?.faces.len(), 6, "{faces:?}");
+            }
+        }
+
+        for faces in [&["xxxxxx"][..], &["xx", "xx", "xx"], &["xxxx", "xx"]] {
+            let (_, _, grid) = super::get_data(&net(faces, 2));
+            assert!(Cube::fold(&grid).is_err(), "{faces:?}");
+        }
         Ok(())
     }
 }
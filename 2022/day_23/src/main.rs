@@ -1,3 +1,4 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_data_lines, reset_sigpipe};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
@@ -16,7 +17,7 @@ fn get_data(data: &[String]) -> HashSet<(i64, i64)> {
 }
 
 #[rustfmt::skip]
-fn solve(start_positions: &HashSet<(i64, i64)>, part: usize) -> Result<usize, Box<dyn Error>> {
+fn solve(start_positions: &HashSet<(i64, i64)>, part: usize, anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let mut positions = HashMap::new();
     for (e, pos) in start_positions.iter().enumerate() {
         positions.insert(e, *pos);
@@ -32,6 +33,8 @@ fn solve(start_positions: &HashSet<(i64, i64)>, part: usize) -> Result<usize, Bo
 
     let mut round = 0;
     loop {
+        anim.draw_points(&format!("Part {part}, Round {round}"), positions.values().copied(), '#')?;
+
         let mut proposals = HashMap::new();
         let mut destinations = HashMap::new();
         let pset = positions.values().copied().collect::<HashSet<(_, _)>>();
@@ -64,6 +67,7 @@ fn solve(start_positions: &HashSet<(i64, i64)>, part: usize) -> Result<usize, Bo
             moved = true;
         }
         if part == 1 && round == 10 {
+            anim.draw_points(&format!("Part {part}, Round {round}"), positions.values().copied(), '#')?;
             let minr = positions.values().map(|(r, _)| r).min().unwrap();
             let maxr = positions.values().map(|(r, _)| r).max().unwrap();
             let minc = positions.values().map(|(_, c)| c).min().unwrap();
@@ -76,14 +80,14 @@ fn solve(start_positions: &HashSet<(i64, i64)>, part: usize) -> Result<usize, Bo
     }
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part1(puzzle_lines: &[String], anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let start_positions = get_data(puzzle_lines);
-    solve(&start_positions, 1)
+    solve(&start_positions, 1, anim)
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part2(puzzle_lines: &[String], anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let start_positions = get_data(puzzle_lines);
-    solve(&start_positions, 2)
+    solve(&start_positions, 2, anim)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -102,8 +106,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines)?)?;
-    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines)?)?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('#', Color::BrightGreen)
+        .with_color('.', Color::Gray);
+    writeln!(stdout, "Answer Part 1 = {}", part1(&puzzle_lines, &mut anim)?)?;
+    writeln!(stdout, "Answer Part 2 = {}", part2(&puzzle_lines, &mut anim)?)?;
 
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
@@ -123,28 +130,28 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part1(&puzzle_lines)?, 110);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 110);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part1(&puzzle_lines)?, 4158);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 4158);
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example");
-        assert_eq!(part2(&puzzle_lines)?, 20);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 20);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual");
-        assert_eq!(part2(&puzzle_lines)?, 1014);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 1014);
         Ok(())
     }
 }
@@ -1,3 +1,5 @@
+use general::geometry::{Location, Polygon};
+use general::render::{Dumper, Palette, GRAY, GREEN, RED, WHITE};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use ndarray::{Array, Array2, ArrayView};
 use std::collections::HashSet;
@@ -86,20 +88,6 @@ impl Pipe {
             _ => false,
         }
     }
-    fn direction(&self, pipe: Pipe) -> (bool, bool, bool, bool) {
-        let rowdiff = (self.i as i64 - pipe.i as i64).abs() == 1;
-        let coldiff = (self.j as i64 - pipe.j as i64).abs() == 1;
-
-        let samerow = !rowdiff && coldiff && (self.i == pipe.i);
-        let samecol = !coldiff && rowdiff && (self.j == pipe.j);
-        //println!("{self:?} against {pipe:?}; rowdiff={rowdiff}, coldiff={coldiff}, samerow={samerow}, samecol={samecol}");
-        let up = samecol && pipe.i < self.i;
-        let down = samecol && pipe.i > self.i;
-        let left = samerow && pipe.j < self.j;
-        let right = samerow && pipe.j > self.j;
-        (up, down, left, right)
-    }
-
     fn graphic(&self, pipe: &Pipe) -> char {
         let rowdiff = (self.i as i64 - pipe.i as i64).abs() == 1;
         let coldiff = (self.j as i64 - pipe.j as i64).abs() == 1;
@@ -238,7 +226,7 @@ fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
     })
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part2(puzzle_lines: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
     let mut grid = get_grid(puzzle_lines)?;
     let s = *grid.iter().find(|pipe| pipe.is_s()).unwrap();
 
@@ -274,131 +262,49 @@ fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
         }
     }
 
+    // the loop is a lattice polygon, the tiles strictly inside it are enclosed
     let loop_pipes = &found[0];
-    let mut chain = loop_pipes
-        .windows(2)
-        .map(|w| (w[0], w[0].direction(w[1])))
-        .collect::<Vec<_>>();
-    let lst = loop_pipes[loop_pipes.len() - 1];
-    chain.push((lst, lst.direction(loop_pipes[0])));
-
-    // returns a pipe in the proximity in direction (avoids loop points)
-    let (nrow, ncol) = grid.dim();
-    let proximity_pipe = |d: (i64, i64), i: usize, j: usize| -> Option<Pipe> {
-        let ii = d.0 + i as i64;
-        let jj = d.1 + j as i64;
-        if ii >= 0 && jj >= 0 && ii < nrow as i64 && jj < ncol as i64 {
-            let ii = ii as usize;
-            let jj = jj as usize;
-            if !loop_pipes.contains(&grid[[ii, jj]]) {
-                return Some(grid[[ii, jj]]);
-            }
-        }
-        None
-    };
-
-    let mut inside = HashSet::new();
-    for chn in chain {
-        let (pipe, (up, down, left, right)) = chn;
-        let (i, j) = (pipe.i, pipe.j);
-        match (up, down, left, right) {
-            (true, false, false, false) => {
-                // up
-                if let Some(ipipe) = proximity_pipe(RIGHT, i, j) {
-                    inside.insert(ipipe);
-                }
-            }
-            (false, true, false, false) => {
-                // down
-                if let Some(ipipe) = proximity_pipe(LEFT, i, j) {
-                    inside.insert(ipipe);
-                }
-            }
-            (false, false, true, false) => {
-                // left
-                if let Some(ipipe) = proximity_pipe(UP, i, j) {
-                    inside.insert(ipipe);
-                }
-            }
-            (false, false, false, true) => {
-                // right
-                if let Some(ipipe) = proximity_pipe(DOWN, i, j) {
-                    inside.insert(ipipe);
-                }
-            }
-            _ => unreachable!(),
-        }
-    }
-
-    //println!("inside = {inside:?}");
-    let mut changed = true;
-    while changed {
-        changed = false;
-        for ((i, j), p) in grid.indexed_iter() {
-            if inside.contains(p) {
-                for d in [UP, DOWN, LEFT, RIGHT] {
-                    if let Some(ipipe) = proximity_pipe(d, i, j) {
-                        if !inside.contains(&ipipe) {
-                            inside.insert(ipipe);
-                            changed = true;
+    let poly = Polygon::new(loop_pipes.iter().map(|p| (p.i as i64, p.j as i64)));
+    let on_loop = loop_pipes.iter().map(|p| (p.i, p.j)).collect::<HashSet<_>>();
+
+    // the loop drawn with arrows and the enclosed tiles marked
+    if dumper.is_enabled() {
+        let mut picture = vec![];
+        for row in grid.rows() {
+            let mut line = vec![];
+            for elem in row {
+                if on_loop.contains(&(elem.i, elem.j)) {
+                    let mut blah = false;
+                    for w in loop_pipes.windows(2) {
+                        if w[1] == *elem {
+                            line.push(w[0].graphic(&w[1]));
+                            blah = true;
+                            break;
                         }
                     }
-                }
-            }
-        }
-    }
-
-    let mut outside = HashSet::new();
-    for ((i, j), p) in grid.indexed_iter().filter(|tup| !loop_pipes.contains(tup.1)) {
-        if i == 0 || i == nrow - 1 || j == 0 || j == ncol - 1 {
-            outside.insert(*p);
-        }
-    }
-
-    let mut changed = true;
-    while changed {
-        changed = false;
-        for ((i, j), p) in grid.indexed_iter().filter(|t| !loop_pipes.contains(t.1)) {
-            if outside.contains(p) {
-                for d in [UP, DOWN, LEFT, RIGHT] {
-                    if let Some(ipipe) = proximity_pipe(d, i, j) {
-                        if !inside.contains(&ipipe) && !outside.contains(&ipipe) {
-                            outside.insert(ipipe);
-                            changed = true;
-                        }
+                    if !blah {
+                        line.push('•');
                     }
+                } else if poly.locate((elem.i as i64, elem.j as i64)) == Location::Inside {
+                    line.push('I');
+                } else {
+                    line.push('O');
                 }
             }
+            picture.push(line);
         }
-    }
 
-    for row in grid.rows() {
-        for elem in row {
-            if loop_pipes.contains(elem) {
-                let mut blah = false;
-                for w in loop_pipes.windows(2) {
-                    if w[1] == *elem {
-                        print!("{}", w[0].graphic(&w[1]));
-                        blah = true;
-                        break;
-                    }
-                }
-                if !blah {
-                    print!("•");
-                }
-            } else if outside.contains(elem) {
-                print!("O");
-            } else if inside.contains(elem) {
-                print!("I");
-            } else {
-                //print!("{}", elem.c);
-                print!("•");
-            }
-        }
-        println!();
+        // the loop arrows fall through to the default color
+        let palette = Palette::new(RED).with('O', GRAY).with('I', GREEN).with('•', WHITE);
+        let text: String = picture
+            .iter()
+            .map(|line| line.iter().collect::<String>() + "\n")
+            .collect();
+        dumper.text("part2-loop.txt", &text)?;
+        dumper.grid("part2-loop.png", picture, &palette)?;
     }
 
-    Ok(inside.len() + 1)
+    Ok(poly.interior_points() as usize)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -419,7 +325,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let n = part1(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = part2(&puzzle_lines)?;
+    let n = part2(&puzzle_lines, &Dumper::from_args(&args).with_scale(4))?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -464,7 +370,7 @@ mod tests {
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example2")?;
-        assert_eq!(part2(&puzzle_lines)?, 4);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 4);
         Ok(())
     }
     */
@@ -472,7 +378,7 @@ mod tests {
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part2(&puzzle_lines)?, 383);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 383);
         Ok(())
     }
 }
@@ -1,74 +1,273 @@
-use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_on, trim_split_ws};
-use std::collections::HashMap;
+use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_on};
+use num::{BigUint, CheckedAdd, One, Zero};
 use std::error::Error;
 use std::io::{self, Write};
+use std::str::FromStr;
 
-// https://github.com/jonathanpaulson/AdventOfCode/blob/master/2023/12.py
-fn f(
-    dp: &mut HashMap<(usize, usize, usize), usize>,
-    pattern: &[char],
-    items: &[usize],
-    i: usize,
-    bi: usize,
-    current: usize,
-) -> usize {
-    let key = (i, bi, current);
-    if dp.contains_key(&key) {
-        return dp[&key];
-    }
-    if i == pattern.len() {
-        if bi == items.len() && current == 0 || bi == items.len() - 1 && items[bi] == current {
-            return 1;
-        } else {
-            return 0;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+impl Cell {
+    fn from_char(c: char) -> Result<Cell, Box<dyn Error>> {
+        match c {
+            '?' => Ok(Cell::Unknown),
+            '#' => Ok(Cell::Filled),
+            '.' => Ok(Cell::Empty),
+            _ => Err(format!("unknown cell {c:?}").into()),
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Cell::Unknown => '?',
+            Cell::Filled => '#',
+            Cell::Empty => '.',
         }
     }
-    let mut ans = 0;
-    for c in ['.', '#'] {
-        if pattern[i] == c || pattern[i] == '?' {
-            if c == '.' && current == 0 {
-                ans += f(dp, pattern, items, i + 1, bi, 0);
-            } else if c == '.' && current > 0 && bi < items.len() && items[bi] == current {
-                ans += f(dp, pattern, items, i + 1, bi + 1, 0);
-            } else if c == '#' {
-                ans += f(dp, pattern, items, i + 1, bi, current + 1);
+}
+
+// a run of `len` filled cells can start at `i`, it needs an empty (or unknown) cell or the end after it
+fn fits(cells: &[Cell], i: usize, len: usize) -> bool {
+    i + len <= cells.len()
+        && cells[i..i + len].iter().all(|c| *c != Cell::Empty)
+        && cells.get(i + len).is_none_or(|c| *c != Cell::Filled)
+}
+
+// ways[i][j] is the number of ways runs[j..] can be placed in cells[i..], None if a count overflows N
+fn table<N: Clone + Zero + One + CheckedAdd>(cells: &[Cell], runs: &[usize]) -> Option<Vec<Vec<N>>> {
+    let (n, k) = (cells.len(), runs.len());
+    let mut ways = vec![vec![N::zero(); k + 1]; n + 1];
+    ways[n][k] = N::one();
+    for i in (0..n).rev() {
+        for j in 0..=k {
+            let mut w = N::zero();
+            if cells[i] != Cell::Filled {
+                w = w.checked_add(&ways[i + 1][j])?;
+            }
+            if j < k && fits(cells, i, runs[j]) {
+                w = w.checked_add(&ways[(i + runs[j] + 1).min(n)][j + 1])?;
             }
+            ways[i][j] = w;
         }
     }
-    dp.insert(key, ans);
-    ans
+    Some(ways)
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut ans = 0;
-    for line in puzzle_lines {
-        let parts: Vec<_> = trim_split_ws::<String>(line)?;
-        let pattern: Vec<_> = parts[0].chars().collect();
-        let items: Vec<_> = trim_split_on::<usize>(&parts[1], ',')?;
-        ans += f(&mut HashMap::new(), &pattern, &items, 0, 0, 0);
+// the number of ways to fill the unknown cells so the filled runs are exactly `runs`
+fn count<N: Clone + Zero + One + CheckedAdd>(cells: &[Cell], runs: &[usize]) -> Option<N> {
+    Some(table::<N>(cells, runs)?[0][0].clone())
+}
+
+// every arrangement, only following branches the counts say lead somewhere
+fn arrangements(cells: &[Cell], runs: &[usize]) -> Vec<Vec<Cell>> {
+    fn walk(
+        ways: &[Vec<BigUint>],
+        cells: &[Cell],
+        runs: &[usize],
+        (i, j): (usize, usize),
+        current: &mut Vec<Cell>,
+        found: &mut Vec<Vec<Cell>>,
+    ) {
+        if i == cells.len() {
+            found.push(current.clone());
+            return;
+        }
+        let n = cells.len();
+        if cells[i] != Cell::Filled && !ways[i + 1][j].is_zero() {
+            current.push(Cell::Empty);
+            walk(ways, cells, runs, (i + 1, j), current, found);
+            current.pop();
+        }
+        if j < runs.len() && fits(cells, i, runs[j]) && !ways[(i + runs[j] + 1).min(n)][j + 1].is_zero() {
+            let mark = current.len();
+            current.extend(std::iter::repeat_n(Cell::Filled, runs[j]));
+            if i + runs[j] < n {
+                current.push(Cell::Empty);
+            }
+            walk(ways, cells, runs, ((i + runs[j] + 1).min(n), j + 1), current, found);
+            current.truncate(mark);
+        }
+    }
+
+    let ways = table::<BigUint>(cells, runs).expect("BigUint counts can't overflow");
+    let mut found = vec![];
+    if !ways[0][0].is_zero() {
+        walk(&ways, cells, runs, (0, 0), &mut vec![], &mut found);
     }
+    found
+}
 
-    Ok(ans)
+// A row of springs and the sizes of its damaged groups
+#[derive(Clone, Debug)]
+struct Row {
+    cells: Vec<Cell>,
+    runs: Vec<usize>,
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut ans = 0;
-    for line in puzzle_lines {
-        let parts: Vec<_> = trim_split_ws::<String>(line)?;
+impl FromStr for Row {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cells, runs) = s
+            .trim()
+            .split_once(' ')
+            .ok_or(format!("expected cells and runs: {s}"))?;
+        Ok(Row {
+            cells: cells.chars().map(Cell::from_char).collect::<Result<_, _>>()?,
+            runs: trim_split_on::<usize>(runs, ',')?,
+        })
+    }
+}
 
-        let mut p0 = parts[0].to_string();
-        let mut p1 = parts[1].to_string();
-        for _ in 0..4 {
-            p0 += &("?".to_owned() + &parts[0]);
-            p1 += &(",".to_owned() + &parts[1]);
+impl Row {
+    // `factor` copies of the cells joined by unknowns, and `factor` copies of the runs
+    fn unfold(&self, factor: usize) -> Row {
+        let mut cells = vec![];
+        for i in 0..factor {
+            if i > 0 {
+                cells.push(Cell::Unknown);
+            }
+            cells.extend(&self.cells);
         }
+        Row {
+            cells,
+            runs: self.runs.repeat(factor),
+        }
+    }
 
-        let pattern: Vec<_> = p0.chars().collect();
-        let items: Vec<_> = trim_split_on::<usize>(&p1, ',')?;
-        ans += f(&mut HashMap::new(), &pattern, &items, 0, 0, 0);
+    fn count<N: Clone + Zero + One + CheckedAdd>(&self) -> Option<N> {
+        count(&self.cells, &self.runs)
     }
+}
 
-    Ok(ans)
+// A nonogram, the run lengths of every row and column of a hidden picture
+//
+// Not needed by the puzzle, it checks the counter works in two dimensions.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+struct Nonogram {
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+}
+
+#[cfg(test)]
+impl Nonogram {
+    fn new(rows: Vec<Vec<usize>>, cols: Vec<Vec<usize>>) -> Self {
+        Nonogram { rows, cols }
+    }
+
+    // fix the cells of a line every remaining arrangement agrees on, false on a contradiction
+    fn propagate(line: &mut [Cell], runs: &[usize]) -> bool {
+        for i in 0..line.len() {
+            if line[i] != Cell::Unknown {
+                continue;
+            }
+            // only whether a count is zero matters, so an overflow saturates
+            let ways = [Cell::Filled, Cell::Empty].map(|c| {
+                line[i] = c;
+                count::<u128>(line, runs).unwrap_or(u128::MAX)
+            });
+            line[i] = match ways {
+                [0, 0] => return false,
+                [0, _] => Cell::Empty,
+                [_, 0] => Cell::Filled,
+                _ => Cell::Unknown,
+            };
+        }
+        count::<u128>(line, runs).is_none_or(|n| n > 0)
+    }
+
+    // row and column propagation until nothing changes, guessing the first unknown cell when stuck
+    fn solve(&self) -> Option<Grid> {
+        let grid = Grid(vec![vec![Cell::Unknown; self.cols.len()]; self.rows.len()]);
+        self.search(grid)
+    }
+
+    fn search(&self, mut grid: Grid) -> Option<Grid> {
+        loop {
+            let before = grid.clone();
+            for (r, runs) in self.rows.iter().enumerate() {
+                if !Self::propagate(&mut grid.0[r], runs) {
+                    return None;
+                }
+            }
+            for (c, runs) in self.cols.iter().enumerate() {
+                let mut column: Vec<Cell> = grid.0.iter().map(|row| row[c]).collect();
+                if !Self::propagate(&mut column, runs) {
+                    return None;
+                }
+                for (row, cell) in grid.0.iter_mut().zip(column) {
+                    row[c] = cell;
+                }
+            }
+            if grid == before {
+                break;
+            }
+        }
+
+        let Some((r, c)) = (0..grid.0.len())
+            .flat_map(|r| (0..grid.0[r].len()).map(move |c| (r, c)))
+            .find(|(r, c)| grid.0[*r][*c] == Cell::Unknown)
+        else {
+            return Some(grid);
+        };
+        [Cell::Filled, Cell::Empty].into_iter().find_map(|cell| {
+            let mut guess = grid.clone();
+            guess.0[r][c] = cell;
+            self.search(guess)
+        })
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+struct Grid(Vec<Vec<Cell>>);
+
+#[cfg(test)]
+impl std::fmt::Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.0 {
+            writeln!(f, "{}", row.iter().map(|c| c.to_char()).collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+fn get_rows(puzzle_lines: &[String]) -> Result<Vec<Row>, Box<dyn Error>> {
+    puzzle_lines.iter().map(|line| line.parse()).collect()
+}
+
+// the arrangements of every row added up, an error if they don't fit a u128
+fn total<'a>(rows: impl IntoIterator<Item = &'a Row>) -> Result<u128, Box<dyn Error>> {
+    rows.into_iter().try_fold(0u128, |sum, row| {
+        row.count::<u128>()
+            .and_then(|n| sum.checked_add(n))
+            .ok_or_else(|| "too many arrangements for a u128".into())
+    })
+}
+
+// `listing` collects every arrangement of every row
+fn part1(puzzle_lines: &[String], listing: Option<&mut Vec<String>>) -> Result<u128, Box<dyn Error>> {
+    let rows = get_rows(puzzle_lines)?;
+    if let Some(listing) = listing {
+        for (line, row) in puzzle_lines.iter().zip(&rows) {
+            for a in arrangements(&row.cells, &row.runs) {
+                listing.push(format!(
+                    "{line} -> {}",
+                    a.iter().map(|c| c.to_char()).collect::<String>()
+                ));
+            }
+        }
+    }
+    total(&rows)
+}
+
+fn part2(puzzle_lines: &[String]) -> Result<u128, Box<dyn Error>> {
+    let rows: Vec<Row> = get_rows(puzzle_lines)?.iter().map(|row| row.unfold(5)).collect();
+    total(&rows)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -87,7 +286,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let n = part1(&puzzle_lines)?;
+    let mut listing = vec![];
+    let n = part1(&puzzle_lines, args.get_flag("verbose").then_some(&mut listing))?;
+    for line in listing {
+        writeln!(stdout, "{line}")?;
+    }
     writeln!(stdout, "Answer Part 1 = {n}")?;
     let n = part2(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
@@ -110,14 +313,14 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part1(&puzzle_lines)?, 21);
+        assert_eq!(part1(&puzzle_lines, None)?, 21);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part1(&puzzle_lines)?, 7032);
+        assert_eq!(part1(&puzzle_lines, None)?, 7032);
         Ok(())
     }
 
@@ -134,4 +337,51 @@ mod tests {
         assert_eq!(part2(&puzzle_lines)?, 1493340882140);
         Ok(())
     }
+
+    #[test]
+    fn arrangements_listed() -> Result<(), Box<dyn Error>> {
+        let row: Row = "?###???????? 3,2,1".parse()?;
+        let found = arrangements(&row.cells, &row.runs);
+        assert_eq!(Some(found.len() as u128), row.count::<u128>());
+        assert_eq!(found.len(), 10);
+        let text: Vec<String> = found.iter().map(|a| a.iter().map(|c| c.to_char()).collect()).collect();
+        assert_eq!(text[0], ".###....##.#");
+        assert_eq!(text[9], ".###.##.#...");
+        assert!(arrangements(&"#.# 2".parse::<Row>()?.cells, &[2]).is_empty());
+
+        let mut listing = vec![];
+        assert_eq!(part1(&get_data("input-example")?, Some(&mut listing))?, 21);
+        assert_eq!(listing.len(), 21);
+        assert_eq!(listing[0], "???.### 1,1,3 -> #.#.###");
+        Ok(())
+    }
+
+    #[test]
+    fn big_unfold() -> Result<(), Box<dyn Error>> {
+        let row: Row = "?###???????? 3,2,1".parse()?;
+        assert_eq!(
+            row.unfold(5).count::<BigUint>(),
+            row.unfold(5).count::<u128>().map(BigUint::from)
+        );
+        assert_eq!(row.unfold(5).count::<u128>(), Some(506250));
+        // more arrangements than fit in a u128
+        assert!(row.unfold(60).count::<BigUint>() > Some(BigUint::from(u128::MAX)));
+        assert_eq!(row.unfold(60).count::<u128>(), None);
+        assert!(total(&[row.unfold(60)]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nonogram() {
+        // .###.
+        // #...#
+        // #####
+        // #...#
+        // ##.##
+        let rows = vec![vec![3], vec![1, 1], vec![5], vec![1, 1], vec![2, 2]];
+        let cols = vec![vec![4], vec![1, 1, 1], vec![1, 1], vec![1, 1, 1], vec![4]];
+        let grid = Nonogram::new(rows, cols).solve().unwrap();
+        assert_eq!(grid.to_string(), ".###.\n#...#\n#####\n#...#\n##.##\n");
+        assert!(Nonogram::new(vec![vec![2]], vec![vec![1]]).solve().is_none());
+    }
 }
@@ -1,65 +1,39 @@
+use general::geometry::{Direction, Polygon};
+use general::render::{Dumper, Palette, Svg, BLUE, CYAN, RED, WHITE};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_ws};
-use geo::*;
 use pathfinding::matrix::*;
 use std::collections::{BTreeSet, VecDeque};
 use std::error::Error;
 use std::io::{self, Write};
 
-#[allow(dead_code)]
-fn display(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+// the part 1 lagoon drawn on a grid, '#' trench and '~' the dug out interior
+fn lagoon(puzzle_lines: &[String]) -> Result<Matrix<char>, Box<dyn Error>> {
+    let poly = dig_plan(puzzle_lines, false)?;
+    let vertices = poly.vertices();
+
+    // every trench point between consecutive corners
     let mut points = BTreeSet::new();
-    let mut cur = (0_i32, 0_i32);
-    points.insert(cur);
-    let mut line_string = vec![];
-    line_string.push((cur.0 as f64, cur.1 as f64));
-    for line in puzzle_lines {
-        let parts: Vec<_> = trim_split_ws::<String>(line)?;
-        let length = parts[1].parse::<usize>()?;
-        let _rgb = &parts[2][2..parts[2].len() - 1].to_string();
-        match parts[0].as_ref() {
-            "U" => {
-                for _ in 0..length {
-                    cur = (cur.0 - 1, cur.1);
-                    points.insert(cur);
-                }
-                line_string.push((cur.0 as f64, cur.1 as f64));
-            }
-            "D" => {
-                for _ in 0..length {
-                    cur = (cur.0 + 1, cur.1);
-                    points.insert(cur);
-                }
-                line_string.push((cur.0 as f64, cur.1 as f64));
-            }
-            "L" => {
-                for _ in 0..length {
-                    cur = (cur.0, cur.1 - 1);
-                    points.insert(cur);
-                }
-                line_string.push((cur.0 as f64, cur.1 as f64));
-            }
-            "R" => {
-                for _ in 0..length {
-                    cur = (cur.0, cur.1 + 1);
-                    points.insert(cur);
-                }
-                line_string.push((cur.0 as f64, cur.1 as f64));
-            }
-            _ => unreachable!(),
+    for (i, start) in vertices.iter().enumerate() {
+        let (mut cur, end) = (*start, vertices[(i + 1) % vertices.len()]);
+        let step = ((end.0 - cur.0).signum(), (end.1 - cur.1).signum());
+        points.insert(cur);
+        while cur != end {
+            cur = (cur.0 + step.0, cur.1 + step.1);
+            points.insert(cur);
         }
     }
-    //println!("line_string = {line_string:?}");
+
     let minr = points.iter().map(|k| k.0).min().unwrap();
     let maxr = points.iter().map(|k| k.0).max().unwrap();
     let minc = points.iter().map(|k| k.1).min().unwrap();
     let maxc = points.iter().map(|k| k.1).max().unwrap();
-    //println!("{minr:?}, {maxr:?}, {minc:?}, {maxc:?}");
     let mut m = Matrix::new(1 + (maxr - minr) as usize, 1 + (maxc - minc) as usize, '.');
     for k in points {
         let (i, j) = ((k.0 - minr) as usize, (k.1 - minc) as usize);
         m[(i, j)] = '#';
     }
 
+    // flood fill from just inside the top-left trench corner
     let mut p = (0, 0);
     'outer: for i in 0..m.rows {
         for j in 0..m.columns {
@@ -72,128 +46,66 @@ fn display(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
     let mut workq = VecDeque::new();
     workq.push_back(p);
     while let Some(p) = workq.pop_front() {
-        if m[p] == '#' {
+        if m[p] != '.' {
             continue;
         }
-        for n in m.neighbours(p, false).filter(|p| m[*p] != '#') {
+        for n in m.neighbours(p, false).filter(|p| m[*p] == '.') {
             workq.push_back(n);
         }
-        m[p] = '#';
-    }
-    let mut total = 0;
-    for i in 0..m.rows {
-        for j in 0..m.columns {
-            //print!("{}", m[(i, j)]);
-            if m[(i, j)] == '#' {
-                total += 1
-            }
-        }
-        //println!();
+        m[p] = '~';
     }
-    Ok(total)
-}
-
-// Input: a list of coordinates representing a connected point path
-// Output: area and perimiter length
-// https://en.wikipedia.org/wiki/Shoelace_formula
-fn shoelace(line_string: &[(i64, i64)]) -> (usize, usize) {
-    let manhatten = |a: (i64, i64), b: (i64, i64)| -> usize { ((a.0 - b.0).abs() + (a.1 - b.1).abs()) as usize };
-
-    // walk the point path by pairs
-    let (shoelace, perimiter) = line_string.windows(2).fold((0, 0), |(shoe, perim), p| {
-        (
-            // shoelace
-            shoe + p[0].0 * p[1].1 - p[0].1 * p[1].0,
-            // perimiter
-            perim + manhatten(p[0], p[1]),
-        )
-    });
-
-    ((shoelace / 2).unsigned_abs() as usize, perimiter)
+    Ok(m)
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut line_string = vec![];
-    let mut cur = (0_i64, 0_i64);
-    let mut perim = 0;
-    line_string.push((0, 0));
-
+// Input: the dig plan, part2 decodes the instructions from the color codes
+// Output: the trench as a polygon starting at (0, 0)
+fn dig_plan(puzzle_lines: &[String], part2: bool) -> Result<Polygon, Box<dyn Error>> {
+    let mut instructions = vec![];
     for line in puzzle_lines {
         let parts: Vec<_> = trim_split_ws::<String>(line)?;
-        let length = parts[1].parse::<usize>()?;
-        let _rgb = &parts[2][2..parts[2].len() - 1].to_string();
-        let d = length as i64;
-        perim += d;
-        match parts[0].as_ref() {
-            "U" => {
-                cur = (cur.0 - d, cur.1);
-                line_string.push(cur);
-            }
-            "D" => {
-                cur = (cur.0 + d, cur.1);
-                line_string.push(cur);
-            }
-            "L" => {
-                cur = (cur.0, cur.1 - d);
-                line_string.push(cur);
-            }
-            "R" => {
-                cur = (cur.0, cur.1 + d);
-                line_string.push(cur);
-            }
-            _ => unreachable!(),
-        }
+        let rgb = &parts[2][2..parts[2].len() - 1].to_string();
+        instructions.push(match part2 {
+            false => (parts[0].parse::<Direction>()?, parts[1].parse::<i64>()?),
+            true => (
+                match rgb[5..6].as_ref() {
+                    "3" => Direction::Up,
+                    "1" => Direction::Down,
+                    "2" => Direction::Left,
+                    "0" => Direction::Right,
+                    _ => unreachable!(),
+                },
+                i64::from_str_radix(&rgb[0..5], 16)?,
+            ),
+        });
     }
+    Ok(Polygon::from_instructions((0, 0), instructions))
+}
 
-    let (shoelace, tperim) = shoelace(&line_string);
-
-    // could have used geo::*
-    let ls: LineString<f64> = line_string.iter().map(|(r, c)| (*r as f64, *c as f64)).collect();
-    let poly = Polygon::new(ls, vec![]);
-    assert_eq!(poly.unsigned_area() as usize, shoelace);
-
-    // we calculated the perimiter 2 ways
-    assert_eq!(perim, tperim as i64);
-
-    // https://en.wikipedia.org/wiki/Pick%27s_theorem
-    Ok(1 + shoelace + (perim / 2) as usize)
+// the trench and the dug out interior, every lattice point of the polygon
+fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+    let poly = dig_plan(puzzle_lines, false)?;
+    Ok(poly.lattice_points() as usize)
 }
 
 fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut line_string = vec![];
-    let mut cur = (0_i64, 0_i64);
-    let mut perim = 0;
-    line_string.push((0, 0));
+    let poly = dig_plan(puzzle_lines, true)?;
+    Ok(poly.lattice_points() as usize)
+}
 
-    for line in puzzle_lines {
-        let parts: Vec<_> = trim_split_ws::<String>(line)?;
-        let _length = parts[1].parse::<usize>()?;
-        let rgb = &parts[2][2..parts[2].len() - 1].to_string();
-        let d = i64::from_str_radix(&rgb[0..5], 16)?;
-        perim += d;
-        match rgb[5..6].as_ref() {
-            "3" => {
-                cur = (cur.0 - d, cur.1);
-                line_string.push(cur);
-            }
-            "1" => {
-                cur = (cur.0 + d, cur.1);
-                line_string.push(cur);
-            }
-            "2" => {
-                cur = (cur.0, cur.1 - d);
-                line_string.push(cur);
-            }
-            "0" => {
-                cur = (cur.0, cur.1 + d);
-                line_string.push(cur);
-            }
-            _ => unreachable!(),
-        }
+// writes the trench outlines as SVG and the part 1 lagoon as a PNG
+fn dump_images(puzzle_lines: &[String], dumper: &Dumper) -> Result<(), Box<dyn Error>> {
+    // part 2 distances are in the millions, scale them down to a similar size
+    for (part2, scale, name) in [(false, 4.0, "part1-trench.svg"), (true, 1e-4, "part2-trench.svg")] {
+        let poly = dig_plan(puzzle_lines, part2)?;
+        let mut svg = Svg::new(scale).with_stroke_width(2.0);
+        svg.polygon(poly.vertices(), RED);
+        dumper.svg(name, &svg)?;
     }
-    let (shoelace, tperim) = shoelace(&line_string);
-    assert_eq!(perim, tperim as i64);
-    Ok(1 + shoelace + (perim / 2) as usize)
+
+    let m = lagoon(puzzle_lines)?;
+    let palette = Palette::new(WHITE).with('#', BLUE).with('~', CYAN);
+    dumper.grid("part1-lagoon.png", m.iter().map(|row| row.iter().copied()), &palette)?;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -217,6 +129,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     let n = part2(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
+    let dumper = Dumper::from_args(&args).with_scale(4);
+    if dumper.is_enabled() {
+        dump_images(&puzzle_lines, &dumper)?;
+    }
+
     if args.get_flag("time") {
         writeln!(stdout, "Total Runtime: {:?}", timer.elapsed())?;
     }
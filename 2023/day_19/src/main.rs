@@ -1,152 +1,316 @@
-use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_on};
-use std::collections::{HashMap, VecDeque};
+use general::render::Dumper;
+use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
 
-fn evaluate(expr: &str, vars: &HashMap<String, usize>, logic_stmts: &HashMap<String, String>) -> bool {
-    if expr == "A" {
-        true
-    } else if expr == "R" {
-        false
-    } else if let Some(e) = logic_stmts.get(expr) {
-        evaluate(e, vars, logic_stmts)
-    } else {
-        let cmp = expr.split_once(':').unwrap();
-        let branch = cmp.1.split_once(',').unwrap();
-        let lt = cmp.0.contains('<');
-        let (k, v) = match lt {
-            true => cmp.0.split_once('<').unwrap(),
-            false => cmp.0.split_once('>').unwrap(),
+// a part's ratings indexed by category x, m, a, s
+type Part = [usize; 4];
+
+// inclusive (lo, hi) rating ranges for each category
+type Ranges = [(usize, usize); 4];
+
+const CATEGORIES: &str = "xmas";
+
+fn category(c: &str) -> Result<usize, Box<dyn Error>> {
+    match c.len() == 1 {
+        true => CATEGORIES
+            .find(c)
+            .ok_or_else(|| format!("unknown category {c:?}").into()),
+        false => Err(format!("unknown category {c:?}").into()),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Less,
+    Greater,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Condition {
+    var: usize,
+    op: Op,
+    threshold: usize,
+}
+
+impl Condition {
+    fn matches(&self, part: &Part) -> bool {
+        match self.op {
+            Op::Less => part[self.var] < self.threshold,
+            Op::Greater => part[self.var] > self.threshold,
+        }
+    }
+
+    // the (matching, not matching) pieces of `ranges`, None when a piece is empty
+    fn split(&self, ranges: &Ranges) -> (Option<Ranges>, Option<Ranges>) {
+        let (lo, hi) = ranges[self.var];
+        let (yes, no) = match self.op {
+            Op::Less => (
+                (lo, hi.min(self.threshold.saturating_sub(1))),
+                (lo.max(self.threshold), hi),
+            ),
+            Op::Greater => ((lo.max(self.threshold + 1), hi), (lo, hi.min(self.threshold))),
+        };
+        let piece = |r: (usize, usize)| {
+            let mut ranges = *ranges;
+            ranges[self.var] = r;
+            match r.0 <= r.1 {
+                true => Some(ranges),
+                false => None,
+            }
         };
-        let n = v.parse::<usize>().unwrap();
-        match lt && vars[k] < n || !lt && vars[k] > n {
-            true => evaluate(branch.0, vars, logic_stmts),
-            false => evaluate(branch.1, vars, logic_stmts),
+        (piece(yes), piece(no))
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Less => '<',
+            Op::Greater => '>',
+        };
+        write!(f, "{}{op}{}", &CATEGORIES[self.var..self.var + 1], self.threshold)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Accept,
+    Reject,
+    Workflow(String),
+}
+
+impl From<&str> for Target {
+    fn from(s: &str) -> Self {
+        match s {
+            "A" => Target::Accept,
+            "R" => Target::Reject,
+            _ => Target::Workflow(s.to_string()),
         }
     }
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut logic_stmts = HashMap::new();
-    let mut first_half = true;
-    let mut ans = 0;
-    for line in puzzle_lines {
-        if line.is_empty() {
-            first_half = false;
-            continue;
+// a rule without a condition always sends the part to its target
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    condition: Option<Condition>,
+    target: Target,
+}
+
+impl std::str::FromStr for Rule {
+    type Err = Box<dyn Error>;
+
+    // ex. a<2006:qkq or rfg
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((cond, target)) = s.split_once(':') else {
+            return Ok(Rule {
+                condition: None,
+                target: s.into(),
+            });
+        };
+        let (var, op, threshold) = match cond.split_once('<') {
+            Some((var, n)) => (var, Op::Less, n),
+            None => {
+                let (var, n) = cond.split_once('>').ok_or_else(|| format!("bad condition {cond:?}"))?;
+                (var, Op::Greater, n)
+            }
+        };
+        Ok(Rule {
+            condition: Some(Condition {
+                var: category(var)?,
+                op,
+                threshold: threshold.parse()?,
+            }),
+            target: target.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Workflows {
+    flows: HashMap<String, Vec<Rule>>,
+}
+
+impl Workflows {
+    // ex. px{a<2006:qkq,m>2090:A,rfg}
+    fn parse(lines: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut flows = HashMap::new();
+        for line in lines {
+            let (name, rules) = line
+                .strip_suffix('}')
+                .and_then(|l| l.split_once('{'))
+                .ok_or_else(|| format!("bad workflow {line:?}"))?;
+            let rules = rules.split(',').map(|r| r.parse()).collect::<Result<Vec<Rule>, _>>()?;
+            if rules.last().map(|r| r.condition.is_some()).unwrap_or(true) {
+                return Err(format!("workflow {name} must end with an unconditional rule").into());
+            }
+            if flows.insert(name.to_string(), rules).is_some() {
+                return Err(format!("workflow {name} is defined twice").into());
+            }
         }
-        if first_half {
-            let names = line.split_once('{').unwrap();
-            let expr = names.1.replace('}', "");
-            // ex. px{a<2006:qkq,m>2090:A,rfg} => (px, a<2006:qkq,m>2090:A,rfg)
-            logic_stmts.insert(names.0.to_string(), expr.to_string());
-        } else {
-            let line = line.replace(['{', '}'], "");
-            // ex. {x=787,m=2655,a=1222,s=2876} => (x, 787), (m, 2655), ..
-            let mut vars = HashMap::new();
-            for def in trim_split_on::<String>(&line, ',')? {
-                let parts = def.split_once('=').unwrap();
-                vars.insert(parts.0.to_string(), parts.1.parse::<usize>().unwrap());
+        let workflows = Workflows { flows };
+        workflows.validate()?;
+        Ok(workflows)
+    }
+
+    // "in" exists, every target is defined and no workflow can reach itself
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !self.flows.contains_key("in") {
+            return Err("no \"in\" workflow".into());
+        }
+        for (name, rules) in &self.flows {
+            for rule in rules {
+                if let Target::Workflow(t) = &rule.target {
+                    if !self.flows.contains_key(t) {
+                        return Err(format!("workflow {name} sends parts to undefined workflow {t}").into());
+                    }
+                }
             }
-            if evaluate("in", &vars, &logic_stmts) {
-                ans += vars.values().sum::<usize>();
+        }
+
+        // depth first search keeping the current path, revisiting a workflow on the path is a cycle
+        let mut done = HashSet::new();
+        let mut names: Vec<&String> = self.flows.keys().collect();
+        names.sort();
+        for name in names {
+            let mut path = vec![];
+            self.find_cycle(name, &mut path, &mut done)?;
+        }
+        Ok(())
+    }
+
+    fn find_cycle<'a>(
+        &'a self,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(i) = path.iter().position(|p| *p == name) {
+            return Err(format!("workflow cycle {} -> {name}", path[i..].join(" -> ")).into());
+        }
+        if done.contains(name) {
+            return Ok(());
+        }
+        path.push(name);
+        for rule in &self.flows[name] {
+            if let Target::Workflow(t) = &rule.target {
+                self.find_cycle(t, path, done)?;
             }
         }
+        path.pop();
+        done.insert(name);
+        Ok(())
     }
-    Ok(ans)
-}
-
-fn new_range(op: &str, n: usize, lo: usize, hi: usize) -> (usize, usize) {
-    let mut lo = lo;
-    let mut hi = hi;
-    match op {
-        ">" => lo = lo.max(n + 1),
-        "<" => hi = hi.min(n - 1),
-        ">=" => lo = lo.max(n),
-        "<=" => hi = hi.min(n),
-        _ => unreachable!(),
-    }
-    (lo, hi)
-}
-
-#[allow(clippy::too_many_arguments)]
-fn new_ranges(
-    var: &str,
-    op: &str,
-    n: usize,
-    xl: usize,
-    xh: usize,
-    ml: usize,
-    mh: usize,
-    al: usize,
-    ah: usize,
-    sl: usize,
-    sh: usize,
-) -> (usize, usize, usize, usize, usize, usize, usize, usize) {
-    let mut xl = xl;
-    let mut xh = xh;
-    let mut ml = ml;
-    let mut mh = mh;
-    let mut al = al;
-    let mut ah = ah;
-    let mut sl = sl;
-    let mut sh = sh;
-    match var {
-        "x" => (xl, xh) = new_range(op, n, xl, xh),
-        "m" => (ml, mh) = new_range(op, n, ml, mh),
-        "a" => (al, ah) = new_range(op, n, al, ah),
-        "s" => (sl, sh) = new_range(op, n, sl, sh),
-        _ => unreachable!(),
-    }
-    (xl, xh, ml, mh, al, ah, sl, sh)
-}
-
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    let mut logic_stmts: HashMap<String, String> = HashMap::new();
-    let mut ans = 0;
-    for line in puzzle_lines {
-        if line.is_empty() {
-            break;
+
+    fn accepts(&self, part: &Part) -> bool {
+        let mut name = "in";
+        loop {
+            let rule = self.flows[name]
+                .iter()
+                .find(|r| r.condition.map(|c| c.matches(part)).unwrap_or(true))
+                .unwrap();
+            match &rule.target {
+                Target::Accept => return true,
+                Target::Reject => return false,
+                Target::Workflow(t) => name = t,
+            }
         }
-        let names = line.split_once('{').unwrap();
-        let expr = names.1.replace('}', "");
-        logic_stmts.insert(names.0.to_string(), expr.to_string());
     }
 
-    let mut workq = VecDeque::new();
-    workq.push_back(("in", 1, 4000, 1, 4000, 1, 4000, 1, 4000));
-    while let Some((expr, mut xl, mut xh, mut ml, mut mh, mut al, mut ah, mut sl, mut sh)) = workq.pop_front() {
-        if expr == "R" || xl > xh || ml > mh || al > ah || sl > sh {
-            continue;
+    // number of rating combinations within `ranges` that are accepted
+    fn accepted_combinations(&self, ranges: Ranges) -> usize {
+        let mut ans = 0;
+        let mut workq = VecDeque::from([(Target::Workflow("in".to_string()), ranges)]);
+        while let Some((target, ranges)) = workq.pop_front() {
+            let name = match target {
+                Target::Accept => {
+                    ans += ranges.iter().map(|(lo, hi)| hi - lo + 1).product::<usize>();
+                    continue;
+                }
+                Target::Reject => continue,
+                Target::Workflow(name) => name,
+            };
+
+            // each rule takes the piece it matches, the rest falls through to the next rule
+            let mut rest = Some(ranges);
+            for rule in &self.flows[&name] {
+                let Some(ranges) = rest else {
+                    break;
+                };
+                let (matched, unmatched) = match rule.condition {
+                    Some(c) => c.split(&ranges),
+                    None => (Some(ranges), None),
+                };
+                if let Some(matched) = matched {
+                    workq.push_back((rule.target.clone(), matched));
+                }
+                rest = unmatched;
+            }
         }
-        if expr == "A" {
-            ans += (xh - xl + 1) * (mh - ml + 1) * (ah - al + 1) * (sh - sl + 1);
-            continue;
-        } else if let Some(e) = logic_stmts.get(expr) {
-            for cmd in e.split(',') {
-                if cmd.contains(':') {
-                    let t = cmd.split_once(':').unwrap();
-                    let (cond, res) = (t.0, t.1);
-                    let var = &cond[0..1];
-                    let op = &cond[1..2];
-                    let n = &cond[2..].parse::<usize>().unwrap();
-                    let (xl2, xh2, ml2, mh2, al2, ah2, sl2, sh2) =
-                        new_ranges(var, op, *n, xl, xh, ml, mh, al, ah, sl, sh);
-                    workq.push_back((res, xl2, xh2, ml2, mh2, al2, ah2, sl2, sh2));
-                    if op == "<" {
-                        (xl, xh, ml, mh, al, ah, sl, sh) = new_ranges(var, ">=", *n, xl, xh, ml, mh, al, ah, sl, sh);
-                    } else {
-                        (xl, xh, ml, mh, al, ah, sl, sh) = new_ranges(var, "<=", *n, xl, xh, ml, mh, al, ah, sl, sh);
-                    }
-                } else {
-                    workq.push_back((cmd, xl, xh, ml, mh, al, ah, sl, sh));
+        ans
+    }
+
+    // Graphviz rendering of the workflows, edges are labeled with their conditions
+    fn to_dot(&self) -> String {
+        let mut names: Vec<&String> = self.flows.keys().collect();
+        names.sort();
+
+        let mut dot = String::from("digraph workflows {\n");
+        dot.push_str("    A [shape=box, color=green];\n    R [shape=box, color=red];\n");
+        for name in names {
+            for rule in &self.flows[name] {
+                let target = match &rule.target {
+                    Target::Accept => "A",
+                    Target::Reject => "R",
+                    Target::Workflow(t) => t,
+                };
+                match rule.condition {
+                    Some(c) => dot.push_str(&format!("    {name} -> {target} [label=\"{c}\"];\n")),
+                    None => dot.push_str(&format!("    {name} -> {target};\n")),
                 }
             }
-        } else {
-            unreachable!()
         }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// the workflows and the parts after the empty line
+fn get_data(puzzle_lines: &[String]) -> Result<(Workflows, Vec<Part>), Box<dyn Error>> {
+    let blank = puzzle_lines
+        .iter()
+        .position(|s| s.is_empty())
+        .unwrap_or(puzzle_lines.len());
+    let workflows = Workflows::parse(&puzzle_lines[..blank])?;
+
+    // ex. {x=787,m=2655,a=1222,s=2876}
+    let mut parts = vec![];
+    for line in puzzle_lines.iter().skip(blank + 1) {
+        let mut part = [0; 4];
+        for def in line.trim_matches(|c| c == '{' || c == '}').split(',') {
+            let (k, v) = def.split_once('=').ok_or_else(|| format!("bad rating {def:?}"))?;
+            part[category(k)?] = v.parse()?;
+        }
+        parts.push(part);
     }
-    Ok(ans)
+    Ok((workflows, parts))
+}
+
+fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+    let (workflows, parts) = get_data(puzzle_lines)?;
+    Ok(parts
+        .iter()
+        .filter(|p| workflows.accepts(p))
+        .map(|p| p.iter().sum::<usize>())
+        .sum())
+}
+
+fn part2(puzzle_lines: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
+    let (workflows, _) = get_data(puzzle_lines)?;
+    dumper.text("workflows.dot", &workflows.to_dot())?;
+    Ok(workflows.accepted_combinations([(1, 4000); 4]))
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -167,7 +331,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let n = part1(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = part2(&puzzle_lines)?;
+    let n = part2(&puzzle_lines, &Dumper::from_args(&args))?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -202,14 +366,35 @@ mod tests {
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part2(&puzzle_lines)?, 167409079868000);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 167409079868000);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part2(&puzzle_lines)?, 124615747767410);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 124615747767410);
+        Ok(())
+    }
+
+    #[test]
+    fn validation() {
+        let lines = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(Workflows::parse(&lines(&["in{x<10:a,R}", "a{A}"])).is_ok());
+        assert!(Workflows::parse(&lines(&["in{x<10:b,R}", "a{A}"])).is_err());
+        assert!(Workflows::parse(&lines(&["in{x<10:a,R}", "a{m>5:in,A}"])).is_err());
+        assert!(Workflows::parse(&lines(&["in{x<10:a}", "a{A}"])).is_err());
+        assert!(Workflows::parse(&lines(&["in{q<10:a,R}", "a{A}"])).is_err());
+        assert!(Workflows::parse(&lines(&["start{A}"])).is_err());
+    }
+
+    #[test]
+    fn dot() -> Result<(), Box<dyn Error>> {
+        let (workflows, _) = super::get_data(&get_data("input-example")?)?;
+        let dot = workflows.to_dot();
+        assert!(dot.starts_with("digraph workflows {"));
+        assert!(dot.contains("    in -> px [label=\"s<1351\"];\n"));
+        assert!(dot.contains("    in -> qqz;\n"));
         Ok(())
     }
 }
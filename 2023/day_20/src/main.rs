@@ -1,3 +1,4 @@
+use general::render::Dumper;
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_ws};
 use std::collections::{HashMap, VecDeque};
 use std::error::Error;
@@ -9,220 +10,285 @@ enum Pulse {
     High,
 }
 
-#[derive(Hash, Clone, Debug, PartialEq, Eq)]
-enum ModuleType {
-    FlipFlop,
-    Conjunction,
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    // Flip-flop modules (prefix %) are either on or off; they are initially off.
+    // If a flip-flop module receives a high pulse, it is ignored and nothing happens.
+    // However, if a flip-flop module receives a low pulse, it flips between on and off.
+    // If it was off, it turns on and sends a high pulse.
+    // If it was on, it turns off and sends a low pulse.
+    FlipFlop { on: bool },
+
+    // Conjunction modules (prefix &) remember the type of the most recent pulse
+    // received from each of their connected input modules; they initially default
+    // to remembering a low pulse for each input.
+    // When a pulse is received, the conjunction module first updates its memory
+    // for that input. Then, if it remembers high pulses for all inputs,
+    // it sends a low pulse; otherwise, it sends a high pulse.
+    //
+    // memory[i] is the last pulse from inputs[i]
+    Conjunction { memory: Vec<Pulse> },
+
+    // There is a single broadcast module (named broadcaster).
+    // When it receives a pulse, it sends the same pulse to all of its destination modules.
     Broadcast,
-    Button,
+
+    // a destination that isn't defined as a module (e.g. rx), it only receives
+    Output,
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Clone, Debug)]
 struct Module {
-    mtype: ModuleType,
+    name: String,
+    kind: Kind,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+// button presses at which `node` sent a `pulse`
+#[derive(Clone, Debug)]
+struct Watch {
+    node: usize,
     pulse: Pulse,
-    dest: Vec<String>,
+    fired: Vec<usize>,
 }
 
-fn get_modules(puzzle_lines: &[String]) -> Result<HashMap<String, Module>, Box<dyn Error>> {
-    let mut hmodules = HashMap::new();
-    for line in puzzle_lines {
-        let parts = trim_split_ws::<String>(line)?;
-        let mut names = vec![];
-        for label in &parts[2..] {
-            names.push(label.replace(',', ""));
-        }
-        let (name, mtype) = if parts[0] == "broadcaster" {
-            ("broadcaster".to_string(), ModuleType::Broadcast)
-        } else if parts[0].starts_with('%') {
-            (parts[0].replace('%', "").to_string(), ModuleType::FlipFlop)
-        } else {
-            (parts[0].replace('&', "").to_string(), ModuleType::Conjunction)
+#[derive(Clone, Debug)]
+struct Circuit {
+    modules: Vec<Module>,
+    index: HashMap<String, usize>,
+    broadcaster: usize,
+    presses: usize,
+    low: usize,
+    high: usize,
+    watches: Vec<Watch>,
+}
+
+impl Circuit {
+    fn parse(puzzle_lines: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut modules: Vec<Module> = vec![];
+        let mut index = HashMap::new();
+        let mut node = |modules: &mut Vec<Module>, name: &str| -> usize {
+            *index.entry(name.to_string()).or_insert_with(|| {
+                modules.push(Module {
+                    name: name.to_string(),
+                    kind: Kind::Output,
+                    inputs: vec![],
+                    outputs: vec![],
+                });
+                modules.len() - 1
+            })
         };
 
-        hmodules.insert(
-            name,
-            Module {
-                mtype,
-                pulse: Pulse::Low,
-                dest: names,
-            },
-        );
-    }
+        // ex. %a -> inv, con
+        for line in puzzle_lines {
+            let parts = trim_split_ws::<String>(line)?;
+            if parts.len() < 3 || parts[1] != "->" {
+                return Err(format!("bad module {line:?}").into());
+            }
+            let (name, kind) = match parts[0].as_str() {
+                "broadcaster" => ("broadcaster", Kind::Broadcast),
+                s if s.starts_with('%') => (&s[1..], Kind::FlipFlop { on: false }),
+                s if s.starts_with('&') => (&s[1..], Kind::Conjunction { memory: vec![] }),
+                s => return Err(format!("unknown module type {s:?}").into()),
+            };
+            let m = node(&mut modules, name);
+            if modules[m].kind != Kind::Output {
+                return Err(format!("module {name} is defined twice").into());
+            }
+            modules[m].kind = kind;
+            for label in &parts[2..] {
+                let d = node(&mut modules, &label.replace(',', ""));
+                modules[m].outputs.push(d);
+            }
+        }
 
-    let button = Module {
-        mtype: ModuleType::Button,
-        pulse: Pulse::Low,
-        dest: vec!["broadcaster".to_string()],
-    };
-    hmodules.insert("button".to_string(), button);
+        // wire up the inputs, conjunctions remember a low pulse from each one
+        for m in 0..modules.len() {
+            for d in modules[m].outputs.clone() {
+                modules[d].inputs.push(m);
+                if let Kind::Conjunction { memory } = &mut modules[d].kind {
+                    memory.push(Pulse::Low);
+                }
+            }
+        }
 
-    Ok(hmodules)
-}
+        let broadcaster = *index.get("broadcaster").ok_or("no broadcaster module")?;
+        Ok(Circuit {
+            modules,
+            index,
+            broadcaster,
+            presses: 0,
+            low: 0,
+            high: 0,
+            watches: vec![],
+        })
+    }
 
-fn update_module_pulse(modules: &mut HashMap<String, Module>, name: &str, pulse: Pulse) {
-    // update the module pulse if it changed
-    if modules[name].pulse != pulse {
-        let mut module = modules[name].clone();
-        module.pulse = pulse;
-        modules.insert(name.to_string(), module);
+    fn node(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        self.index
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("no module {name:?}").into())
     }
-}
 
-fn solution(puzzle_lines: &[String], p2: bool) -> Result<usize, Box<dyn Error>> {
-    let mut modules = get_modules(puzzle_lines)?;
+    fn inputs(&self, name: &str) -> Result<Vec<&str>, Box<dyn Error>> {
+        let m = self.node(name)?;
+        Ok(self.modules[m]
+            .inputs
+            .iter()
+            .map(|i| self.modules[*i].name.as_str())
+            .collect())
+    }
 
-    // initialize an empty map of inputs for each conjunction module
-    let mut conjunctions = modules
-        .iter()
-        .filter(|(_, v)| matches!(v.mtype, ModuleType::Conjunction))
-        .map(|(k, _)| (k.to_string(), HashMap::<String, Pulse>::new()))
-        .collect::<HashMap<String, HashMap<String, Pulse>>>();
-
-    // populate the map with the inputs to each conjunction, initialized with a low pulse
-    for (k, v) in &modules {
-        for d in &v.dest {
-            if let Some(h) = conjunctions.get_mut(d) {
-                h.insert(k.to_string(), Pulse::Low);
-            }
-        }
+    // (low, high) pulses sent so far, including the button's
+    fn pulse_counts(&self) -> (usize, usize) {
+        (self.low, self.high)
     }
 
-    let mut cycle = HashMap::new();
-    let mut counts = HashMap::new();
+    // record the presses at which `name` sends `pulse`, returns an id for fired()
+    fn watch(&mut self, name: &str, pulse: Pulse) -> Result<usize, Box<dyn Error>> {
+        let node = self.node(name)?;
+        self.watches.push(Watch {
+            node,
+            pulse,
+            fired: vec![],
+        });
+        Ok(self.watches.len() - 1)
+    }
 
-    // Part 2 asks when the inputs to conjunction "rx" will all be low at the same time
-    // so we need to find the cycle length of each and multiply
-    let rx = modules.iter().find(|(_, v)| v.dest.contains(&"rx".to_string()));
-    // &ll -> rx
-    // kv, vm, kl, vb -> ll
-    //
-    // println!("rx_inputs = {rx_inputs:?}");
-    // ["kv", "vm", "kl", "vb"]
-    let rx_inputs = match rx {
-        Some(m) => modules
-            .iter()
-            .filter(|(_, v)| v.dest.contains(m.0))
-            .map(|(k, _)| k.to_string())
-            .collect::<Vec<_>>(),
-        None => vec![],
-    };
+    fn fired(&self, watch: usize) -> &[usize] {
+        &self.watches[watch].fired
+    }
 
-    let mut low_pulse_count = 0;
-    let mut high_pulse_count = 0;
-    let mut lcm: usize = 1;
-    for button_push in 0..10000 {
-        let mut workq = VecDeque::new();
-
-        // initialize the workq with a button press
-        workq.push_back(
-            //     sender,           pulse,            receiver
-            ("button".to_string(), Pulse::Low, "broadcaster".to_string()),
-        );
-
-        while let Some(msg) = workq.pop_front() {
-            let (sender, pulse, receiver) = msg;
-
-            // Part1: Consult your module configuration; determine the number of low pulses and high pulses
-            // that would be sent after pushing the button 1000 times, waiting for all pulses to be fully
-            // handled after each push of the button. What do you get if you multiply the total number of
-            // low pulses sent by the total number of high pulses sent?
-            if button_push == 1000 && !p2 {
-                return Ok(low_pulse_count * high_pulse_count);
+    // push the button once and wait for all pulses to be fully handled
+    fn press(&mut self) {
+        self.presses += 1;
+
+        // a single low pulse is sent directly to the broadcaster module
+        //                            sender, pulse,      receiver
+        let mut workq = VecDeque::from([(None, Pulse::Low, self.broadcaster)]);
+        while let Some((sender, pulse, receiver)) = workq.pop_front() {
+            match pulse {
+                Pulse::Low => self.low += 1,
+                Pulse::High => self.high += 1,
             }
 
-            if pulse == Pulse::Low {
-                low_pulse_count += 1;
-
-                // Reset all modules to their default states.
-                // Waiting for all pulses to be fully handled after each button press,
-                // what is the fewest number of button presses required to deliver a
-                // single low pulse to the module named rx?
-                if p2 {
-                    if rx_inputs.contains(&receiver) {
-                        *counts.entry(receiver.to_string()).or_insert(0) += 1;
-                        // On the 2nd instance of each receiver, accumulate the product of its cycle length
-                        if counts[&receiver] == 2 {
-                            //lcm *= (button_push - cycle[&receiver]) as usize;
-                            lcm = num_integer::lcm(lcm, (button_push - cycle[&receiver]) as usize);
-                        } else {
-                            cycle.entry(receiver.to_string()).or_insert(button_push);
+            let module = &mut self.modules[receiver];
+            let send = match &mut module.kind {
+                Kind::FlipFlop { on } => match pulse {
+                    Pulse::High => continue,
+                    Pulse::Low => {
+                        *on = !*on;
+                        match on {
+                            true => Pulse::High,
+                            false => Pulse::Low,
                         }
                     }
-                    // stop when the 2nd instance of all inputs have been seen & accumulated
-                    if counts.len() == rx_inputs.len() && counts.values().all(|c| *c == 2) {
-                        return Ok(lcm);
+                },
+                Kind::Conjunction { memory } => {
+                    if let Some(i) = module.inputs.iter().position(|i| Some(*i) == sender) {
+                        memory[i] = pulse;
                     }
-                }
-            } else {
-                high_pulse_count += 1;
-            }
-
-            //
-            // this section just sends pulses according to the module rules
-            //
-            if let Some(module) = modules.get(&receiver) {
-                let module = module.clone();
-                let mut send_pulse = module.pulse;
-
-                match module.mtype {
-                    // Flip-flop modules (prefix %) are either on or off; they are initially off.
-                    // If a flip-flop module receives a high pulse, it is ignored and nothing happens.
-                    // However, if a flip-flop module receives a low pulse, it flips between on and off.
-                    // If it was off, it turns on and sends a high pulse.
-                    // If it was on, it turns off and sends a low pulse.
-                    ModuleType::FlipFlop => match pulse {
-                        Pulse::High => continue,
-                        Pulse::Low => {
-                            send_pulse = match module.pulse {
-                                Pulse::Low => Pulse::High,
-                                Pulse::High => Pulse::Low,
-                            };
-                        }
-                    },
-                    // Conjunction modules (prefix &) remember the type of the most recent pulse
-                    // received from each of their connected input modules; they initially default
-                    // to remembering a low pulse for each input.
-                    // When a pulse is received, the conjunction module first updates its memory
-                    // for that input. Then, if it remembers high pulses for all inputs,
-                    // it sends a low pulse; otherwise, it sends a high pulse.
-                    ModuleType::Conjunction => {
-                        let conj = conjunctions.get_mut(&receiver).unwrap();
-                        conj.insert(sender.to_string(), pulse);
-                        send_pulse = match conj.values().all(|p| *p == Pulse::High) {
-                            true => Pulse::Low,
-                            false => Pulse::High,
-                        };
+                    match memory.iter().all(|p| *p == Pulse::High) {
+                        true => Pulse::Low,
+                        false => Pulse::High,
                     }
-                    // There is a single broadcast module (named broadcaster).
-                    // When it receives a pulse, it sends the same pulse to all of its destination modules.
-                    //
-                    // Here at Desert Machine Headquarters, there is a module with a single button
-                    // on it called, aptly, the button module. When you push the button,
-                    // a single low pulse is sent directly to the broadcaster module.
-                    ModuleType::Broadcast | ModuleType::Button => (),
                 }
-
-                // update the receiver module
-                update_module_pulse(&mut modules, &receiver, send_pulse);
-
-                // send a pulse to each destination module
-                for m in &module.dest {
-                    workq.push_back((receiver.to_string(), send_pulse, m.to_string()));
+                Kind::Broadcast => pulse,
+                Kind::Output => continue,
+            };
+
+            for w in self
+                .watches
+                .iter_mut()
+                .filter(|w| w.node == receiver && w.pulse == send)
+            {
+                if w.fired.last() != Some(&self.presses) {
+                    w.fired.push(self.presses);
                 }
             }
+            for d in &self.modules[receiver].outputs {
+                workq.push_back((Some(receiver), send, *d));
+            }
+        }
+    }
+
+    // Graphviz rendering, flip-flops are boxes and conjunctions are diamonds
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph modules {\n");
+        for m in &self.modules {
+            let shape = match m.kind {
+                Kind::FlipFlop { .. } => "box",
+                Kind::Conjunction { .. } => "diamond",
+                Kind::Broadcast => "doublecircle",
+                Kind::Output => "plaintext",
+            };
+            dot.push_str(&format!("    {} [shape={shape}];\n", m.name));
+        }
+        for m in &self.modules {
+            for d in &m.outputs {
+                dot.push_str(&format!("    {} -> {};\n", m.name, self.modules[*d].name));
+            }
         }
+        dot.push_str("}\n");
+        dot
     }
-    // NOTREACHED
-    Ok(0)
 }
 
+// Consult your module configuration; determine the number of low pulses and high pulses
+// that would be sent after pushing the button 1000 times, waiting for all pulses to be fully
+// handled after each push of the button. What do you get if you multiply the total number of
+// low pulses sent by the total number of high pulses sent?
 fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    solution(puzzle_lines, false)
+    let mut circuit = Circuit::parse(puzzle_lines)?;
+    for _ in 0..1000 {
+        circuit.press();
+    }
+    let (low, high) = circuit.pulse_counts();
+    Ok(low * high)
+}
+
+// Fewest presses for `target` to receive a low pulse when it is fed by a single conjunction
+//
+// The conjunction sends low once all of its inputs last sent high, each of those inputs
+// sends high periodically so the answer is the lcm of their periods.
+fn presses_until_low(circuit: &mut Circuit, target: &str, max_presses: usize) -> Result<usize, Box<dyn Error>> {
+    let feeders = circuit.inputs(target)?;
+    let [feeder] = feeders[..] else {
+        return Err(format!("{target} must have a single input, found {feeders:?}").into());
+    };
+    if !matches!(circuit.modules[circuit.node(feeder)?].kind, Kind::Conjunction { .. }) {
+        return Err(format!("{feeder} feeding {target} is not a conjunction").into());
+    }
+    let feeder = feeder.to_string();
+
+    let inputs: Vec<String> = circuit.inputs(&feeder)?.iter().map(|s| s.to_string()).collect();
+    let watches = inputs
+        .iter()
+        .map(|name| circuit.watch(name, Pulse::High))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    while circuit.presses < max_presses {
+        circuit.press();
+        if watches.iter().all(|w| circuit.fired(*w).len() >= 2) {
+            return Ok(watches.iter().fold(1, |lcm, w| {
+                let fired = circuit.fired(*w);
+                num_integer::lcm(lcm, fired[1] - fired[0])
+            }));
+        }
+    }
+    Err(format!("no periods found for the inputs of {feeder} in {max_presses} presses").into())
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    solution(puzzle_lines, true)
+// Reset all modules to their default states.
+// Waiting for all pulses to be fully handled after each button press,
+// what is the fewest number of button presses required to deliver a
+// single low pulse to the module named rx?
+fn part2(puzzle_lines: &[String], dumper: &Dumper) -> Result<usize, Box<dyn Error>> {
+    let mut circuit = Circuit::parse(puzzle_lines)?;
+    dumper.text("modules.dot", &circuit.to_dot())?;
+    presses_until_low(&mut circuit, "rx", 100000)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -243,7 +309,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let n = part1(&puzzle_lines)?;
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = part2(&puzzle_lines)?;
+    let n = part2(&puzzle_lines, &Dumper::from_args(&args))?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -275,6 +341,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn watch_points() -> Result<(), Box<dyn Error>> {
+        // the inverter in example 2 sends high on the presses that turn the flip-flop a off
+        let mut circuit = Circuit::parse(&get_data("input-example2")?)?;
+        assert_eq!(circuit.inputs("con")?, vec!["a", "b"]);
+        let inv = circuit.watch("inv", Pulse::High)?;
+        let con = circuit.watch("con", Pulse::Low)?;
+        for _ in 0..4 {
+            circuit.press();
+        }
+        assert_eq!(circuit.fired(inv), &[2, 4]);
+        assert_eq!(circuit.fired(con), &[1, 3]);
+        // the puzzle's 4250 low and 2750 high pulses after 1000 presses repeat every 4 presses
+        assert_eq!(circuit.pulse_counts(), (4250 / 250, 2750 / 250));
+        assert!(circuit.to_dot().contains("    con [shape=diamond];\n"));
+        Ok(())
+    }
+
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
@@ -285,7 +369,7 @@ mod tests {
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part2(&puzzle_lines)?, 241528184647003);
+        assert_eq!(part2(&puzzle_lines, &Dumper::default())?, 241528184647003);
         Ok(())
     }
 }
@@ -1,57 +1,38 @@
+use general::graph::Graph;
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
-use rustworkx_core::connectivity::stoer_wagner_min_cut;
-use rustworkx_core::petgraph::graph::UnGraph;
-use rustworkx_core::Result;
-use std::collections::HashMap;
 use std::error::Error;
 use std::io::{self, Write};
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    // start a new graph
-    let mut graph: UnGraph<(), ()> = UnGraph::new_undirected();
-    let mut edges = vec![];
-
-    // nodes is a map <String, NodeIndex>: "label" => graph.add_node(())
-    let mut nodes = HashMap::new();
-
-    // Example graph input:
-    //
-    // jqt: rhn xhk nvd
-    // rsh: frs pzl lsr
-    // xhk: hfx
-    // cmg: qnr nvd lhk bvb
-    // rhn: xhk bvb hfx
-    // bvb: xhk hfx
-    // pzl: lsr hfx nvd
-    // qnr: nvd
-    // ntq: jqt hfx bvb xhk
-    // nvd: lhk
-    // lsr: lhk
-    // rzs: qnr cmg lsr rsh
-    // frs: qnr lhk lsr
+// Example graph input:
+//
+// jqt: rhn xhk nvd
+// rsh: frs pzl lsr
+// xhk: hfx
+// ...
+fn get_graph(puzzle_lines: &[String]) -> Result<Graph, Box<dyn Error>> {
+    let mut graph = Graph::new();
     for line in puzzle_lines {
-        let parse = line.split_once(':').unwrap();
-        let (node, others) = parse;
-
-        // get the node for the left label
-        let left = *nodes.entry(node.to_string()).or_insert_with(|| graph.add_node(()));
-
-        // collect the edges paired with left
-        for node in others.split_whitespace() {
-            let right = *nodes.entry(node.to_string()).or_insert_with(|| graph.add_node(()));
-            edges.push((left, right));
+        let (node, others) = line.split_once(':').ok_or(format!("expected 'node: others': {line}"))?;
+        let left = graph.node(node.trim());
+        for other in others.split_whitespace() {
+            let right = graph.node(other);
+            graph.add_edge(left, right, 1);
         }
     }
+    Ok(graph)
+}
 
-    // create the graph from the edges
-    graph.extend_with_edges(edges);
+fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+    let graph = get_graph(puzzle_lines)?;
 
-    // perform a minimum cut using Stoer-Wagner: https://en.wikipedia.org/wiki/Stoer%E2%80%93Wagner_algorithm
-    let min_cut_res: Result<Option<(usize, Vec<_>)>> = stoer_wagner_min_cut(&graph, |_| Ok(1));
+    // perform a minimum cut using Stoer-Wagner, the puzzle promises it is three wires
+    let cut = graph.stoer_wagner().ok_or("nothing to cut")?;
+    if cut.weight != 3 {
+        return Err(format!("the minimum cut is {} wires, not 3", cut.weight).into());
+    }
 
-    // return the product of the lengths of the graphs after the cut
-    let partition = min_cut_res?.unwrap().1;
-    Ok((graph.node_count() - partition.len()) * partition.len())
+    // return the product of the sizes of the two groups after the cut
+    Ok(cut.partition.0.len() * cut.partition.1.len())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -100,4 +81,28 @@ mod tests {
         assert_eq!(part1(&puzzle_lines)?, 582692);
         Ok(())
     }
+
+    #[test]
+    fn cut_wires() -> Result<(), Box<dyn Error>> {
+        let graph = get_graph(&get_data("input-example")?)?;
+        let cut = graph.stoer_wagner().unwrap();
+        let mut wires: Vec<_> = cut
+            .edges
+            .iter()
+            .map(|(a, b)| {
+                let mut pair = [graph.label(*a), graph.label(*b)];
+                pair.sort();
+                pair
+            })
+            .collect();
+        wires.sort();
+        assert_eq!(wires, [["bvb", "cmg"], ["hfx", "pzl"], ["jqt", "nvd"]]);
+        assert_eq!(graph.karger_stein(5, 25), Some(cut.clone()));
+
+        // three disjoint paths across the cut, more than three within either group
+        let (a, b) = (cut.partition.0[0], cut.partition.1[0]);
+        assert!(graph.disjoint_paths(a, b, 3) && !graph.disjoint_paths(a, b, 4));
+        assert!(graph.disjoint_paths(a, cut.partition.0[1], 4));
+        Ok(())
+    }
 }
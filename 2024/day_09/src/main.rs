@@ -1,89 +1,207 @@
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::error::Error;
+use std::fmt;
 use std::io::{self, Write};
+use std::str::FromStr;
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    const RADIX: u32 = 10;
-    let mut disk_map: Vec<_> = puzzle_lines[0]
-        .chars()
-        .map(|c| c.to_digit(RADIX).unwrap() as usize)
-        .collect();
-
-    // create a compact disk representation to compute the checksum
-    let mut compact_disk = vec![];
-    let mut i = 0;
-    let mut j = disk_map.len() - 1;
-
-    while i <= j {
-        let even = i % 2 == 0;
-        for _ in 0..disk_map[i] {
-            if even {
-                compact_disk.push(i / 2);
-            } else {
-                if disk_map[j] == 0 {
-                    j -= 2;
-                }
-                disk_map[j] -= 1;
-                compact_disk.push(j / 2);
+// A file stored in one or more extents of (start block, length)
+#[derive(Clone, Debug, PartialEq)]
+struct File {
+    id: usize,
+    extents: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    // move single blocks from the end of the disk into the leftmost free block
+    Blocks,
+    // move whole files, highest id first, into the leftmost free span big enough
+    Files,
+}
+
+// The disk as files laid out over `size` blocks
+#[derive(Clone, Debug, PartialEq)]
+struct Disk {
+    size: usize,
+    files: Vec<File>,
+}
+
+// the dense disk map alternates file and free space lengths, 12345 is 0..111....22222
+impl FromStr for Disk {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut files = vec![];
+        let mut size = 0;
+        for (i, c) in s.trim().chars().enumerate() {
+            let len = c.to_digit(10).ok_or(format!("bad disk map digit {c:?}"))? as usize;
+            if i % 2 == 0 {
+                files.push(File {
+                    id: i / 2,
+                    extents: vec![(size, len)],
+                });
             }
+            size += len;
+        }
+        if files.is_empty() {
+            return Err("empty disk map".into());
         }
-        i += 1;
+        Ok(Disk { size, files })
     }
-
-    // checksum
-    Ok(compact_disk.iter().enumerate().map(|(i, n)| i * n).sum())
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
-    const RADIX: u32 = 10;
-    // tuple (id, len, free)
-    let mut files: Vec<_> = puzzle_lines[0]
-        .chars()
-        .map(|c| c.to_digit(RADIX).unwrap() as usize)
-        .collect::<Vec<_>>()
-        .chunks(2)
-        .enumerate()
-        .map(|(i, digits)| (i, digits[0], if digits.len() == 2 { digits[1] } else { 0 }))
-        .collect();
-
-    // process in decreasing file id order
-    for id in (1..files.len()).rev() {
-        // get the index of the candidate file to move
-        let i = files.iter().rposition(|&tup| tup.0 == id).expect("fixme");
-
-        // search for a previous file with enough free space
-        if let Some(j) = files.iter().take(i).position(|&file| file.2 >= files[i].1) {
-            // ------------------------------------
-            // Move file[i] to the right of file[j]
-            // ------------------------------------
-
-            // remove the file[i] from its current location and add its length + free space
-            // to the previous file[i-1], preserving the gap created.
-            let mut file = files.remove(i);
-            files[i - 1].2 += file.1 + file.2;
-
-            // in its new location the free space will be the space remaining from file[j]
-            file.2 = files[j].2 - file.1;
-            files.insert(j + 1, file);
-
-            // files[j]'s free space is now zero, any remaining was transferred to file[i] above
-            files[j].2 = 0;
+impl Disk {
+    // the file id stored in each block
+    fn blocks(&self) -> Vec<Option<usize>> {
+        let mut blocks = vec![None; self.size];
+        for file in &self.files {
+            for (start, len) in &file.extents {
+                blocks[*start..start + len].fill(Some(file.id));
+            }
         }
+        blocks
     }
 
-    // avoid padding the last file with zeros
-    let last = files.len() - 1;
-    files[last].2 = 0;
+    // files rebuilt from their blocks, consecutive blocks of a file make one extent
+    fn from_blocks(blocks: &[Option<usize>], nfiles: usize) -> Disk {
+        let mut files: Vec<File> = (0..nfiles).map(|id| File { id, extents: vec![] }).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            let Some(id) = block else {
+                continue;
+            };
+            let extents = &mut files[*id].extents;
+            match extents.last_mut() {
+                Some((start, len)) if *start + *len == i => *len += 1,
+                _ => extents.push((i, 1)),
+            }
+        }
+        Disk {
+            size: blocks.len(),
+            files,
+        }
+    }
 
-    // create a compact disk representation to compute the checksum
-    let mut compact_disk = vec![];
-    for f in files {
-        compact_disk.resize(compact_disk.len() + f.1, f.0);
-        compact_disk.resize(compact_disk.len() + f.2, 0);
+    fn checksum(&self) -> usize {
+        self.files
+            .iter()
+            .flat_map(|f| {
+                f.extents
+                    .iter()
+                    .filter(|(_, len)| *len > 0)
+                    .map(move |(start, len)| f.id * (start + start + len - 1) * len / 2)
+            })
+            .sum()
     }
 
-    // checksum
-    Ok(compact_disk.iter().enumerate().map(|(i, n)| i * n).sum())
+    // compact in place, `trace` collects a rendering of the disk after every move
+    fn compact(&mut self, strategy: Strategy, mut trace: Option<&mut Vec<String>>) {
+        if let Some(t) = trace.as_mut() {
+            t.push(self.to_string());
+        }
+        match strategy {
+            Strategy::Blocks => self.compact_blocks(trace),
+            Strategy::Files => self.compact_files(trace),
+        }
+    }
+
+    fn compact_blocks(&mut self, mut trace: Option<&mut Vec<String>>) {
+        let mut blocks = self.blocks();
+        let (mut i, mut j) = (0, blocks.len());
+        loop {
+            while i < blocks.len() && blocks[i].is_some() {
+                i += 1;
+            }
+            while j > 0 && blocks[j - 1].is_none() {
+                j -= 1;
+            }
+            if j == 0 || i >= j - 1 {
+                break;
+            }
+            blocks.swap(i, j - 1);
+            if let Some(t) = trace.as_mut() {
+                t.push(render(&blocks));
+            }
+        }
+        *self = Disk::from_blocks(&blocks, self.files.len());
+    }
+
+    // free spans are indexed by length, min-heaps of their starts, so each move is O(log n)
+    fn compact_files(&mut self, mut trace: Option<&mut Vec<String>>) {
+        let mut spans = vec![BinaryHeap::new(); 10];
+        let mut used: Vec<(usize, usize)> = self.files.iter().flat_map(|f| f.extents.iter().copied()).collect();
+        used.sort();
+        let mut end = 0;
+        for (start, len) in used.into_iter().chain([(self.size, 0)]) {
+            if start > end {
+                if start - end >= spans.len() {
+                    spans.resize(start - end + 1, BinaryHeap::new());
+                }
+                spans[start - end].push(Reverse(end));
+            }
+            end = end.max(start + len);
+        }
+
+        // files move left so the space they leave behind is never useful to the files still to come
+        for id in (0..self.files.len()).rev() {
+            let [(start, len)] = self.files[id].extents[..] else {
+                continue;
+            };
+            if len == 0 {
+                continue;
+            }
+            let best = (len..spans.len())
+                .filter_map(|n| spans[n].peek().map(|Reverse(at)| (*at, n)))
+                .min();
+            let Some((at, n)) = best.filter(|(at, _)| *at < start) else {
+                continue;
+            };
+            spans[n].pop();
+            if n > len {
+                spans[n - len].push(Reverse(at + len));
+            }
+            self.files[id].extents = vec![(at, len)];
+            if let Some(t) = trace.as_mut() {
+                t.push(self.to_string());
+            }
+        }
+    }
+}
+
+fn render(blocks: &[Option<usize>]) -> String {
+    blocks
+        .iter()
+        .map(|b| match b {
+            Some(id) => char::from_digit((id % 36) as u32, 36).unwrap(),
+            None => '.',
+        })
+        .collect()
+}
+
+// one character per block, file ids in base 36 (wrapping) and '.' for free space
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", render(&self.blocks()))
+    }
+}
+
+// `trace` collects the disk layout before and after every move
+fn solve(
+    puzzle_lines: &[String],
+    strategy: Strategy,
+    trace: Option<&mut Vec<String>>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut disk: Disk = puzzle_lines.first().ok_or("no disk map")?.parse()?;
+    disk.compact(strategy, trace);
+    Ok(disk.checksum())
+}
+
+fn part1(puzzle_lines: &[String], trace: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    solve(puzzle_lines, Strategy::Blocks, trace)
+}
+
+fn part2(puzzle_lines: &[String], trace: Option<&mut Vec<String>>) -> Result<usize, Box<dyn Error>> {
+    solve(puzzle_lines, Strategy::Files, trace)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -102,9 +220,17 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let n = part1(&puzzle_lines)?;
+    let verbose = args.get_flag("verbose");
+    let mut trace = vec![];
+    let n = part1(&puzzle_lines, verbose.then_some(&mut trace))?;
+    for line in trace.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = part2(&puzzle_lines)?;
+    let n = part2(&puzzle_lines, verbose.then_some(&mut trace))?;
+    for line in trace {
+        writeln!(stdout, "{line}")?;
+    }
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -125,28 +251,77 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part1(&puzzle_lines)?, 1928);
+        assert_eq!(part1(&puzzle_lines, None)?, 1928);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part1(&puzzle_lines)?, 6241633730082);
+        assert_eq!(part1(&puzzle_lines, None)?, 6241633730082);
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part2(&puzzle_lines)?, 2858);
+        assert_eq!(part2(&puzzle_lines, None)?, 2858);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part2(&puzzle_lines)?, 6265268809555);
+        assert_eq!(part2(&puzzle_lines, None)?, 6265268809555);
+        Ok(())
+    }
+
+    #[test]
+    fn block_steps() -> Result<(), Box<dyn Error>> {
+        let mut disk: Disk = "12345".parse()?;
+        let mut trace = vec![];
+        disk.compact(Strategy::Blocks, Some(&mut trace));
+        assert_eq!(
+            trace,
+            [
+                "0..111....22222",
+                "02.111....2222.",
+                "022111....222..",
+                "0221112...22...",
+                "02211122..2....",
+                "022111222......",
+            ]
+        );
+        assert_eq!(disk.files[2].extents, [(1, 2), (6, 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn file_steps() -> Result<(), Box<dyn Error>> {
+        let mut disk: Disk = get_data("input-example")?[0].parse()?;
+        let mut trace = vec![];
+        disk.compact(Strategy::Files, Some(&mut trace));
+        assert_eq!(
+            trace,
+            [
+                "00...111...2...333.44.5555.6666.777.888899",
+                "0099.111...2...333.44.5555.6666.777.8888..",
+                "0099.1117772...333.44.5555.6666.....8888..",
+                "0099.111777244.333....5555.6666.....8888..",
+                "00992111777.44.333....5555.6666.....8888..",
+            ]
+        );
+        assert_eq!(disk.checksum(), 2858);
+
+        // an empty file stays where it is and adds nothing
+        for (strategy, layout, checksum) in [(Strategy::Blocks, "11..", 1), (Strategy::Files, ".11.", 3)] {
+            let mut disk: Disk = "0121".parse()?;
+            assert_eq!(disk.checksum(), 3);
+            disk.compact(strategy, None);
+            assert_eq!(disk.to_string(), layout);
+            assert_eq!(disk.checksum(), checksum);
+        }
+        assert!("12a".parse::<Disk>().is_err());
         Ok(())
     }
 }
@@ -1,5 +1,6 @@
+use general::linear::LinearSystem;
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
-use num_bigint::BigInt;
+use num::{BigInt, ToPrimitive};
 use regex::Regex;
 use std::error::Error;
 use std::io::{self, Write};
@@ -40,76 +41,31 @@ fn get_machines(data: &[String]) -> Result<Vec<Behavior>, Box<dyn Error>> {
         .collect())
 }
 
-// matrix determinant using BigInt
-fn det(mat: &[BigInt]) -> BigInt {
-    let mut sz = 2;
-    while sz * sz != mat.len() {
-        sz += 1;
-    }
-
-    if sz == 2 {
-        mat[0].clone() * mat[3].clone() - mat[1].clone() * mat[2].clone()
-    } else {
-        let mut result = BigInt::from(0);
-        let mut sign = 1;
-        for n in 0..sz {
-            let mut v = vec![];
-            for i in 1..sz {
-                for j in (0..sz).filter(|j| *j != n) {
-                    v.push(mat[i * sz + j].clone())
-                }
-            }
-            result += sign * mat[n].clone() * det(&v);
-            sign = -sign;
-        }
-        result
-    }
-}
-
 fn solve(puzzle_lines: &[String], part2: bool) -> Result<usize, Box<dyn Error>> {
     let mut total = BigInt::from(0);
 
     for machine in get_machines(puzzle_lines)? {
-        // coefficient matrix
-        let a = [machine.a.0, machine.b.0, machine.a.1, machine.b.1];
-
         // solution vector
         let b = match part2 {
             true => [machine.p.0 + 10000000000000, machine.p.1 + 10000000000000],
             false => [machine.p.0, machine.p.1],
         };
 
-        // =============
-        // Cramer's Rule
-        // =============
-
-        // replace columns in "a" with solution vectors for x,y
-        let mut bx = a;
-        let mut by = a;
-        for i in 0..b.len() {
-            bx[b.len() * i] = b[i];
-            by[b.len() * i + 1] = b[i];
-        }
-
-        // prepare the large integer solution using BigInt
-        let a: Vec<BigInt> = a.into_iter().map(BigInt::from).collect();
-        let bx: Vec<BigInt> = bx.into_iter().map(BigInt::from).collect();
-        let by: Vec<BigInt> = by.into_iter().map(BigInt::from).collect();
-
-        // determinant of the coefficient matrix is the denominator in solutions
-        let x = det(&bx) / det(&a);
-        let y = det(&by) / det(&a);
+        // a * A + b * B = P, solved exactly
+        let mut system = LinearSystem::new(2);
+        system.push(&[machine.a.0, machine.b.0], b[0])?;
+        system.push(&[machine.a.1, machine.b.1], b[1])?;
 
-        // sum valid solutions
-        if x.clone() * a[0].clone() + y.clone() * a[1].clone() == b[0].into()
-            && x.clone() * a[2].clone() + y.clone() * a[3].clone() == b[1].into()
-        {
-            total += 3 * x + y;
+        // sum the valid solutions, a whole number of presses for each button
+        if let Some(presses) = system.solve().naturals() {
+            total += 3 * &presses[0] + &presses[1];
         }
     }
 
     // returne the BigInt converted to a usize
-    Ok(total.to_string().parse::<usize>()?)
+    total
+        .to_usize()
+        .ok_or_else(|| format!("{total} doesn't fit a usize").into())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -1,4 +1,5 @@
-use counter::Counter;
+use general::picture::PictureDetector;
+use general::render::Dumper;
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use pathfinding::prelude::Grid;
 use regex::Regex;
@@ -38,7 +39,13 @@ fn get_robots(data: &[String]) -> Result<Vec<Robot>, Box<dyn Error>> {
         .collect())
 }
 
-fn solve(puzzle_lines: &[String], width: usize, height: usize, part2: bool) -> Result<usize, Box<dyn Error>> {
+fn solve(
+    puzzle_lines: &[String],
+    width: usize,
+    height: usize,
+    part2: bool,
+    dumper: &Dumper,
+) -> Result<usize, Box<dyn Error>> {
     let width = width as i64;
     let height = height as i64;
     let robots = get_robots(puzzle_lines)?;
@@ -77,46 +84,34 @@ fn solve(puzzle_lines: &[String], width: usize, height: usize, part2: bool) -> R
         }
         Ok(quads.iter().product())
     } else {
-        let mut points: Vec<_> = robots.iter().enumerate().map(|(i, r)| (i, r.start)).collect();
-        let mut best = 0;
-        let mut result = 0;
-        let mut g = Grid::new(0, 0);
-        for iteration in 1..10000 {
-            points = points
+        // robot positions after t seconds as (row, col)
+        let frame = |t: usize| -> Vec<(i64, i64)> {
+            let t = t as i64;
+            robots
+                .iter()
+                .map(|r| {
+                    (
+                        (r.start.y + r.velocity.y * t).rem_euclid(height),
+                        (r.start.x + r.velocity.x * t).rem_euclid(width),
+                    )
+                })
+                .collect()
+        };
+
+        // the positions repeat every width * height seconds
+        let detector = PictureDetector::new(5);
+        let ranked = detector.rank(1..(width * height) as usize, frame);
+        detector.dump(dumper, "part2-frame", &ranked, frame)?;
+
+        let best = ranked.first().ok_or("no frames to rank")?;
+        if dumper.is_enabled() {
+            let g = frame(best.step)
                 .into_iter()
-                .map(|(i, p)| (i, advance(p, robots[i].velocity)))
-                .collect();
-
-            // sum the frequency of the most populated row and column
-            let heuristic = points
-                .clone()
-                .into_iter()
-                .map(|(_, p)| p.x as usize)
-                .collect::<Counter<_>>()
-                .values()
-                .max()
-                .unwrap()
-                + points
-                    .clone()
-                    .into_iter()
-                    .map(|(_, p)| p.y as usize)
-                    .collect::<Counter<_>>()
-                    .values()
-                    .max()
-                    .unwrap();
-
-            if heuristic > best {
-                best = heuristic;
-                result = iteration;
-                g = points
-                    .clone()
-                    .into_iter()
-                    .map(|(_, p)| (p.x as usize, p.y as usize))
-                    .collect::<Grid>();
-            }
+                .map(|(r, c)| (c as usize, r as usize))
+                .collect::<Grid>();
+            dumper.text("part2-tree.txt", &format!("{g:?}\n{best:?}\n"))?;
         }
-        println!("{g:?}\n{best}\n");
-        Ok(result)
+        Ok(best.step)
     }
 }
 
@@ -136,10 +131,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let n = solve(&puzzle_lines, 101, 103, false)?;
+    let dumper = Dumper::from_args(&args).with_scale(4);
+    let n = solve(&puzzle_lines, 101, 103, false, &dumper)?;
     writeln!(stdout, "Answer Part 1 = {n}")?;
     if puzzle_lines.len() > 499 {
-        let n = solve(&puzzle_lines, 101, 103, true)?;
+        let n = solve(&puzzle_lines, 101, 103, true, &dumper)?;
         writeln!(stdout, "Answer Part 2 = {n}")?;
     }
 
@@ -161,21 +157,23 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(solve(&puzzle_lines, 11, 7, false)?, 12);
+        assert_eq!(solve(&puzzle_lines, 11, 7, false, &Dumper::default())?, 12);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(solve(&puzzle_lines, 101, 103, false)?, 228690000);
+        assert_eq!(solve(&puzzle_lines, 101, 103, false, &Dumper::default())?, 228690000);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(solve(&puzzle_lines, 101, 103, true)?, 7093);
+        assert_eq!(solve(&puzzle_lines, 101, 103, true, &Dumper::default())?, 7093);
+        // a 1x1 room repeats at once, leaving no frames to rank
+        assert!(solve(&puzzle_lines, 1, 1, true, &Dumper::default()).is_err());
         Ok(())
     }
 }
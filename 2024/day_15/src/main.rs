@@ -1,3 +1,4 @@
+use general::animate::{Animation, Color};
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe};
 use pathfinding::matrix::*;
 use std::collections::VecDeque;
@@ -33,9 +34,14 @@ fn get_grid_moves(data: &[String]) -> Result<(Matrix<char>, Point, Vec<Direction
     Ok((grid, robot, moves))
 }
 
-fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn display(anim: &mut Animation, title: &str, grid: &Matrix<char>) -> io::Result<()> {
+    anim.draw(title, grid.iter().map(|row| row.iter().copied()))
+}
+
+fn part1(puzzle_lines: &[String], anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let (mut grid, mut robot, moves) = get_grid_moves(puzzle_lines)?;
 
+    display(anim, "Part 1", &grid)?;
     for m in moves {
         if let Some(p) = grid.move_in_direction(robot, m) {
             if grid[p] == '.' {
@@ -59,6 +65,7 @@ fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
                 }
             }
         }
+        display(anim, "Part 1", &grid)?;
     }
 
     Ok(grid
@@ -68,7 +75,7 @@ fn part1(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
         .sum())
 }
 
-fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
+fn part2(puzzle_lines: &[String], anim: &mut Animation) -> Result<usize, Box<dyn Error>> {
     let puzzle_lines = puzzle_lines
         .iter()
         .map(|line| {
@@ -81,23 +88,13 @@ fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
         .collect::<Vec<_>>();
     let (mut grid, mut robot, moves) = get_grid_moves(&puzzle_lines)?;
 
-    #[allow(unused_variables)]
-    let display = |g: Matrix<char>| {
-        for row in g.iter() {
-            for elem in row {
-                print!("{elem}");
-            }
-            println!();
-        }
-    };
-
+    display(anim, "Part 2", &grid)?;
     for m in moves {
         if let Some(p) = grid.move_in_direction(robot, m) {
             if grid[p] == '.' {
                 grid[robot] = '.';
                 robot = p;
                 grid[robot] = '@';
-                //display(grid.clone());
             } else if grid[p] == '[' || grid[p] == ']' {
                 let bx = if grid[p] == '[' {
                     (p, grid.move_in_direction(p, directions::E).unwrap())
@@ -191,8 +188,8 @@ fn part2(puzzle_lines: &[String]) -> Result<usize, Box<dyn Error>> {
                     grid[robot] = '@';
                 }
             }
-            //display(grid.clone());
         }
+        display(anim, "Part 2", &grid)?;
     }
 
     Ok(grid
@@ -218,9 +215,15 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let n = part1(&puzzle_lines)?;
+    let mut anim = Animation::from_args(&args)
+        .with_color('#', Color::Gray)
+        .with_color('@', Color::BrightRed)
+        .with_color('O', Color::BrightYellow)
+        .with_color('[', Color::BrightYellow)
+        .with_color(']', Color::BrightYellow);
+    let n = part1(&puzzle_lines, &mut anim)?;
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = part2(&puzzle_lines)?;
+    let n = part2(&puzzle_lines, &mut anim)?;
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -241,56 +244,56 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part1(&puzzle_lines)?, 2028);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 2028);
         Ok(())
     }
 
     #[test]
     fn part1_example2() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example2")?;
-        assert_eq!(part1(&puzzle_lines)?, 10092);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 10092);
         Ok(())
     }
 
     #[test]
     fn part1_example3() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example3")?;
-        assert_eq!(part1(&puzzle_lines)?, 908);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 908);
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part1(&puzzle_lines)?, 1486930);
+        assert_eq!(part1(&puzzle_lines, &mut Animation::default())?, 1486930);
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(part2(&puzzle_lines)?, 1751);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 1751);
         Ok(())
     }
 
     #[test]
     fn part2_example2() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example2")?;
-        assert_eq!(part2(&puzzle_lines)?, 9021);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 9021);
         Ok(())
     }
 
     #[test]
     fn part2_example3() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example3")?;
-        assert_eq!(part2(&puzzle_lines)?, 618);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 618);
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(part2(&puzzle_lines)?, 1492011);
+        assert_eq!(part2(&puzzle_lines, &mut Animation::default())?, 1492011);
         Ok(())
     }
 }
@@ -1,5 +1,4 @@
 use general::{get_args, read_trimmed_data_lines, reset_sigpipe, trim_split_on};
-use std::collections::VecDeque;
 use std::error::Error;
 use std::io::{self, Write};
 
@@ -21,29 +20,72 @@ fn get_program(data: &[String]) -> Result<(Vec<usize>, Vec<usize>), Box<dyn Erro
     Ok((registers, program))
 }
 
-fn run_program(program: &[usize], registers: &mut [usize]) -> Vec<usize> {
+const MNEMONICS: [&str; 8] = ["adv", "bxl", "bst", "jnz", "bxc", "out", "bdv", "cdv"];
+
+// opcodes whose operand is a combo operand, the others are literal (bxc ignores its operand)
+fn is_combo(opcode: usize) -> bool {
+    matches!(opcode, 0 | 2 | 5 | 6 | 7)
+}
+
+// combo operands 0-3 are literals, 4-6 are registers A, B and C, 7 is reserved
+fn combo_operand(operand: usize, registers: &[usize]) -> Result<usize, Box<dyn Error>> {
+    match operand {
+        0..=3 => Ok(operand),
+        4..=6 => Ok(registers[operand - 4]),
+        _ => Err(format!("reserved combo operand {operand}").into()),
+    }
+}
+
+fn render_operand(opcode: usize, operand: usize) -> String {
+    match (is_combo(opcode), operand) {
+        (true, 4..=6) => ["A", "B", "C"][operand - 4].to_string(),
+        (true, 7) => "?7".to_string(),
+        (false, _) if opcode == 4 => String::new(),
+        _ => operand.to_string(),
+    }
+}
+
+// one line per instruction, e.g. "  4: cdv B"
+fn disassemble(program: &[usize]) -> Result<Vec<String>, Box<dyn Error>> {
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| match pair {
+            [opcode @ 0..=7, operand @ 0..=7] => {
+                let text = format!("{} {}", MNEMONICS[*opcode], render_operand(*opcode, *operand));
+                Ok(format!("{:>3}: {}", 2 * i, text.trim_end()))
+            }
+            _ => Err(format!("bad instruction {pair:?} at {}", 2 * i).into()),
+        })
+        .collect()
+}
+
+// A divided by 2^n, shifting out every bit once n reaches the width of A
+fn shift(a: usize, n: usize) -> usize {
+    u32::try_from(n).ok().and_then(|n| a.checked_shr(n)).unwrap_or(0)
+}
+
+// run the program, `trace` collects each executed instruction with the registers after it
+fn execute(
+    program: &[usize],
+    registers: &mut [usize],
+    mut trace: Option<&mut Vec<String>>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
     let mut pc = 0;
     let mut output = vec![];
 
-    while pc < program.len() - 1 {
+    while pc + 1 < program.len() {
         let mut jmp = false;
-        let (opcode, operand) = (program[pc], program[pc + 1]);
-        let combo_operand = match operand {
-            n if [0, 1, 2, 3].contains(&n) => n,
-            4 => registers[0],
-            5 => registers[1],
-            6 => registers[2],
-            _ => unreachable!(),
-        };
-        //println!("opcode {opcode}, operand {operand}, combo_operand {combo_operand}");
+        let (at, opcode, operand) = (pc, program[pc], program[pc + 1]);
+        let combo = || combo_operand(operand, registers);
 
         match opcode {
             // adv
-            0 => registers[0] >>= combo_operand,
+            0 => registers[0] = shift(registers[0], combo()?),
             // bxl
             1 => registers[1] ^= operand,
             // bst
-            2 => registers[1] = combo_operand & 0b111,
+            2 => registers[1] = combo()? & 0b111,
             // jnz
             3 => {
                 if registers[0] != 0 {
@@ -54,65 +96,144 @@ fn run_program(program: &[usize], registers: &mut [usize]) -> Vec<usize> {
             // bxc
             4 => registers[1] ^= registers[2],
             // out
-            5 => output.push(combo_operand & 0b111),
+            5 => output.push(combo()? & 0b111),
             // bdv
-            6 => registers[1] = registers[0] >> combo_operand,
+            6 => registers[1] = shift(registers[0], combo()?),
             // cdv
-            7 => registers[2] = registers[0] >> combo_operand,
-            _ => unreachable!(),
+            7 => registers[2] = shift(registers[0], combo()?),
+            _ => return Err(format!("bad opcode {opcode} at {pc}").into()),
+        }
+        if let Some(trace) = trace.as_mut() {
+            let text = format!("{} {}", MNEMONICS[opcode], render_operand(opcode, operand));
+            trace.push(format!(
+                "{at:>3}: {:<6} A={} B={} C={}",
+                text.trim_end(),
+                registers[0],
+                registers[1],
+                registers[2]
+            ));
         }
         if !jmp {
             pc += 2
         }
     }
-    output
+    Ok(output)
+}
+
+fn run_program(program: &[usize], registers: &mut [usize]) -> Result<Vec<usize>, Box<dyn Error>> {
+    execute(program, registers, None)
 }
 
-#[allow(dead_code)]
-fn _dfs(program: &[usize], d: usize, n: usize) -> usize {
-    let mut best = usize::MAX;
-    if d == 0 || run_program(program, &mut [n, 0, 0]) == program[program.len() - d..] {
-        if d == program.len() {
-            return n;
+// Check the program is a single loop which
+//
+//   - ends with the only jump, "jnz 0"
+//   - shifts A right by 3 exactly once with "adv 3" and never changes A otherwise
+//   - outputs exactly once
+//   - writes B and C before reading them, so only A carries between iterations
+//
+// then each output only depends on the lowest bits of A at the start of its iteration
+// and the 3 bits shifted off A per output can be found from the last output backwards.
+fn check_shift_loop(program: &[usize]) -> Result<(), Box<dyn Error>> {
+    disassemble(program)?;
+    let instructions: Vec<(usize, usize)> = program.chunks(2).map(|p| (p[0], p[1])).collect();
+    let count = |f: &dyn Fn(&(usize, usize)) -> bool| instructions.iter().filter(|i| f(i)).count();
+
+    if instructions.last() != Some(&(3, 0)) || count(&|i| i.0 == 3) != 1 {
+        return Err("the program must end with its only jump, jnz 0".into());
+    }
+    if count(&|i| i.0 == 0) != 1 || !instructions.contains(&(0, 3)) {
+        return Err("the program must shift A by 3 bits exactly once (adv 3)".into());
+    }
+    if count(&|i| i.0 == 5) != 1 {
+        return Err("the program must output exactly once per loop".into());
+    }
+
+    // registers read and written by an instruction, A=0, B=1, C=2
+    let access = |(opcode, operand): (usize, usize)| -> (Vec<usize>, Vec<usize>) {
+        let mut reads = match (is_combo(opcode), operand) {
+            (true, 4..=6) => vec![operand - 4],
+            _ => vec![],
+        };
+        let writes = match opcode {
+            1 | 2 | 4 | 6 => vec![1],
+            7 => vec![2],
+            _ => vec![],
+        };
+        match opcode {
+            0 | 6 | 7 => reads.push(0),
+            1 => reads.push(1),
+            4 => reads.extend([1, 2]),
+            _ => (),
         }
-        for i in 0..8 {
-            best = best.min(_dfs(program, d + 1, n << 3 | i))
+        (reads, writes)
+    };
+    let mut written = [true, false, false];
+    for (i, inst) in instructions.iter().enumerate() {
+        let (reads, writes) = access(*inst);
+        if let Some(r) = reads.iter().find(|r| !written[**r]) {
+            return Err(format!(
+                "{} is read at {} before it is written in the loop",
+                ["A", "B", "C"][*r],
+                2 * i
+            )
+            .into());
+        }
+        for w in writes {
+            written[w] = true;
         }
     }
-    best
-}
-#[allow(dead_code)]
-fn dfs(program: &[usize]) -> usize {
-    _dfs(program, 0, 0)
+    Ok(())
 }
 
-fn priorityq(program: &[usize]) -> usize {
-    let mut workq = VecDeque::new();
-    workq.push_back((0, 0));
-    while let Some((d, n)) = workq.pop_front() {
-        if d == 0 || run_program(program, &mut [n, 0, 0]) == program[program.len() - d..] {
-            if d == program.len() {
-                return n;
+// the smallest initial A whose output is `target`, for programs that fit check_shift_loop()
+fn smallest_a_for_output(program: &[usize], target: &[usize]) -> Result<usize, Box<dyn Error>> {
+    check_shift_loop(program)?;
+
+    // depth first from the highest 3 bits, each tried in ascending order, so the first complete match is the smallest
+    fn search(program: &[usize], target: &[usize], d: usize, a: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        if d == target.len() {
+            return Ok(Some(a));
+        }
+        for i in 0..8 {
+            let next = a << 3 | i;
+            if next == 0 {
+                continue;
             }
-            for i in 0..8 {
-                workq.push_back((d + 1, n << 3 | i));
+            if run_program(program, &mut [next, 0, 0])? == target[target.len() - d - 1..] {
+                if let Some(a) = search(program, target, d + 1, next)? {
+                    return Ok(Some(a));
+                }
             }
         }
+        Ok(None)
+    }
+
+    match target.len() {
+        0 => Err("the target output is empty".into()),
+        // each output takes 3 more bits of A
+        n if 3 * n > usize::BITS as usize => {
+            Err(format!("{n} outputs need more than {} bits of A", usize::BITS).into())
+        }
+        _ => search(program, target, 0, 0)?.ok_or_else(|| "no value of A produces the target".into()),
     }
-    usize::MAX
 }
 
-fn solve(puzzle_lines: &[String], part2: bool) -> Result<String, Box<dyn Error>> {
+// `details` collects the execution trace for part 1 and the disassembled loop for part 2
+fn solve(puzzle_lines: &[String], part2: bool, details: Option<&mut Vec<String>>) -> Result<String, Box<dyn Error>> {
     let (mut registers, program) = get_program(puzzle_lines)?;
     if !part2 {
-        Ok(run_program(&program, &mut registers)
+        Ok(execute(&program, &mut registers, details)?
             .iter()
             .map(|n| n.to_string())
             .collect::<Vec<_>>()
             .join(","))
     } else {
-        //assert_eq!(dfs(&program), priorityq(&program));
-        Ok(priorityq(&program).to_string())
+        // the program outputs a copy of itself
+        let a = smallest_a_for_output(&program, &program)?;
+        if let Some(details) = details {
+            details.extend(disassemble(&program)?);
+        }
+        Ok(a.to_string())
     }
 }
 
@@ -132,9 +253,17 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // ==============================================================
 
-    let n = solve(&puzzle_lines, false)?;
+    let verbose = args.get_flag("verbose");
+    let mut details = vec![];
+    let n = solve(&puzzle_lines, false, verbose.then_some(&mut details))?;
+    for line in details.drain(..) {
+        writeln!(stdout, "{line}")?;
+    }
     writeln!(stdout, "Answer Part 1 = {n}")?;
-    let n = solve(&puzzle_lines, true)?;
+    let n = solve(&puzzle_lines, true, verbose.then_some(&mut details))?;
+    for line in details {
+        writeln!(stdout, "{line}")?;
+    }
     writeln!(stdout, "Answer Part 2 = {n}")?;
 
     if args.get_flag("time") {
@@ -155,28 +284,73 @@ mod tests {
     #[test]
     fn part1_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example")?;
-        assert_eq!(solve(&puzzle_lines, false)?, "4,6,3,5,6,3,5,2,1,0".to_string());
+        assert_eq!(solve(&puzzle_lines, false, None)?, "4,6,3,5,6,3,5,2,1,0".to_string());
         Ok(())
     }
 
     #[test]
     fn part1_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(solve(&puzzle_lines, false)?, "1,2,3,1,3,2,5,3,1".to_string());
+        assert_eq!(solve(&puzzle_lines, false, None)?, "1,2,3,1,3,2,5,3,1".to_string());
         Ok(())
     }
 
     #[test]
     fn part2_example() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-example2")?;
-        assert_eq!(solve(&puzzle_lines, true)?, "117440".to_string());
+        assert_eq!(solve(&puzzle_lines, true, None)?, "117440".to_string());
         Ok(())
     }
 
     #[test]
     fn part2_actual() -> Result<(), Box<dyn Error>> {
         let puzzle_lines = get_data("input-actual")?;
-        assert_eq!(solve(&puzzle_lines, true)?, "105706277661082".to_string());
+        assert_eq!(solve(&puzzle_lines, true, None)?, "105706277661082".to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn disassembler() -> Result<(), Box<dyn Error>> {
+        let (_, program) = get_program(&get_data("input-actual")?)?;
+        assert_eq!(
+            disassemble(&program)?,
+            vec![
+                "  0: bst A",
+                "  2: bxl 5",
+                "  4: cdv B",
+                "  6: bxl 6",
+                "  8: adv 3",
+                " 10: bxc",
+                " 12: out B",
+                " 14: jnz 0"
+            ]
+        );
+        let mut trace = vec![];
+        solve(&get_data("input-example")?, false, Some(&mut trace))?;
+        assert_eq!(trace[0], "  0: adv 1  A=364 B=0 C=0");
+        assert_eq!(trace[2], "  4: jnz 0  A=364 B=0 C=0");
+        assert_eq!(trace.len(), 30);
+        assert!(run_program(&[5, 7], &mut [0, 0, 0]).is_err());
+        // bdv B, out B with B past 32 bits shifts everything out of A
+        assert_eq!(run_program(&[6, 5, 5, 5], &mut [8, (1 << 32) + 3, 0])?, vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn structure() -> Result<(), Box<dyn Error>> {
+        // A is shifted by 1 bit in the part 1 example
+        let (_, program) = get_program(&get_data("input-example")?)?;
+        assert!(check_shift_loop(&program).is_err());
+        // B carries over from the previous loop
+        assert!(check_shift_loop(&[0, 3, 1, 1, 5, 5, 3, 0]).is_err());
+        // an output sequence other than the program
+        let (_, program) = get_program(&get_data("input-actual")?)?;
+        assert_eq!(
+            run_program(&program, &mut [smallest_a_for_output(&program, &[1, 2, 3])?, 0, 0])?,
+            vec![1, 2, 3]
+        );
+        let e = smallest_a_for_output(&program, &[1; 22]).unwrap_err();
+        assert_eq!(e.to_string(), "22 outputs need more than 64 bits of A");
         Ok(())
     }
 }
@@ -0,0 +1,251 @@
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// ANSI foreground colors used to paint cell classes
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl Color {
+    fn code(&self) -> u8 {
+        match self {
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+            Color::Gray => 90,
+            Color::BrightRed => 91,
+            Color::BrightGreen => 92,
+            Color::BrightYellow => 93,
+            Color::BrightBlue => 94,
+            Color::BrightMagenta => 95,
+            Color::BrightCyan => 96,
+            Color::BrightWhite => 97,
+        }
+    }
+}
+
+const HOME: &str = "\x1b[H";
+const CLEAR_SCREEN: &str = "\x1b[2J";
+const CLEAR_LINE: &str = "\x1b[K";
+const CLEAR_BELOW: &str = "\x1b[J";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const RESET: &str = "\x1b[0m";
+
+// Redraws a grid of chars in place on stderr, one frame per call to draw()
+//
+// An Animation created by default() (or from_args() without --animate) is disabled
+// and draw() is a no-op, so solvers can call it unconditionally
+#[derive(Debug, Default)]
+pub struct Animation {
+    delay: Option<Duration>,
+    palette: HashMap<char, Color>,
+    frame: usize,
+    last: Option<Instant>,
+}
+
+impl Animation {
+    // an enabled animation running at `fps` frames per second, fps <= 0 doesn't sleep
+    pub fn new(fps: f64) -> Self {
+        let delay = match fps.is_finite() && fps > 0.0 {
+            true => Duration::from_secs_f64(1.0 / fps),
+            false => Duration::ZERO,
+        };
+        Self {
+            delay: Some(delay),
+            palette: HashMap::new(),
+            frame: 0,
+            last: None,
+        }
+    }
+
+    // enabled when the "--animate [fps]" runner option was given
+    pub fn from_args(args: &ArgMatches) -> Self {
+        match args.get_one::<f64>("animate") {
+            Some(fps) => Self::new(*fps),
+            None => Self::default(),
+        }
+    }
+
+    // paint every `c` cell with `color`
+    pub fn with_color(mut self, c: char, color: Color) -> Self {
+        self.palette.insert(c, color);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.delay.is_some()
+    }
+
+    // number of frames drawn so far
+    pub fn frames(&self) -> usize {
+        self.frame
+    }
+
+    // the escape sequences drawing the next frame, the first one also clears the screen
+    pub fn render<I, R>(&self, title: &str, rows: I) -> String
+    where
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = char>,
+    {
+        let mut buf = String::new();
+        if self.frame == 0 {
+            buf.push_str(HIDE_CURSOR);
+            buf.push_str(CLEAR_SCREEN);
+        }
+        buf.push_str(HOME);
+        buf.push_str(&format!("{title} [frame {}]{CLEAR_LINE}\n", self.frame));
+        for row in rows {
+            let mut current = None;
+            for c in row {
+                let color = self.palette.get(&c).copied();
+                if color != current {
+                    match color {
+                        Some(color) => buf.push_str(&format!("\x1b[{}m", color.code())),
+                        None => buf.push_str(RESET),
+                    }
+                    current = color;
+                }
+                buf.push(c);
+            }
+            if current.is_some() {
+                buf.push_str(RESET);
+            }
+            buf.push_str(CLEAR_LINE);
+            buf.push('\n');
+        }
+        buf.push_str(CLEAR_BELOW);
+        buf
+    }
+
+    // draw one frame from rows of cells, `title` is shown above the grid
+    pub fn draw<I, R>(&mut self, title: &str, rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = char>,
+    {
+        let Some(delay) = self.delay else {
+            return Ok(());
+        };
+
+        // render the whole frame before touching the terminal to avoid flicker
+        let buf = self.render(title, rows);
+
+        // hold the previous frame on screen for the remainder of its time slot
+        if let Some(last) = self.last {
+            let elapsed = last.elapsed();
+            if elapsed < delay {
+                std::thread::sleep(delay - elapsed);
+            }
+        }
+
+        let mut stderr = io::stderr().lock();
+        stderr.write_all(buf.as_bytes())?;
+        stderr.flush()?;
+
+        self.last = Some(Instant::now());
+        self.frame += 1;
+        Ok(())
+    }
+
+    // draw one frame of a sparse point set, `c` at each point and '.' elsewhere
+    // within the bounding box of the points
+    pub fn draw_points<I>(&mut self, title: &str, points: I, c: char) -> io::Result<()>
+    where
+        I: IntoIterator<Item = (i64, i64)>,
+    {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let points = points.into_iter().collect::<std::collections::HashSet<_>>();
+        if points.is_empty() {
+            return self.draw(title, std::iter::empty::<std::iter::Empty<char>>());
+        }
+        let minr = points.iter().map(|p| p.0).min().unwrap();
+        let maxr = points.iter().map(|p| p.0).max().unwrap();
+        let minc = points.iter().map(|p| p.1).min().unwrap();
+        let maxc = points.iter().map(|p| p.1).max().unwrap();
+
+        let rows = (minr..=maxr).map(|r| {
+            (minc..=maxc)
+                .map(|col| match points.contains(&(r, col)) {
+                    true => c,
+                    false => '.',
+                })
+                .collect::<Vec<_>>()
+        });
+        self.draw(title, rows)
+    }
+}
+
+impl Drop for Animation {
+    fn drop(&mut self) {
+        if self.frame > 0 {
+            let _ = write!(io::stderr(), "{RESET}{SHOW_CURSOR}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let animation = Animation::new(0.0)
+            .with_color('#', Color::Red)
+            .with_color('o', Color::BrightCyan);
+        let frame = animation.render("t", ["#.o", "..."].map(|row| row.chars()));
+        assert_eq!(
+            frame,
+            "\x1b[?25l\x1b[2J\x1b[Ht [frame 0]\x1b[K\n\
+             \x1b[31m#\x1b[0m.\x1b[96mo\x1b[0m\x1b[K\n\
+             ...\x1b[K\n\
+             \x1b[J"
+        );
+
+        // runs of one class share an escape, later frames only go home
+        let mut animation = Animation::new(0.0).with_color('#', Color::Gray);
+        animation.frame = 1;
+        let frame = animation.render("t", ["##."].map(|row| row.chars()));
+        assert_eq!(
+            frame,
+            "\x1b[Ht [frame 1]\x1b[K\n\x1b[90m##\x1b[0m.\x1b[K\n\x1b[J"
+        );
+    }
+
+    #[test]
+    fn disabled() -> io::Result<()> {
+        let mut animation = Animation::default().with_color('#', Color::Red);
+        assert!(!animation.is_enabled());
+        animation.draw("t", ["#"].map(|row| row.chars()))?;
+        animation.draw_points("t", [(0, 0), (2, 3)], '#')?;
+        assert_eq!(animation.frames(), 0);
+        assert!(animation.last.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use std::error::Error;
+use std::str::FromStr;
+
+// Grid coordinates are (row, col), Up decreases the row and Left decreases the column
+pub type Point = (i64, i64);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn delta(&self) -> Point {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+}
+
+// accepts U/D/L/R, N/S/W/E and ^/v/</>
+impl FromStr for Direction {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "U" | "N" | "^" => Ok(Direction::Up),
+            "D" | "S" | "v" => Ok(Direction::Down),
+            "L" | "W" | "<" => Ok(Direction::Left),
+            "R" | "E" | ">" => Ok(Direction::Right),
+            _ => Err(format!("unknown direction: {s:?}").into()),
+        }
+    }
+}
+
+// Where a point lies relative to a polygon
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a.abs(),
+        _ => gcd(b, a % b),
+    }
+}
+
+// A simple closed polygon with integer vertices
+//
+// The closing edge from the last vertex back to the first is implied. Repeated
+// and collinear vertices are dropped, they don't change any of the measurements.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    pub fn new<I: IntoIterator<Item = Point>>(vertices: I) -> Self {
+        let mut vertices: Vec<Point> = vertices.into_iter().collect();
+        vertices.dedup();
+        while vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+
+        // remove middle points of straight runs, repeating until stable handles the wrap around
+        let collinear =
+            |a: Point, b: Point, c: Point| (b.0 - a.0) * (c.1 - b.1) == (b.1 - a.1) * (c.0 - b.0);
+        let mut changed = true;
+        while changed && vertices.len() > 2 {
+            changed = false;
+            let n = vertices.len();
+            let keep: Vec<_> = (0..n)
+                .map(|i| {
+                    !collinear(
+                        vertices[(i + n - 1) % n],
+                        vertices[i],
+                        vertices[(i + 1) % n],
+                    )
+                })
+                .collect();
+            if keep.iter().any(|k| !k) {
+                changed = true;
+                vertices = vertices
+                    .iter()
+                    .zip(&keep)
+                    .filter(|(_, k)| **k)
+                    .map(|(v, _)| *v)
+                    .collect();
+            }
+        }
+        Self { vertices }
+    }
+
+    // walk a list of (direction, length) instructions from `start`
+    pub fn from_instructions<I>(start: Point, instructions: I) -> Self
+    where
+        I: IntoIterator<Item = (Direction, i64)>,
+    {
+        let mut cur = start;
+        let mut vertices = vec![cur];
+        for (direction, length) in instructions {
+            let (dr, dc) = direction.delta();
+            cur = (cur.0 + dr * length, cur.1 + dc * length);
+            vertices.push(cur);
+        }
+        Self::new(vertices)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    // twice the signed area, positive when the vertices turn counter-clockwise with row as x and col as y
+    // https://en.wikipedia.org/wiki/Shoelace_formula
+    pub fn doubled_signed_area(&self) -> i64 {
+        self.edges().map(|(a, b)| a.0 * b.1 - a.1 * b.0).sum()
+    }
+
+    pub fn signed_area(&self) -> f64 {
+        self.doubled_signed_area() as f64 / 2.0
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    // the length of the boundary walked along axis aligned edges
+    pub fn perimeter(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| (a.0 - b.0).abs() + (a.1 - b.1).abs())
+            .sum()
+    }
+
+    // number of lattice points on the boundary
+    pub fn boundary_points(&self) -> i64 {
+        self.edges().map(|(a, b)| gcd(b.0 - a.0, b.1 - a.1)).sum()
+    }
+
+    // number of lattice points strictly inside
+    // https://en.wikipedia.org/wiki/Pick%27s_theorem, A = i + b/2 - 1
+    pub fn interior_points(&self) -> i64 {
+        (self.doubled_signed_area().abs() - self.boundary_points() + 2) / 2
+    }
+
+    // interior and boundary lattice points
+    pub fn lattice_points(&self) -> i64 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    // ray casting along the row, boundary points are detected first
+    pub fn locate(&self, p: Point) -> Location {
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            let cross = (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+            if cross == 0
+                && p.0 >= a.0.min(b.0)
+                && p.0 <= a.0.max(b.0)
+                && p.1 >= a.1.min(b.1)
+                && p.1 <= a.1.max(b.1)
+            {
+                return Location::Boundary;
+            }
+            // half-open rule on rows so vertices aren't counted twice
+            if (a.0 > p.0) != (b.0 > p.0) {
+                // column where the edge crosses row p.0, compared without division
+                let lhs = (p.1 - a.1) * (b.0 - a.0);
+                let rhs = (b.1 - a.1) * (p.0 - a.0);
+                if (b.0 > a.0 && lhs < rhs) || (b.0 < a.0 && lhs > rhs) {
+                    inside = !inside;
+                }
+            }
+        }
+        match inside {
+            true => Location::Inside,
+            false => Location::Outside,
+        }
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        self.locate(p) != Location::Outside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023 day 18 example dig plan
+    fn lagoon() -> Polygon {
+        let plan = "R6 D5 L2 D2 R2 D2 L5 U2 L1 U2 R2 U3 L2 U2";
+        Polygon::from_instructions(
+            (0, 0),
+            plan.split_whitespace()
+                .map(|s| (s[0..1].parse().unwrap(), s[1..].parse().unwrap())),
+        )
+    }
+
+    #[test]
+    fn pick() {
+        let poly = lagoon();
+        assert_eq!(poly.perimeter(), 38);
+        assert_eq!(poly.boundary_points(), 38);
+        assert_eq!(poly.area(), 42.0);
+        assert_eq!(poly.interior_points(), 24);
+        assert_eq!(poly.lattice_points(), 62);
+    }
+
+    #[test]
+    fn collinear_vertices_dropped() {
+        let square = Polygon::new([
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (2, 0),
+            (1, 0),
+            (0, 0),
+        ]);
+        assert_eq!(square.vertices(), &[(0, 0), (0, 2), (2, 2), (2, 0)]);
+        assert_eq!(square.interior_points(), 1);
+    }
+
+    #[test]
+    fn orientation() {
+        let ccw = Polygon::new([(0, 0), (0, 3), (-4, 0)]);
+        assert_eq!(ccw.signed_area(), 6.0);
+        assert_eq!(
+            Polygon::new(ccw.vertices().iter().rev().copied()).signed_area(),
+            -6.0
+        );
+        // diagonal edges
+        assert_eq!(ccw.boundary_points(), 3 + 4 + 1);
+    }
+
+    #[test]
+    fn locate() {
+        let poly = lagoon();
+        assert_eq!(poly.locate((1, 1)), Location::Inside);
+        assert_eq!(poly.locate((0, 3)), Location::Boundary);
+        assert_eq!(poly.locate((5, 4)), Location::Boundary);
+        assert_eq!(poly.locate((6, 0)), Location::Boundary);
+        assert_eq!(poly.locate((6, 5)), Location::Outside);
+        assert_eq!(poly.locate((3, -1)), Location::Outside);
+        let inside = (-1..10)
+            .flat_map(|r| (-1..10).map(move |c| (r, c)))
+            .filter(|p| poly.locate(*p) == Location::Inside)
+            .count();
+        assert_eq!(inside as i64, poly.interior_points());
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+
+// An undirected graph with labelled nodes and weighted edges
+//
+// Nodes are numbered in the order they're added, parallel edges add their weights
+// and self loops are ignored since they can never be part of a cut.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    labels: Vec<String>,
+    index: HashMap<String, usize>,
+    adjacent: Vec<BTreeMap<usize, u64>>,
+}
+
+// A partition of the nodes into two non-empty sides and the edges crossing between them
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cut {
+    pub weight: u64,
+    pub edges: Vec<(usize, usize)>,
+    pub partition: (Vec<usize>, Vec<usize>),
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the node for `label`, added if it's new
+    pub fn node(&mut self, label: &str) -> usize {
+        if let Some(n) = self.index.get(label) {
+            return *n;
+        }
+        self.labels.push(label.to_string());
+        self.adjacent.push(BTreeMap::new());
+        self.index.insert(label.to_string(), self.labels.len() - 1);
+        self.labels.len() - 1
+    }
+
+    pub fn find(&self, label: &str) -> Option<usize> {
+        self.index.get(label).copied()
+    }
+
+    pub fn label(&self, n: usize) -> &str {
+        &self.labels[n]
+    }
+
+    pub fn add_edge(&mut self, a: usize, b: usize, weight: u64) {
+        if a != b {
+            *self.adjacent[a].entry(b).or_default() += weight;
+            *self.adjacent[b].entry(a).or_default() += weight;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn neighbors(&self, n: usize) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.adjacent[n].iter().map(|(m, w)| (*m, *w))
+    }
+
+    // each edge once as (a, b, weight) with a < b
+    pub fn edges(&self) -> Vec<(usize, usize, u64)> {
+        (0..self.len())
+            .flat_map(|a| {
+                self.neighbors(a)
+                    .filter(move |(b, _)| a < *b)
+                    .map(move |(b, w)| (a, b, w))
+            })
+            .collect()
+    }
+
+    // the cut separating `side` from the rest of the nodes
+    pub fn cut(&self, side: &[usize]) -> Cut {
+        let mut inside = vec![false; self.len()];
+        for n in side {
+            inside[*n] = true;
+        }
+        let crossing: Vec<_> = self
+            .edges()
+            .into_iter()
+            .filter(|(a, b, _)| inside[*a] != inside[*b])
+            .collect();
+        let (mut a, mut b): (Vec<usize>, Vec<usize>) = (0..self.len()).partition(|n| inside[*n]);
+        if a.first() > b.first() {
+            std::mem::swap(&mut a, &mut b);
+        }
+        Cut {
+            weight: crossing.iter().map(|e| e.2).sum(),
+            edges: crossing.iter().map(|(a, b, _)| (*a, *b)).collect(),
+            partition: (a, b),
+        }
+    }
+
+    // global minimum cut, None with fewer than 2 nodes
+    // https://en.wikipedia.org/wiki/Stoer%E2%80%93Wagner_algorithm
+    pub fn stoer_wagner(&self) -> Option<Cut> {
+        if self.len() < 2 {
+            return None;
+        }
+        let side = stoer_wagner(self.len(), &self.edges());
+        Some(self.cut(&side))
+    }
+
+    // global minimum cut with high probability, more `trials` make a miss less likely
+    // https://en.wikipedia.org/wiki/Karger%27s_algorithm#Karger%E2%80%93Stein_algorithm
+    pub fn karger_stein(&self, trials: usize, seed: u64) -> Option<Cut> {
+        if self.len() < 2 {
+            return None;
+        }
+        let mut rng = XorShift::new(seed);
+        let members: Vec<Vec<usize>> = (0..self.len()).map(|n| vec![n]).collect();
+        (0..trials.max(1))
+            .map(|_| self.cut(&karger_stein(&members, &self.edges(), &mut rng)))
+            .min_by_key(|cut| cut.weight)
+    }
+
+    // maximum flow from s to t, every edge carries its weight in either direction
+    // https://en.wikipedia.org/wiki/Edmonds%E2%80%93Karp_algorithm
+    pub fn max_flow(&self, s: usize, t: usize) -> u64 {
+        self.max_flow_limit(s, t, u64::MAX)
+    }
+
+    // at least k edge disjoint paths join s and t when every edge has weight 1
+    pub fn disjoint_paths(&self, s: usize, t: usize, k: u64) -> bool {
+        self.max_flow_limit(s, t, k) >= k
+    }
+
+    // stops augmenting once the flow reaches `limit`
+    fn max_flow_limit(&self, s: usize, t: usize, limit: u64) -> u64 {
+        if s == t {
+            return limit;
+        }
+
+        // arc i and i ^ 1 are the two directions of one edge and each other's residual
+        let mut head = vec![];
+        let mut capacity = vec![];
+        let mut arcs = vec![vec![]; self.len()];
+        for (a, b, w) in self.edges() {
+            arcs[a].push(head.len());
+            head.push(b);
+            capacity.push(w);
+            arcs[b].push(head.len());
+            head.push(a);
+            capacity.push(w);
+        }
+
+        let mut flow = 0;
+        while flow < limit {
+            // shortest augmenting path, remembering the arc used to reach each node
+            let mut via = vec![None; self.len()];
+            let mut workq = VecDeque::from([s]);
+            while let Some(n) = workq.pop_front() {
+                for arc in &arcs[n] {
+                    let m = head[*arc];
+                    if capacity[*arc] > 0 && m != s && via[m].is_none() {
+                        via[m] = Some(*arc);
+                        workq.push_back(m);
+                    }
+                }
+            }
+            if via[t].is_none() {
+                break;
+            }
+
+            let mut path = vec![];
+            let mut n = t;
+            while let Some(arc) = via[n] {
+                path.push(arc);
+                n = head[arc ^ 1];
+            }
+            let bottleneck = path
+                .iter()
+                .map(|arc| capacity[*arc])
+                .min()
+                .unwrap()
+                .min(limit - flow);
+            for arc in path {
+                capacity[arc] -= bottleneck;
+                capacity[arc ^ 1] += bottleneck;
+            }
+            flow += bottleneck;
+        }
+        flow
+    }
+}
+
+// one side of a minimum cut of nodes 0..n, merging the last two nodes of each maximum adjacency ordering
+fn stoer_wagner(n: usize, edges: &[(usize, usize, u64)]) -> Vec<usize> {
+    let mut adjacent = vec![HashMap::<usize, u64>::new(); n];
+    for (a, b, w) in edges {
+        *adjacent[*a].entry(*b).or_default() += w;
+        *adjacent[*b].entry(*a).or_default() += w;
+    }
+    let mut members: Vec<Vec<usize>> = (0..n).map(|v| vec![v]).collect();
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut best: Option<(u64, Vec<usize>)> = None;
+
+    while active.len() > 1 {
+        // a phase: repeatedly add the node most tightly connected to those already added
+        let mut added = vec![false; n];
+        let mut weight = vec![0_u64; n];
+        let mut heap = BinaryHeap::from([(0, active[0])]);
+        let (mut s, mut t) = (active[0], active[0]);
+        let mut remaining = active.len();
+        // a node the others can't reach joins with weight 0
+        let next = |heap: &mut BinaryHeap<(u64, usize)>, added: &[bool]| {
+            heap.pop()
+                .or_else(|| active.iter().find(|v| !added[**v]).map(|v| (0, *v)))
+        };
+        while let Some((w, v)) = next(&mut heap, &added) {
+            if added[v] || w != weight[v] {
+                continue;
+            }
+            added[v] = true;
+            (s, t) = (t, v);
+            remaining -= 1;
+            if remaining == 0 {
+                break;
+            }
+            for (u, uw) in &adjacent[v] {
+                if !added[*u] {
+                    weight[*u] += uw;
+                    heap.push((weight[*u], *u));
+                }
+            }
+        }
+
+        // the cut of the phase separates the last node added from everything else
+        if best.as_ref().is_none_or(|(w, _)| weight[t] < *w) {
+            best = Some((weight[t], members[t].clone()));
+        }
+
+        // merge t into s
+        let t_adjacent = std::mem::take(&mut adjacent[t]);
+        for (u, w) in t_adjacent {
+            adjacent[u].remove(&t);
+            if u != s {
+                *adjacent[s].entry(u).or_default() += w;
+                *adjacent[u].entry(s).or_default() += w;
+            }
+        }
+        let t_members = std::mem::take(&mut members[t]);
+        members[s].extend(t_members);
+        active.retain(|v| *v != t);
+    }
+    best.unwrap().1
+}
+
+// one side of a small cut, contract to n/√2 nodes twice independently and keep the better result
+fn karger_stein(
+    members: &[Vec<usize>],
+    edges: &[(usize, usize, u64)],
+    rng: &mut XorShift,
+) -> Vec<usize> {
+    let exact = |members: &[Vec<usize>], edges: &[(usize, usize, u64)]| {
+        stoer_wagner(members.len(), edges)
+            .iter()
+            .flat_map(|v| members[*v].iter().copied())
+            .collect::<Vec<_>>()
+    };
+    let n = members.len();
+    if n <= 6 {
+        return exact(members, edges);
+    }
+    let target = 1 + (n as f64 / std::f64::consts::SQRT_2).ceil() as usize;
+    (0..2)
+        .map(|_| {
+            let (members, edges) = contract(members, edges, target, rng);
+            // more components than the target stop the contraction, recursing would never end
+            let side = match members.len() < n {
+                true => karger_stein(&members, &edges, rng),
+                false => exact(&members, &edges),
+            };
+            let weight = cut_weight(&side, &members, &edges);
+            (weight, side)
+        })
+        .min_by_key(|(weight, _)| *weight)
+        .unwrap()
+        .1
+}
+
+// randomly contract edges, heavier ones first on average, until `target` nodes remain
+#[allow(clippy::type_complexity)]
+fn contract(
+    members: &[Vec<usize>],
+    edges: &[(usize, usize, u64)],
+    target: usize,
+    rng: &mut XorShift,
+) -> (Vec<Vec<usize>>, Vec<(usize, usize, u64)>) {
+    // exponential keys pick a random contraction order weighted by edge weight
+    let mut order: Vec<_> = edges
+        .iter()
+        .map(|e| (-rng.next_f64().ln() / e.2 as f64, *e))
+        .collect();
+    order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut parent: Vec<usize> = (0..members.len()).collect();
+    fn root(parent: &mut [usize], v: usize) -> usize {
+        let mut r = v;
+        while parent[r] != r {
+            r = parent[r];
+        }
+        parent[v] = r;
+        r
+    }
+    let mut count = members.len();
+    for (_, (a, b, _)) in order {
+        if count <= target {
+            break;
+        }
+        let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+        if ra != rb {
+            parent[rb] = ra;
+            count -= 1;
+        }
+    }
+
+    // renumber the merged nodes and combine their edges
+    let mut renumber = HashMap::new();
+    let mut merged: Vec<Vec<usize>> = vec![];
+    for (v, m) in members.iter().enumerate() {
+        let r = root(&mut parent, v);
+        let i = *renumber.entry(r).or_insert_with(|| {
+            merged.push(vec![]);
+            merged.len() - 1
+        });
+        merged[i].extend(m);
+    }
+    let mut weights: BTreeMap<(usize, usize), u64> = BTreeMap::new();
+    for (a, b, w) in edges {
+        let (ra, rb) = (
+            renumber[&root(&mut parent, *a)],
+            renumber[&root(&mut parent, *b)],
+        );
+        if ra != rb {
+            *weights.entry((ra.min(rb), ra.max(rb))).or_default() += w;
+        }
+    }
+    (
+        merged,
+        weights.into_iter().map(|((a, b), w)| (a, b, w)).collect(),
+    )
+}
+
+fn cut_weight(side: &[usize], members: &[Vec<usize>], edges: &[(usize, usize, u64)]) -> u64 {
+    let inside: std::collections::HashSet<_> = side.iter().collect();
+    let contains = |v: usize| inside.contains(&members[v][0]);
+    edges
+        .iter()
+        .filter(|(a, b, _)| contains(*a) != contains(*b))
+        .map(|e| e.2)
+        .sum()
+}
+
+// a small seeded generator so randomized results are repeatable
+// https://en.wikipedia.org/wiki/Xorshift
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    // uniform in (0, 1]
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 11) + 1) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two squares with diagonals joined by a single light edge and a heavier one
+    fn barbell() -> Graph {
+        let mut g = Graph::new();
+        for (a, b, w) in [
+            ("a", "b", 3),
+            ("b", "c", 3),
+            ("c", "d", 3),
+            ("d", "a", 3),
+            ("a", "c", 3),
+            ("w", "x", 3),
+            ("x", "y", 3),
+            ("y", "z", 3),
+            ("z", "w", 3),
+            ("w", "y", 3),
+            ("c", "w", 1),
+            ("d", "z", 2),
+        ] {
+            let (a, b) = (g.node(a), g.node(b));
+            g.add_edge(a, b, w);
+        }
+        g
+    }
+
+    #[test]
+    fn min_cut() {
+        let g = barbell();
+        let cut = g.stoer_wagner().unwrap();
+        assert_eq!(cut.weight, 3);
+        assert_eq!(cut.partition, (vec![0, 1, 2, 3], vec![4, 5, 6, 7]));
+        let mut labels: Vec<_> = cut
+            .edges
+            .iter()
+            .map(|(a, b)| (g.label(*a), g.label(*b)))
+            .collect();
+        labels.sort();
+        assert_eq!(labels, [("c", "w"), ("d", "z")]);
+        assert_eq!(g.karger_stein(10, 1), Some(cut));
+        assert_eq!(Graph::new().stoer_wagner(), None);
+    }
+
+    #[test]
+    fn flow() {
+        let g = barbell();
+        let (a, c, y) = (
+            g.find("a").unwrap(),
+            g.find("c").unwrap(),
+            g.find("y").unwrap(),
+        );
+        assert_eq!(g.max_flow(a, y), 3);
+        assert_eq!(g.max_flow(a, c), 9);
+
+        let mut ring = Graph::new();
+        for i in 0..6 {
+            let (a, b) = (
+                ring.node(&i.to_string()),
+                ring.node(&((i + 1) % 6).to_string()),
+            );
+            ring.add_edge(a, b, 1);
+        }
+        assert!(ring.disjoint_paths(0, 3, 2));
+        assert!(!ring.disjoint_paths(0, 3, 3));
+
+        // two separate pieces have nothing to cut
+        let mut apart = ring.clone();
+        let (x, y) = (apart.node("x"), apart.node("y"));
+        apart.add_edge(x, y, 5);
+        assert_eq!(apart.stoer_wagner().unwrap().weight, 0);
+        assert_eq!(apart.max_flow(0, x), 0);
+    }
+
+    #[test]
+    fn disconnected() {
+        // too many components for contraction to reach its target
+        let mut scattered = Graph::new();
+        for i in 0..10 {
+            scattered.node(&i.to_string());
+        }
+        assert_eq!(scattered.stoer_wagner().unwrap().weight, 0);
+        assert_eq!(scattered.karger_stein(1, 1).unwrap().weight, 0);
+
+        // pairs joined by heavy edges, contraction stops with one node per pair
+        let mut pairs = scattered.clone();
+        for i in (0..10).step_by(2) {
+            pairs.add_edge(i, i + 1, 7);
+        }
+        let cut = pairs.karger_stein(3, 2).unwrap();
+        assert_eq!(cut.weight, 0);
+        assert!(cut.edges.is_empty());
+        assert_eq!(cut.partition.0.len() + cut.partition.1.len(), 10);
+    }
+}
@@ -8,6 +8,14 @@ use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+pub mod animate;
+pub mod geometry;
+pub mod graph;
+pub mod linear;
+pub mod picture;
+pub mod render;
+pub mod rotation;
+
 // https://doc.rust-lang.org/stable/rust-by-example/std_misc/file/read_lines.html
 //
 // The output is wrapped in a Result to allow matching on errors
@@ -144,9 +152,32 @@ pub fn get_args() -> ArgMatches {
         )
         .arg(
             Arg::new("time")
-            .short('t')
-            .help("Show runtime")
-            .action(clap::ArgAction::SetTrue)
+                .short('t')
+                .help("Show runtime")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Show solution details, like the steps taken, before the answers")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("animate")
+                .long("animate")
+                .value_name("fps")
+                .help("Animate grid simulations in the terminal (default 10 fps)")
+                .num_args(0..=1)
+                .default_missing_value("10")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("dump-image")
+                .long("dump-image")
+                .value_name("DIR")
+                .help("Write debug images (PNG/PPM/SVG) and graphs (DOT) into DIR")
+                .value_parser(value_parser!(PathBuf)),
         );
     app.get_matches_from(env::args().collect::<Vec<String>>())
 }
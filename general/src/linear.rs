@@ -0,0 +1,237 @@
+use num::{BigInt, BigRational, Signed, Zero};
+use std::error::Error;
+
+// The outcome of reducing a system of linear equations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Solution {
+    // exactly one solution
+    Unique(Vec<BigRational>),
+
+    // infinitely many solutions, `particular` sets every free variable to zero
+    Underdetermined {
+        particular: Vec<BigRational>,
+        free: Vec<usize>,
+    },
+
+    // no solution, some row reduced to 0 = c with c != 0
+    Inconsistent,
+}
+
+// Ax = b over exact rationals solved by Gauss-Jordan elimination
+#[derive(Clone, Debug, Default)]
+pub struct LinearSystem {
+    nvars: usize,
+    rows: Vec<Vec<BigRational>>,
+}
+
+impl LinearSystem {
+    pub fn new(nvars: usize) -> Self {
+        Self {
+            nvars,
+            rows: vec![],
+        }
+    }
+
+    // one equation: coefficients . x = rhs
+    pub fn push<T: Clone + Into<BigInt>>(
+        &mut self,
+        coefficients: &[T],
+        rhs: T,
+    ) -> Result<(), Box<dyn Error>> {
+        if coefficients.len() != self.nvars {
+            return Err(format!(
+                "expected {} coefficients, got {}",
+                self.nvars,
+                coefficients.len()
+            )
+            .into());
+        }
+        let ratio = |v: &T| BigRational::from_integer(v.clone().into());
+        let mut row: Vec<_> = coefficients.iter().map(ratio).collect();
+        row.push(ratio(&rhs));
+        self.rows.push(row);
+        Ok(())
+    }
+
+    // a row major coefficient matrix and the right hand side
+    pub fn from_rows<T: Clone + Into<BigInt>>(
+        a: &[Vec<T>],
+        b: &[T],
+    ) -> Result<Self, Box<dyn Error>> {
+        if a.len() != b.len() {
+            return Err(format!("{} equations but {} right hand sides", a.len(), b.len()).into());
+        }
+        let mut system = Self::new(a.first().map(|r| r.len()).unwrap_or(0));
+        for (row, rhs) in a.iter().zip(b) {
+            system.push(row, rhs.clone())?;
+        }
+        Ok(system)
+    }
+
+    pub fn nvars(&self) -> usize {
+        self.nvars
+    }
+
+    pub fn nequations(&self) -> usize {
+        self.rows.len()
+    }
+
+    // rank of the coefficient matrix
+    pub fn rank(&self) -> usize {
+        self.reduce().1.len()
+    }
+
+    // reduced row echelon form and the pivot column of each leading row
+    fn reduce(&self) -> (Vec<Vec<BigRational>>, Vec<usize>) {
+        let mut m = self.rows.clone();
+        let mut pivots = vec![];
+        let mut r = 0;
+        for c in 0..self.nvars {
+            let Some(p) = (r..m.len()).find(|i| !m[*i][c].is_zero()) else {
+                continue;
+            };
+            m.swap(r, p);
+
+            let pivot = m[r][c].clone();
+            for v in m[r].iter_mut() {
+                *v /= pivot.clone();
+            }
+            let pivot_row = m[r].clone();
+            for (_, row) in m.iter_mut().enumerate().filter(|(i, _)| *i != r) {
+                if row[c].is_zero() {
+                    continue;
+                }
+                let factor = row[c].clone();
+                for (v, p) in row[c..].iter_mut().zip(&pivot_row[c..]) {
+                    *v -= factor.clone() * p;
+                }
+            }
+
+            pivots.push(c);
+            r += 1;
+            if r == m.len() {
+                break;
+            }
+        }
+        (m, pivots)
+    }
+
+    pub fn solve(&self) -> Solution {
+        let (m, pivots) = self.reduce();
+
+        // rows below the pivots are all zero coefficients, their rhs must be zero too
+        if m[pivots.len()..]
+            .iter()
+            .any(|row| !row[self.nvars].is_zero())
+        {
+            return Solution::Inconsistent;
+        }
+
+        let mut values = vec![BigRational::zero(); self.nvars];
+        for (row, c) in pivots.iter().enumerate() {
+            values[*c] = m[row][self.nvars].clone();
+        }
+
+        match pivots.len() == self.nvars {
+            true => Solution::Unique(values),
+            false => Solution::Underdetermined {
+                particular: values,
+                free: (0..self.nvars).filter(|c| !pivots.contains(c)).collect(),
+            },
+        }
+    }
+}
+
+impl Solution {
+    pub fn is_consistent(&self) -> bool {
+        *self != Solution::Inconsistent
+    }
+
+    pub fn unique(&self) -> Option<&[BigRational]> {
+        match self {
+            Solution::Unique(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    // the unique solution when every value is an integer
+    pub fn integers(&self) -> Option<Vec<BigInt>> {
+        self.unique()?
+            .iter()
+            .map(|v| match v.is_integer() {
+                true => Some(v.to_integer()),
+                false => None,
+            })
+            .collect()
+    }
+
+    // the unique solution when every value is a non-negative integer
+    pub fn naturals(&self) -> Option<Vec<BigInt>> {
+        self.integers()
+            .filter(|v| v.iter().all(|n| !n.is_negative()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(numer.into(), denom.into())
+    }
+
+    #[test]
+    fn unique() -> Result<(), Box<dyn Error>> {
+        // 2024 day 13 example, first machine
+        let system = LinearSystem::from_rows(&[vec![94, 22], vec![34, 67]], &[8400, 5400])?;
+        let solution = system.solve();
+        assert_eq!(system.rank(), 2);
+        assert_eq!(
+            solution.integers(),
+            Some(vec![BigInt::from(80), BigInt::from(40)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fractional() -> Result<(), Box<dyn Error>> {
+        let system = LinearSystem::from_rows(&[vec![2, 0], vec![0, 3]], &[1, 3])?;
+        let solution = system.solve();
+        assert_eq!(solution.unique(), Some(&[ratio(1, 2), ratio(1, 1)][..]));
+        assert_eq!(solution.integers(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn rank_deficient() -> Result<(), Box<dyn Error>> {
+        let system = LinearSystem::from_rows(&[vec![1, 2, 3], vec![2, 4, 6]], &[6, 12])?;
+        assert_eq!(system.rank(), 1);
+        assert_eq!(
+            system.solve(),
+            Solution::Underdetermined {
+                particular: vec![ratio(6, 1), ratio(0, 1), ratio(0, 1)],
+                free: vec![1, 2],
+            }
+        );
+
+        let system = LinearSystem::from_rows(&[vec![1, 2], vec![2, 4]], &[1, 3])?;
+        assert!(!system.solve().is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn overdetermined() -> Result<(), Box<dyn Error>> {
+        let mut system = LinearSystem::new(2);
+        system.push(&[1, 1], 3)?;
+        system.push(&[1, -1], 1)?;
+        system.push(&[2, 1], 5)?;
+        assert_eq!(
+            system.solve().naturals(),
+            Some(vec![BigInt::from(2), BigInt::from(1)])
+        );
+        system.push(&[0, 1], 7)?;
+        assert_eq!(system.solve(), Solution::Inconsistent);
+        assert!(system.push(&[1], 1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::render::{Dumper, BLACK, GREEN};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+
+// Measurements of one frame of a point swarm simulation
+//
+// entropy: Shannon entropy (bits) of the points binned over their bounding box
+// largest_component: size of the largest 8-connected group of points
+// variance: sum of the row and column variances
+// score: combined rank, only meaningful relative to the other frames ranked with it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameScore {
+    pub step: usize,
+    pub entropy: f64,
+    pub largest_component: usize,
+    pub variance: f64,
+    pub score: f64,
+}
+
+// Ranks simulation frames by how much they look like a picture
+//
+// Pictures are ordered (low entropy), drawn with connected strokes (large component)
+// and usually tighter than the noise around them (low variance). Each measurement is
+// standardized over all the frames seen so no single scale dominates the combined score.
+#[derive(Clone, Debug)]
+pub struct PictureDetector {
+    top_k: usize,
+    bins: usize,
+}
+
+impl Default for PictureDetector {
+    fn default() -> Self {
+        Self { top_k: 5, bins: 16 }
+    }
+}
+
+impl PictureDetector {
+    pub fn new(top_k: usize) -> Self {
+        Self {
+            top_k: top_k.max(1),
+            ..Default::default()
+        }
+    }
+
+    // number of bins per axis used for the entropy measurement
+    pub fn with_bins(mut self, bins: usize) -> Self {
+        self.bins = bins.max(1);
+        self
+    }
+
+    // measure a single frame, points are (row, col)
+    pub fn measure(&self, step: usize, points: &[(i64, i64)]) -> FrameScore {
+        FrameScore {
+            step,
+            entropy: entropy(points, self.bins),
+            largest_component: largest_component(points),
+            variance: variance(points),
+            score: 0.0,
+        }
+    }
+
+    // measure `frame(step)` for every step and return the top-k frames, best first
+    pub fn rank<I, F>(&self, steps: I, mut frame: F) -> Vec<FrameScore>
+    where
+        I: IntoIterator<Item = usize>,
+        F: FnMut(usize) -> Vec<(i64, i64)>,
+    {
+        let mut scores: Vec<_> = steps
+            .into_iter()
+            .map(|t| self.measure(t, &frame(t)))
+            .collect();
+
+        let entropy = standardize(scores.iter().map(|s| s.entropy));
+        let component = standardize(scores.iter().map(|s| s.largest_component as f64));
+        let variance = standardize(scores.iter().map(|s| s.variance));
+        for (i, s) in scores.iter_mut().enumerate() {
+            s.score = component[i] - entropy[i] - variance[i];
+        }
+
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.step.cmp(&b.step)));
+        scores.truncate(self.top_k);
+        scores
+    }
+
+    // write the ranked frames as "{prefix}-{step}.png" images, `frame` re-creates them
+    pub fn dump<F>(
+        &self,
+        dumper: &Dumper,
+        prefix: &str,
+        ranked: &[FrameScore],
+        mut frame: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(usize) -> Vec<(i64, i64)>,
+    {
+        if !dumper.is_enabled() {
+            return Ok(());
+        }
+        for s in ranked {
+            dumper.points(
+                &format!("{prefix}-{}.png", s.step),
+                frame(s.step),
+                GREEN,
+                BLACK,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// z-scores, a constant series is all zeros
+fn standardize<I: Iterator<Item = f64>>(values: I) -> Vec<f64> {
+    let values: Vec<_> = values.collect();
+    let n = values.len().max(1) as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let sd = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    values
+        .iter()
+        .map(|v| match sd > 0.0 {
+            true => (v - mean) / sd,
+            false => 0.0,
+        })
+        .collect()
+}
+
+fn entropy(points: &[(i64, i64)], bins: usize) -> f64 {
+    if points.is_empty() {
+        return 0.0;
+    }
+    let minr = points.iter().map(|p| p.0).min().unwrap();
+    let maxr = points.iter().map(|p| p.0).max().unwrap();
+    let minc = points.iter().map(|p| p.1).min().unwrap();
+    let maxc = points.iter().map(|p| p.1).max().unwrap();
+
+    let bin = |v: i64, lo: i64, hi: i64| {
+        ((v - lo) as i128 * bins as i128 / (hi - lo + 1) as i128) as usize
+    };
+    let mut counts = vec![0_usize; bins * bins];
+    for p in points {
+        counts[bin(p.0, minr, maxr) * bins + bin(p.1, minc, maxc)] += 1;
+    }
+
+    let n = points.len() as f64;
+    counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f64 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn largest_component(points: &[(i64, i64)]) -> usize {
+    if points.is_empty() {
+        return 0;
+    }
+    let minr = points.iter().map(|p| p.0).min().unwrap();
+    let maxr = points.iter().map(|p| p.0).max().unwrap();
+    let minc = points.iter().map(|p| p.1).min().unwrap();
+    let maxc = points.iter().map(|p| p.1).max().unwrap();
+    let (nrows, ncols) = ((maxr - minr + 1) as u128, (maxc - minc + 1) as u128);
+
+    // sparse frames (e.g. stars far apart) use a set, otherwise a padded bitmap is much faster
+    if nrows * ncols > 64 * points.len().max(1024) as u128 {
+        return largest_component_sparse(points);
+    }
+    let (nrows, ncols) = (nrows as usize + 2, ncols as usize + 2);
+    let mut unseen = vec![false; nrows * ncols];
+    for p in points {
+        unseen[(p.0 - minr + 1) as usize * ncols + (p.1 - minc + 1) as usize] = true;
+    }
+
+    let mut largest = 0;
+    let mut workq = vec![];
+    for start in 0..unseen.len() {
+        if !unseen[start] {
+            continue;
+        }
+        unseen[start] = false;
+        workq.push(start);
+        let mut size = 0;
+        while let Some(i) = workq.pop() {
+            size += 1;
+            for n in [
+                i - ncols - 1,
+                i - ncols,
+                i - ncols + 1,
+                i - 1,
+                i + 1,
+                i + ncols - 1,
+                i + ncols,
+                i + ncols + 1,
+            ] {
+                if unseen[n] {
+                    unseen[n] = false;
+                    workq.push(n);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
+
+fn largest_component_sparse(points: &[(i64, i64)]) -> usize {
+    let mut unseen: HashSet<_> = points.iter().copied().collect();
+    let mut largest = 0;
+    while let Some(&start) = unseen.iter().next() {
+        unseen.remove(&start);
+        let mut size = 0;
+        let mut workq = VecDeque::from([start]);
+        while let Some((r, c)) = workq.pop_front() {
+            size += 1;
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    if unseen.remove(&(r + dr, c + dc)) {
+                        workq.push_back((r + dr, c + dc));
+                    }
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
+
+fn variance(points: &[(i64, i64)]) -> f64 {
+    let n = points.len().max(1) as f64;
+    let mr = points.iter().map(|p| p.0 as f64).sum::<f64>() / n;
+    let mc = points.iter().map(|p| p.1 as f64).sum::<f64>() / n;
+    points
+        .iter()
+        .map(|p| (p.0 as f64 - mr).powi(2) + (p.1 as f64 - mc).powi(2))
+        .sum::<f64>()
+        / n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components() {
+        let points = [(0, 0), (1, 1), (2, 2), (5, 5), (5, 6)];
+        assert_eq!(largest_component(&points), 3);
+        assert_eq!(largest_component_sparse(&points), 3);
+        assert_eq!(largest_component(&[]), 0);
+    }
+
+    #[test]
+    fn entropy_bounds() {
+        // every point in its own bin is maximal, all points in one bin is zero
+        let spread: Vec<_> = (0..4).flat_map(|r| (0..4).map(move |c| (r, c))).collect();
+        assert_eq!(entropy(&spread, 4), 4.0);
+        assert_eq!(entropy(&[(3, 3), (3, 3)], 4), 0.0);
+    }
+
+    #[test]
+    fn finds_the_square() {
+        // a scattered diagonal except at step 7 where the points form a filled square
+        let frame = |t: usize| -> Vec<(i64, i64)> {
+            match t {
+                7 => (0..5).flat_map(|r| (0..5).map(move |c| (r, c))).collect(),
+                _ => (0..25)
+                    .map(|i| ((i * 7 + t as i64) % 50, (i * 13) % 50))
+                    .collect(),
+            }
+        };
+        let ranked = PictureDetector::new(3).rank(0..20, frame);
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].step, 7);
+        assert_eq!(ranked[0].largest_component, 25);
+    }
+}
@@ -0,0 +1,485 @@
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+pub const GRAY: Rgb = [128, 128, 128];
+pub const RED: Rgb = [220, 50, 47];
+pub const GREEN: Rgb = [64, 160, 43];
+pub const BLUE: Rgb = [38, 139, 210];
+pub const YELLOW: Rgb = [230, 190, 0];
+pub const MAGENTA: Rgb = [211, 54, 130];
+pub const CYAN: Rgb = [42, 161, 152];
+pub const ORANGE: Rgb = [255, 128, 0];
+
+// Maps grid cells to pixel colors, cells not in the palette get the default color
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: HashMap<char, Rgb>,
+    default: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: HashMap::new(),
+            default: BLACK,
+        }
+    }
+}
+
+impl Palette {
+    pub fn new(default: Rgb) -> Self {
+        Self {
+            colors: HashMap::new(),
+            default,
+        }
+    }
+
+    pub fn with(mut self, c: char, color: Rgb) -> Self {
+        self.colors.insert(c, color);
+        self
+    }
+
+    pub fn color(&self, c: char) -> Rgb {
+        *self.colors.get(&c).unwrap_or(&self.default)
+    }
+}
+
+// An RGB raster image, each grid cell becomes a `scale` x `scale` block of pixels
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    // paint the `scale` x `scale` block for cell (row, col)
+    fn fill_cell(&mut self, row: usize, col: usize, scale: usize, color: Rgb) {
+        for y in row * scale..(row + 1) * scale {
+            for x in col * scale..(col + 1) * scale {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    // rows of cells colored by the palette, ragged rows are padded with the default color
+    pub fn from_grid<I, R>(rows: I, palette: &Palette, scale: usize) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = char>,
+    {
+        let scale = scale.max(1);
+        let cells: Vec<Vec<char>> = rows.into_iter().map(|r| r.into_iter().collect()).collect();
+        let ncols = cells.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut image = Self::new(ncols * scale, cells.len() * scale, palette.default);
+        for (i, row) in cells.iter().enumerate() {
+            for (j, c) in row.iter().enumerate() {
+                image.fill_cell(i, j, scale, palette.color(*c));
+            }
+        }
+        image
+    }
+
+    // (row, col) points drawn in `color` over the bounding box of the points
+    pub fn from_points<I>(points: I, color: Rgb, background: Rgb, scale: usize) -> Self
+    where
+        I: IntoIterator<Item = (i64, i64)>,
+    {
+        let scale = scale.max(1);
+        let points: Vec<_> = points.into_iter().collect();
+        if points.is_empty() {
+            return Self::new(0, 0, background);
+        }
+        let minr = points.iter().map(|p| p.0).min().unwrap();
+        let maxr = points.iter().map(|p| p.0).max().unwrap();
+        let minc = points.iter().map(|p| p.1).min().unwrap();
+        let maxc = points.iter().map(|p| p.1).max().unwrap();
+        let (nrows, ncols) = ((maxr - minr + 1) as usize, (maxc - minc + 1) as usize);
+
+        let mut image = Self::new(ncols * scale, nrows * scale, background);
+        for (r, c) in points {
+            image.fill_cell((r - minr) as usize, (c - minc) as usize, scale, color);
+        }
+        image
+    }
+
+    // binary PPM (P6)
+    pub fn write_ppm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for p in &self.pixels {
+            w.write_all(p)?;
+        }
+        w.flush()
+    }
+
+    // 8-bit RGB PNG using uncompressed (stored) deflate blocks
+    pub fn write_png<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let (width, height) = match (u32::try_from(self.width), u32::try_from(self.height)) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("can't encode a {}x{} PNG", self.width, self.height),
+                ))
+            }
+        };
+
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut ihdr = vec![];
+        ihdr.extend(width.to_be_bytes());
+        ihdr.extend(height.to_be_bytes());
+        // bit depth 8, color type 2 (RGB), default compression, filter and no interlace
+        ihdr.extend([8, 2, 0, 0, 0]);
+        png_chunk(&mut w, b"IHDR", &ihdr)?;
+
+        // each scanline is prefixed with filter type 0 (None)
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0);
+            for p in row {
+                raw.extend(p);
+            }
+        }
+        png_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+        png_chunk(&mut w, b"IEND", &[])?;
+        w.flush()
+    }
+
+    // writes a PNG or PPM based on the file extension (PNG when unknown)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let w = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ppm") => self.write_ppm(w),
+            _ => self.write_png(w),
+        }
+    }
+}
+
+fn png_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    w.write_all(&crc.to_be_bytes())
+}
+
+// https://www.w3.org/TR/png/#D-CRCAppendix
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xffffffff_u32;
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => 0xedb88320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+// https://www.rfc-editor.org/rfc/rfc1950 with rfc1951 "stored" blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend(((b << 16) | a).to_be_bytes());
+    out
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[derive(Clone, Debug)]
+struct Line {
+    points: Vec<(i64, i64)>,
+    color: Rgb,
+    closed: bool,
+}
+
+// Polylines over (row, col) points written as an SVG, x is the column and y the row
+#[derive(Clone, Debug)]
+pub struct Svg {
+    scale: f64,
+    stroke_width: f64,
+    background: Option<Rgb>,
+    lines: Vec<Line>,
+}
+
+impl Default for Svg {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            stroke_width: 1.0,
+            background: Some(WHITE),
+            lines: vec![],
+        }
+    }
+}
+
+impl Svg {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_stroke_width(mut self, width: f64) -> Self {
+        self.stroke_width = width;
+        self
+    }
+
+    pub fn with_background(mut self, background: Option<Rgb>) -> Self {
+        self.background = background;
+        self
+    }
+
+    // an open path through the points
+    pub fn polyline(&mut self, points: &[(i64, i64)], color: Rgb) -> &mut Self {
+        self.lines.push(Line {
+            points: points.to_vec(),
+            color,
+            closed: false,
+        });
+        self
+    }
+
+    // a closed path through the points
+    pub fn polygon(&mut self, points: &[(i64, i64)], color: Rgb) -> &mut Self {
+        self.lines.push(Line {
+            points: points.to_vec(),
+            color,
+            closed: true,
+        });
+        self
+    }
+
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let points = || self.lines.iter().flat_map(|line| line.points.iter());
+        let minr = points().map(|p| p.0).min().unwrap_or(0);
+        let maxr = points().map(|p| p.0).max().unwrap_or(0);
+        let minc = points().map(|p| p.1).min().unwrap_or(0);
+        let maxc = points().map(|p| p.1).max().unwrap_or(0);
+
+        // keep the stroke inside the viewBox
+        let pad = self.stroke_width;
+        let width = (maxc - minc) as f64 * self.scale + 2.0 * pad;
+        let height = (maxr - minr) as f64 * self.scale + 2.0 * pad;
+        let xy = |p: &(i64, i64)| {
+            (
+                (p.1 - minc) as f64 * self.scale + pad,
+                (p.0 - minr) as f64 * self.scale + pad,
+            )
+        };
+
+        writeln!(
+            w,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.2}" height="{height:.2}" viewBox="0 0 {width:.2} {height:.2}">"#
+        )?;
+        if let Some(background) = self.background {
+            writeln!(
+                w,
+                r#"  <rect width="100%" height="100%" fill="{}"/>"#,
+                hex(background)
+            )?;
+        }
+        for line in &self.lines {
+            let coords = line
+                .points
+                .iter()
+                .map(|p| {
+                    let (x, y) = xy(p);
+                    format!("{x:.2},{y:.2}")
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                w,
+                r#"  <{} points="{coords}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"/>"#,
+                if line.closed { "polygon" } else { "polyline" },
+                hex(line.color),
+                self.stroke_width
+            )?;
+        }
+        writeln!(w, "</svg>")?;
+        w.flush()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+// Writes images into the "--dump-image DIR" directory
+//
+// A Dumper created by default() (or from_args() without --dump-image) is disabled
+// and every method is a no-op, so solvers can call it unconditionally
+#[derive(Clone, Debug, Default)]
+pub struct Dumper {
+    dir: Option<PathBuf>,
+    scale: usize,
+}
+
+impl Dumper {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: Some(dir.as_ref().to_path_buf()),
+            scale: 1,
+        }
+    }
+
+    pub fn from_args(args: &ArgMatches) -> Self {
+        match args.get_one::<PathBuf>("dump-image") {
+            Some(dir) => Self::new(dir),
+            None => Self::default(),
+        }
+    }
+
+    // pixels per grid cell
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    fn path(&self, name: &str) -> io::Result<Option<PathBuf>> {
+        match &self.dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                Ok(Some(dir.join(name)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // `name` is a file name in the dump directory, ".ppm" selects PPM otherwise PNG
+    pub fn grid<I, R>(&self, name: &str, rows: I, palette: &Palette) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = char>,
+    {
+        match self.path(name)? {
+            Some(path) => Image::from_grid(rows, palette, self.scale).save(path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn points<I>(&self, name: &str, points: I, color: Rgb, background: Rgb) -> io::Result<()>
+    where
+        I: IntoIterator<Item = (i64, i64)>,
+    {
+        match self.path(name)? {
+            Some(path) => Image::from_points(points, color, background, self.scale).save(path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn svg(&self, name: &str, svg: &Svg) -> io::Result<()> {
+        match self.path(name)? {
+            Some(path) => svg.save(path),
+            None => Ok(()),
+        }
+    }
+
+    // any text rendering, e.g. a Graphviz ".dot" file
+    pub fn text(&self, name: &str, contents: &str) -> io::Result<()> {
+        match self.path(name)? {
+            Some(path) => fs::write(path, contents),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn zlib_stored_blocks() {
+        let data = vec![7_u8; 70000];
+        let z = zlib_stored(&data);
+        // header + 2 block headers + data + adler32
+        assert_eq!(z.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(z[2], 0);
+        assert_eq!(z[2 + 5 + 0xffff], 1);
+    }
+
+    #[test]
+    fn grid_scaling() {
+        let palette = Palette::new(WHITE).with('#', RED);
+        let image = Image::from_grid(["#.", "."].iter().map(|s| s.chars()), &palette, 3);
+        assert_eq!((image.width(), image.height()), (6, 6));
+        assert_eq!(image.get(2, 2), RED);
+        assert_eq!(image.get(3, 0), WHITE);
+        assert_eq!(image.get(5, 5), WHITE);
+    }
+
+    #[test]
+    fn ppm_header() -> io::Result<()> {
+        let image = Image::from_points([(-1, 4), (0, 5)], GREEN, BLACK, 1);
+        let mut buf = vec![];
+        image.write_ppm(&mut buf)?;
+        assert!(buf.starts_with(b"P6\n2 2\n255\n"));
+        assert_eq!(buf.len(), 11 + 2 * 2 * 3);
+        Ok(())
+    }
+}
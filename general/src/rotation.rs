@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+
+pub type Vector = [i64; 3];
+
+// A proper rotation of integer 3d space, a signed permutation matrix with determinant 1
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Rotation([[i64; 3]; 3]);
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+
+    // a quarter turn counter-clockwise about the x axis
+    pub const QUARTER_X: Rotation = Rotation([[1, 0, 0], [0, 0, -1], [0, 1, 0]]);
+
+    // a quarter turn counter-clockwise about the z axis
+    pub const QUARTER_Z: Rotation = Rotation([[0, -1, 0], [1, 0, 0], [0, 0, 1]]);
+
+    // the 24 rotations of a cube, generated as the closure of two quarter turns
+    pub fn all() -> Vec<Rotation> {
+        let mut seen = HashSet::from([Self::IDENTITY]);
+        let mut all = vec![Self::IDENTITY];
+        let mut i = 0;
+        while i < all.len() {
+            for generator in [Self::QUARTER_X, Self::QUARTER_Z] {
+                let r = generator.compose(&all[i]);
+                if seen.insert(r) {
+                    all.push(r);
+                }
+            }
+            i += 1;
+        }
+        all
+    }
+
+    pub fn apply(&self, v: Vector) -> Vector {
+        let m = &self.0;
+        [0, 1, 2].map(|r| m[r][0] * v[0] + m[r][1] * v[1] + m[r][2] * v[2])
+    }
+
+    // the rotation applying `other` first and then `self`
+    pub fn compose(&self, other: &Rotation) -> Rotation {
+        let (a, b) = (&self.0, &other.0);
+        Rotation([0, 1, 2].map(|r| [0, 1, 2].map(|c| (0..3).map(|k| a[r][k] * b[k][c]).sum())))
+    }
+
+    // orthogonal, so the transpose
+    pub fn inverse(&self) -> Rotation {
+        let m = &self.0;
+        Rotation([0, 1, 2].map(|r| [0, 1, 2].map(|c| m[c][r])))
+    }
+
+    pub fn determinant(&self) -> i64 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group() {
+        let all = Rotation::all();
+        assert_eq!(all.len(), 24);
+        assert_eq!(all[0], Rotation::IDENTITY);
+        assert!(all.iter().all(|r| r.determinant() == 1));
+        for a in &all {
+            assert_eq!(a.compose(&a.inverse()), Rotation::IDENTITY);
+            for b in &all {
+                assert!(all.contains(&a.compose(b)));
+            }
+        }
+
+        // every way to point x along an axis, each with 4 choices of up
+        let images: HashSet<_> = all
+            .iter()
+            .map(|r| (r.apply([1, 0, 0]), r.apply([0, 1, 0])))
+            .collect();
+        assert_eq!(images.len(), 24);
+    }
+
+    #[test]
+    fn quarter_turns() {
+        assert_eq!(Rotation::QUARTER_Z.apply([1, 2, 3]), [-2, 1, 3]);
+        assert_eq!(Rotation::QUARTER_X.apply([1, 2, 3]), [1, -3, 2]);
+        let half = Rotation::QUARTER_Z.compose(&Rotation::QUARTER_Z);
+        assert_eq!(half.apply([1, 2, 3]), [-1, -2, 3]);
+        assert_eq!(half.compose(&half), Rotation::IDENTITY);
+    }
+}